@@ -0,0 +1,24 @@
+//! 生成 C 头文件到 `OUT_DIR/winclean_rules.h`
+//!
+//! 构建不修改源码树；启用 `update-header` 特性时同时更新随仓库提交的 `include/winclean_rules.h`。
+//! 提交的头文件是否过期由 `header_is_up_to_date` 测试检查。
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    let dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("winclean_rules.h");
+    let config = cbindgen::Config::from_file(dir.join("cbindgen.toml")).expect("读取 cbindgen.toml 失败");
+    cbindgen::generate_with_config(&dir, config)
+        .expect("生成 C 头文件失败")
+        .write_to_file(&out);
+
+    if env::var_os("CARGO_FEATURE_UPDATE_HEADER").is_some() {
+        fs::copy(&out, dir.join("include/winclean_rules.h")).expect("更新 include/winclean_rules.h 失败");
+    }
+
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
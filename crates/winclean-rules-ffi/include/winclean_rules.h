@@ -0,0 +1,278 @@
+/* WinClean Rules - 读取规则包的 C 接口 */
+
+#ifndef WINCLEAN_RULES_H
+#define WINCLEAN_RULES_H
+
+/* 由 cbindgen 根据 src/lib.rs 生成，请勿手动修改 */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// Ed25519 公钥长度
+#define WCR_PUBLIC_KEY_LEN 32
+
+// 包头摘要长度
+#define WCR_DIGEST_LEN 32
+
+// 调用结果
+typedef enum WcrStatus {
+  WCR_STATUS_OK = 0,
+  // 参数为空指针、不是 UTF-8 或公钥无效
+  WCR_STATUS_INVALID_ARGUMENT,
+  // 读取文件失败
+  WCR_STATUS_IO,
+  // 不是规则包
+  WCR_STATUS_NOT_PACKAGE,
+  // 格式版本不受支持
+  WCR_STATUS_UNSUPPORTED_VERSION,
+  // 文件不完整、摘要不符或无法解析
+  WCR_STATUS_CORRUPT,
+  // 超出读取限制
+  WCR_STATUS_LIMIT_EXCEEDED,
+  // 要求签名但规则包未签名
+  WCR_STATUS_UNSIGNED,
+  // 签名无效
+  WCR_STATUS_BAD_SIGNATURE,
+  // 内容序号早于上次接受的规则包
+  WCR_STATUS_ROLLBACK,
+  // 规则包已过期
+  WCR_STATUS_EXPIRED,
+} WcrStatus;
+
+// 签名状态
+typedef enum WcrSignatureStatus {
+  WCR_SIGNATURE_STATUS_UNSIGNED = 0,
+  // 已签名，未提供公钥校验
+  WCR_SIGNATURE_STATUS_UNCHECKED,
+  WCR_SIGNATURE_STATUS_VALID,
+  WCR_SIGNATURE_STATUS_INVALID,
+} WcrSignatureStatus;
+
+// 规则的字符串字段
+typedef enum WcrRuleField {
+  WCR_RULE_FIELD_ID = 0,
+  WCR_RULE_FIELD_NAME,
+  WCR_RULE_FIELD_RISK,
+  WCR_RULE_FIELD_UPDATE,
+  // 可选，未填写时为 NULL
+  WCR_RULE_FIELD_AUTHOR,
+  // 可选，未填写时为 NULL
+  WCR_RULE_FIELD_DESCRIPTION,
+  WCR_RULE_FIELD_CATEGORY,
+  WCR_RULE_FIELD_FILENAME,
+  // 原始 YAML 内容
+  WCR_RULE_FIELD_YAML,
+} WcrRuleField;
+
+// 注册表条目的字符串字段
+typedef enum WcrRegistryField {
+  WCR_REGISTRY_FIELD_PATH = 0,
+  WCR_REGISTRY_FIELD_KEY,
+  // 可选，未填写时为 NULL
+  WCR_REGISTRY_FIELD_VALUE,
+  // 可选，未填写时为 NULL
+  WCR_REGISTRY_FIELD_VALUE_DATA,
+  WCR_REGISTRY_FIELD_ACTION,
+} WcrRegistryField;
+
+// 已打开的规则包
+typedef struct WcrPackage WcrPackage;
+
+// 规则中的一条注册表条目
+typedef struct WcrRegistryEntry WcrRegistryEntry;
+
+// 规则包中的一条规则
+typedef struct WcrRule WcrRule;
+
+// 校验结果
+typedef struct WcrVerifyReport {
+  // 摘要完好，且提供公钥时签名有效
+  bool ok;
+  // 规则数据区长度与摘要是否相符
+  bool payload_intact;
+  // 数据帧缺失或摘要不符的规则数
+  size_t damaged;
+  enum WcrSignatureStatus signature;
+  uint64_t serial;
+  uint64_t created_at;
+  // 过期时间（Unix 时间戳，秒），永不过期时为 0
+  uint64_t expires_at;
+  // 包头摘要，与签名无关，可用于比对可复现构建
+  uint8_t header_digest[WCR_DIGEST_LEN];
+} WcrVerifyReport;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// 当前线程最近一次调用的错误信息（UTF-8），成功时为空字符串
+//
+// 返回的字符串在本线程下一次调用前有效。
+const char *wcr_last_error(void);
+
+// 打开规则包，校验摘要但不校验签名（用于本地调试）
+//
+// # Safety
+//
+// `path` 必须是以 NUL 结尾的 UTF-8 字符串，`out` 必须指向可写的指针。
+enum WcrStatus wcr_package_open(const char *path, struct WcrPackage **out);
+
+// 打开规则包，要求签名有效、未过期且内容序号不小于 `last_serial`
+//
+// `public_key` 为 32 字节的 Ed25519 公钥；`last_serial` 为上次接受的内容序号，没有时传 0。
+// 接受后应保存 `wcr_package_serial` 的结果，作为下次的 `last_serial`。
+//
+// # Safety
+//
+// `path` 必须是以 NUL 结尾的 UTF-8 字符串，`public_key` 必须指向 32 个字节，
+// `out` 必须指向可写的指针。
+enum WcrStatus wcr_package_open_verified(const char *path,
+                                         const uint8_t *public_key,
+                                         uint64_t last_serial,
+                                         struct WcrPackage **out);
+
+// 校验规则包的全部摘要（不解压规则），`public_key` 不为 NULL 时同时校验签名
+//
+// 包头损坏时返回错误；规则数据损坏或签名无效时仍返回 `WCR_STATUS_OK`，由 `out->ok` 表示结果。
+// 不检查内容序号与过期时间。
+//
+// # Safety
+//
+// `path` 必须是以 NUL 结尾的 UTF-8 字符串，`public_key` 为 NULL 或指向 32 个字节，
+// `out` 必须指向可写的 `WcrVerifyReport`。
+enum WcrStatus wcr_package_verify(const char *path,
+                                  const uint8_t *public_key,
+                                  struct WcrVerifyReport *out);
+
+// 释放规则包及其中的全部规则与字符串，`package` 可以为 NULL
+//
+// # Safety
+//
+// `package` 必须来自 `wcr_package_open*` 且只释放一次。
+void wcr_package_free(struct WcrPackage *package);
+
+// 格式版本
+//
+// # Safety
+//
+// `package` 必须是有效的规则包。
+uint32_t wcr_package_format_version(const struct WcrPackage *package);
+
+// 内容序号
+//
+// # Safety
+//
+// `package` 必须是有效的规则包。
+uint64_t wcr_package_serial(const struct WcrPackage *package);
+
+// 创建时间（Unix 时间戳，秒）
+//
+// # Safety
+//
+// `package` 必须是有效的规则包。
+uint64_t wcr_package_created_at(const struct WcrPackage *package);
+
+// 过期时间（Unix 时间戳，秒），永不过期时为 0
+//
+// # Safety
+//
+// `package` 必须是有效的规则包。
+uint64_t wcr_package_expires_at(const struct WcrPackage *package);
+
+// 分类数
+//
+// # Safety
+//
+// `package` 必须是有效的规则包。
+size_t wcr_package_category_count(const struct WcrPackage *package);
+
+// 第 `index` 个分类名，越界时为 NULL
+//
+// # Safety
+//
+// `package` 必须是有效的规则包。
+const char *wcr_package_category(const struct WcrPackage *package, size_t index);
+
+// 规则数
+//
+// # Safety
+//
+// `package` 必须是有效的规则包。
+size_t wcr_package_rule_count(const struct WcrPackage *package);
+
+// 第 `index` 条规则，越界时为 NULL
+//
+// # Safety
+//
+// `package` 必须是有效的规则包。
+const struct WcrRule *wcr_package_rule(const struct WcrPackage *package, size_t index);
+
+// 按 id 查找规则，不存在时为 NULL
+//
+// # Safety
+//
+// `package` 必须是有效的规则包，`id` 必须是以 NUL 结尾的字符串。
+const struct WcrRule *wcr_package_find_rule(const struct WcrPackage *package, const char *id);
+
+// 规则的字符串字段，可选字段未填写时为 NULL
+//
+// # Safety
+//
+// `rule` 必须是有效的规则，`field` 必须是 `WcrRuleField` 中的值。
+const char *wcr_rule_field(const struct WcrRule *rule, enum WcrRuleField field);
+
+// 适用系统数
+//
+// # Safety
+//
+// `rule` 必须是有效的规则。
+size_t wcr_rule_systeminfo_count(const struct WcrRule *rule);
+
+// 第 `index` 个适用系统，越界时为 NULL
+//
+// # Safety
+//
+// `rule` 必须是有效的规则。
+const char *wcr_rule_systeminfo(const struct WcrRule *rule, size_t index);
+
+// 路径模式数
+//
+// # Safety
+//
+// `rule` 必须是有效的规则。
+size_t wcr_rule_path_count(const struct WcrRule *rule);
+
+// 第 `index` 个路径模式，越界时为 NULL
+//
+// # Safety
+//
+// `rule` 必须是有效的规则。
+const char *wcr_rule_path(const struct WcrRule *rule, size_t index);
+
+// 注册表条目数
+//
+// # Safety
+//
+// `rule` 必须是有效的规则。
+size_t wcr_rule_registry_count(const struct WcrRule *rule);
+
+// 第 `index` 个注册表条目，越界时为 NULL
+//
+// # Safety
+//
+// `rule` 必须是有效的规则。
+const struct WcrRegistryEntry *wcr_rule_registry(const struct WcrRule *rule, size_t index);
+
+// 注册表条目的字符串字段，可选字段未填写时为 NULL
+//
+// # Safety
+//
+// `entry` 必须是有效的注册表条目，`field` 必须是 `WcrRegistryField` 中的值。
+const char *wcr_registry_field(const struct WcrRegistryEntry *entry, enum WcrRegistryField field);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* WINCLEAN_RULES_H */
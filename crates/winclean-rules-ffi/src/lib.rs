@@ -0,0 +1,697 @@
+//! WinClean Rules FFI
+//! 供非 Rust 客户端读取规则包的 C ABI，头文件 `include/winclean_rules.h` 由 cbindgen 生成并随仓库提交
+//!
+//! 规则包打开后全部解码到 [`WcrPackage`] 中，规则、注册表条目与字符串都借用自它，
+//! 在 [`wcr_package_free`] 之前一直有效，调用方不需要（也不能）单独释放。
+//! 函数失败时返回 [`WcrStatus`]，详细信息由 [`wcr_last_error`] 取得。
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::ptr;
+use winclean_rules::package::{
+    self, PackageError, PackageHeader, RegistryEntry, RulesPackage, SerializedRule, SignatureStatus, TrustPolicy,
+    DIGEST_LEN,
+};
+use winclean_rules::signing::VerifyingKey;
+
+/// Ed25519 公钥长度
+pub const WCR_PUBLIC_KEY_LEN: usize = 32;
+
+/// 包头摘要长度
+pub const WCR_DIGEST_LEN: usize = 32;
+
+const _: () = assert!(WCR_DIGEST_LEN == DIGEST_LEN);
+
+/// 调用结果
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WcrStatus {
+    Ok = 0,
+    /// 参数为空指针、不是 UTF-8 或公钥无效
+    InvalidArgument,
+    /// 读取文件失败
+    Io,
+    /// 不是规则包
+    NotPackage,
+    /// 格式版本不受支持
+    UnsupportedVersion,
+    /// 文件不完整、摘要不符或无法解析
+    Corrupt,
+    /// 超出读取限制
+    LimitExceeded,
+    /// 要求签名但规则包未签名
+    Unsigned,
+    /// 签名无效
+    BadSignature,
+    /// 内容序号早于上次接受的规则包
+    Rollback,
+    /// 规则包已过期
+    Expired,
+}
+
+/// 签名状态
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WcrSignatureStatus {
+    Unsigned = 0,
+    /// 已签名，未提供公钥校验
+    Unchecked,
+    Valid,
+    Invalid,
+}
+
+/// 规则的字符串字段
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WcrRuleField {
+    Id = 0,
+    Name,
+    Risk,
+    Update,
+    /// 可选，未填写时为 NULL
+    Author,
+    /// 可选，未填写时为 NULL
+    Description,
+    Category,
+    Filename,
+    /// 原始 YAML 内容
+    Yaml,
+}
+
+/// 注册表条目的字符串字段
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WcrRegistryField {
+    Path = 0,
+    Key,
+    /// 可选，未填写时为 NULL
+    Value,
+    /// 可选，未填写时为 NULL
+    ValueData,
+    Action,
+}
+
+/// 校验结果
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct WcrVerifyReport {
+    /// 摘要完好，且提供公钥时签名有效
+    pub ok: bool,
+    /// 规则数据区长度与摘要是否相符
+    pub payload_intact: bool,
+    /// 数据帧缺失或摘要不符的规则数
+    pub damaged: usize,
+    pub signature: WcrSignatureStatus,
+    pub serial: u64,
+    pub created_at: u64,
+    /// 过期时间（Unix 时间戳，秒），永不过期时为 0
+    pub expires_at: u64,
+    /// 包头摘要，与签名无关，可用于比对可复现构建
+    pub header_digest: [u8; WCR_DIGEST_LEN],
+}
+
+/// 已打开的规则包
+pub struct WcrPackage {
+    header: PackageHeader,
+    categories: Vec<CString>,
+    rules: Vec<WcrRule>,
+}
+
+/// 规则包中的一条规则
+pub struct WcrRule {
+    fields: [Option<CString>; 9],
+    systeminfo: Vec<CString>,
+    paths: Vec<CString>,
+    registry: Vec<WcrRegistryEntry>,
+}
+
+/// 规则中的一条注册表条目
+pub struct WcrRegistryEntry {
+    fields: [Option<CString>; 5],
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// 调用失败
+struct Failure {
+    status: WcrStatus,
+    message: String,
+}
+
+impl Failure {
+    fn new(status: WcrStatus, message: impl Into<String>) -> Self {
+        Failure { status, message: message.into() }
+    }
+}
+
+impl From<PackageError> for Failure {
+    fn from(error: PackageError) -> Self {
+        let status = match &error {
+            PackageError::NotPackage => WcrStatus::NotPackage,
+            PackageError::UnsupportedVersion(_) => WcrStatus::UnsupportedVersion,
+            PackageError::Io(_) => WcrStatus::Io,
+            PackageError::LimitExceeded { .. } => WcrStatus::LimitExceeded,
+            PackageError::Unsigned => WcrStatus::Unsigned,
+            PackageError::BadSignature => WcrStatus::BadSignature,
+            PackageError::Rollback { .. } => WcrStatus::Rollback,
+            PackageError::Expired { .. } => WcrStatus::Expired,
+            PackageError::Truncated
+            | PackageError::UnknownCompression(_)
+            | PackageError::UnknownEncoding(_)
+            | PackageError::InvalidHeader(_)
+            | PackageError::InvalidRule { .. }
+            | PackageError::HeaderDigestMismatch
+            | PackageError::PayloadDigestMismatch
+            | PackageError::RuleDigestMismatch { .. }
+            | PackageError::UnsafePath { .. } => WcrStatus::Corrupt,
+        };
+        Failure::new(status, error.to_string())
+    }
+}
+
+/// 记录错误信息并返回状态
+fn finish(result: Result<(), Failure>) -> WcrStatus {
+    let (status, message) = match result {
+        Ok(()) => (WcrStatus::Ok, String::new()),
+        Err(failure) => (failure.status, failure.message),
+    };
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    status
+}
+
+fn c_string(value: &str) -> Result<CString, Failure> {
+    CString::new(value).map_err(|_| Failure::new(WcrStatus::Corrupt, format!("字符串中含有 NUL: {:?}", value)))
+}
+
+fn c_strings(values: &[String]) -> Result<Vec<CString>, Failure> {
+    values.iter().map(|v| c_string(v)).collect()
+}
+
+fn optional(value: &Option<String>) -> Result<Option<CString>, Failure> {
+    value.as_deref().map(c_string).transpose()
+}
+
+impl WcrPackage {
+    fn new(package: RulesPackage) -> Result<Self, Failure> {
+        Ok(WcrPackage {
+            categories: c_strings(&package.header.categories)?,
+            rules: package.rules.iter().map(WcrRule::new).collect::<Result<_, _>>()?,
+            header: package.header,
+        })
+    }
+}
+
+impl WcrRule {
+    fn new(rule: &SerializedRule) -> Result<Self, Failure> {
+        let metadata = &rule.metadata;
+        Ok(WcrRule {
+            fields: [
+                Some(c_string(&metadata.id)?),
+                Some(c_string(&metadata.name)?),
+                Some(c_string(&metadata.risk)?),
+                Some(c_string(&metadata.update)?),
+                optional(&metadata.author)?,
+                optional(&metadata.description)?,
+                Some(c_string(&metadata.category)?),
+                Some(c_string(&metadata.filename)?),
+                Some(c_string(&rule.yaml_content)?),
+            ],
+            systeminfo: c_strings(&metadata.systeminfo)?,
+            paths: c_strings(&rule.paths)?,
+            registry: rule.registry_entries.iter().map(WcrRegistryEntry::new).collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn id(&self) -> &CStr {
+        self.fields[WcrRuleField::Id as usize].as_deref().unwrap_or_default()
+    }
+}
+
+impl WcrRegistryEntry {
+    fn new(entry: &RegistryEntry) -> Result<Self, Failure> {
+        Ok(WcrRegistryEntry {
+            fields: [
+                Some(c_string(&entry.path)?),
+                Some(c_string(&entry.key)?),
+                optional(&entry.value)?,
+                optional(&entry.value_data)?,
+                Some(c_string(&entry.action)?),
+            ],
+        })
+    }
+}
+
+fn as_ptr(value: Option<&CString>) -> *const c_char {
+    value.map_or(ptr::null(), |s| s.as_ptr())
+}
+
+unsafe fn path_arg(path: *const c_char) -> Result<PathBuf, Failure> {
+    if path.is_null() {
+        return Err(Failure::new(WcrStatus::InvalidArgument, "路径为空"));
+    }
+    let path = CStr::from_ptr(path)
+        .to_str()
+        .map_err(|_| Failure::new(WcrStatus::InvalidArgument, "路径不是合法的 UTF-8"))?;
+    Ok(PathBuf::from(path))
+}
+
+unsafe fn key_arg(public_key: *const u8) -> Result<VerifyingKey, Failure> {
+    let bytes: &[u8; WCR_PUBLIC_KEY_LEN] = &*public_key.cast();
+    VerifyingKey::from_bytes(bytes).map_err(|_| Failure::new(WcrStatus::InvalidArgument, "公钥无效"))
+}
+
+fn open_file(path: &PathBuf) -> Result<BufReader<File>, Failure> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| Failure::new(WcrStatus::Io, format!("读取规则包失败: {}: {}", path.display(), e)))
+}
+
+/// 当前线程最近一次调用的错误信息（UTF-8），成功时为空字符串
+///
+/// 返回的字符串在本线程下一次调用前有效。
+#[no_mangle]
+pub extern "C" fn wcr_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// 打开规则包，校验摘要但不校验签名（用于本地调试）
+///
+/// # Safety
+///
+/// `path` 必须是以 NUL 结尾的 UTF-8 字符串，`out` 必须指向可写的指针。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_open(path: *const c_char, out: *mut *mut WcrPackage) -> WcrStatus {
+    finish((|| {
+        if out.is_null() {
+            return Err(Failure::new(WcrStatus::InvalidArgument, "out 为空"));
+        }
+        let path = path_arg(path)?;
+        let package = package::read_package(&mut open_file(&path)?)?;
+        *out = Box::into_raw(Box::new(WcrPackage::new(package)?));
+        Ok(())
+    })())
+}
+
+/// 打开规则包，要求签名有效、未过期且内容序号不小于 `last_serial`
+///
+/// `public_key` 为 32 字节的 Ed25519 公钥；`last_serial` 为上次接受的内容序号，没有时传 0。
+/// 接受后应保存 `wcr_package_serial` 的结果，作为下次的 `last_serial`。
+///
+/// # Safety
+///
+/// `path` 必须是以 NUL 结尾的 UTF-8 字符串，`public_key` 必须指向 32 个字节，
+/// `out` 必须指向可写的指针。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_open_verified(
+    path: *const c_char,
+    public_key: *const u8,
+    last_serial: u64,
+    out: *mut *mut WcrPackage,
+) -> WcrStatus {
+    finish((|| {
+        if out.is_null() || public_key.is_null() {
+            return Err(Failure::new(WcrStatus::InvalidArgument, "public_key 或 out 为空"));
+        }
+        let path = path_arg(path)?;
+        let policy = TrustPolicy { last_serial: Some(last_serial), ..TrustPolicy::new(key_arg(public_key)?) };
+        let package = package::read_package_verified(&mut open_file(&path)?, &policy)?;
+        *out = Box::into_raw(Box::new(WcrPackage::new(package)?));
+        Ok(())
+    })())
+}
+
+/// 校验规则包的全部摘要（不解压规则），`public_key` 不为 NULL 时同时校验签名
+///
+/// 包头损坏时返回错误；规则数据损坏或签名无效时仍返回 `WCR_STATUS_OK`，由 `out->ok` 表示结果。
+/// 不检查内容序号与过期时间。
+///
+/// # Safety
+///
+/// `path` 必须是以 NUL 结尾的 UTF-8 字符串，`public_key` 为 NULL 或指向 32 个字节，
+/// `out` 必须指向可写的 `WcrVerifyReport`。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_verify(
+    path: *const c_char,
+    public_key: *const u8,
+    out: *mut WcrVerifyReport,
+) -> WcrStatus {
+    finish((|| {
+        if out.is_null() {
+            return Err(Failure::new(WcrStatus::InvalidArgument, "out 为空"));
+        }
+        let path = path_arg(path)?;
+        let key = if public_key.is_null() { None } else { Some(key_arg(public_key)?) };
+        let report = package::verify_package(&mut open_file(&path)?, key.as_ref())?;
+        *out = WcrVerifyReport {
+            ok: report.is_ok(),
+            payload_intact: report.payload_intact,
+            damaged: report.damaged.len(),
+            signature: match report.signature {
+                SignatureStatus::Unsigned => WcrSignatureStatus::Unsigned,
+                SignatureStatus::Unchecked => WcrSignatureStatus::Unchecked,
+                SignatureStatus::Valid => WcrSignatureStatus::Valid,
+                SignatureStatus::Invalid => WcrSignatureStatus::Invalid,
+            },
+            serial: report.header.serial,
+            created_at: report.header.created_at,
+            expires_at: report.header.expires_at.unwrap_or(0),
+            header_digest: report.header_digest,
+        };
+        Ok(())
+    })())
+}
+
+/// 释放规则包及其中的全部规则与字符串，`package` 可以为 NULL
+///
+/// # Safety
+///
+/// `package` 必须来自 `wcr_package_open*` 且只释放一次。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_free(package: *mut WcrPackage) {
+    if !package.is_null() {
+        drop(Box::from_raw(package));
+    }
+}
+
+/// 格式版本
+///
+/// # Safety
+///
+/// `package` 必须是有效的规则包。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_format_version(package: *const WcrPackage) -> u32 {
+    (*package).header.version
+}
+
+/// 内容序号
+///
+/// # Safety
+///
+/// `package` 必须是有效的规则包。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_serial(package: *const WcrPackage) -> u64 {
+    (*package).header.serial
+}
+
+/// 创建时间（Unix 时间戳，秒）
+///
+/// # Safety
+///
+/// `package` 必须是有效的规则包。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_created_at(package: *const WcrPackage) -> u64 {
+    (*package).header.created_at
+}
+
+/// 过期时间（Unix 时间戳，秒），永不过期时为 0
+///
+/// # Safety
+///
+/// `package` 必须是有效的规则包。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_expires_at(package: *const WcrPackage) -> u64 {
+    (*package).header.expires_at.unwrap_or(0)
+}
+
+/// 分类数
+///
+/// # Safety
+///
+/// `package` 必须是有效的规则包。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_category_count(package: *const WcrPackage) -> usize {
+    (*package).categories.len()
+}
+
+/// 第 `index` 个分类名，越界时为 NULL
+///
+/// # Safety
+///
+/// `package` 必须是有效的规则包。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_category(package: *const WcrPackage, index: usize) -> *const c_char {
+    as_ptr((&*package).categories.get(index))
+}
+
+/// 规则数
+///
+/// # Safety
+///
+/// `package` 必须是有效的规则包。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_rule_count(package: *const WcrPackage) -> usize {
+    (*package).rules.len()
+}
+
+/// 第 `index` 条规则，越界时为 NULL
+///
+/// # Safety
+///
+/// `package` 必须是有效的规则包。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_rule(package: *const WcrPackage, index: usize) -> *const WcrRule {
+    (&*package).rules.get(index).map_or(ptr::null(), |r| r as *const WcrRule)
+}
+
+/// 按 id 查找规则，不存在时为 NULL
+///
+/// # Safety
+///
+/// `package` 必须是有效的规则包，`id` 必须是以 NUL 结尾的字符串。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_package_find_rule(package: *const WcrPackage, id: *const c_char) -> *const WcrRule {
+    if id.is_null() {
+        return ptr::null();
+    }
+    let id = CStr::from_ptr(id);
+    (*package).rules.iter().find(|r| r.id() == id).map_or(ptr::null(), |r| r as *const WcrRule)
+}
+
+/// 规则的字符串字段，可选字段未填写时为 NULL
+///
+/// # Safety
+///
+/// `rule` 必须是有效的规则，`field` 必须是 `WcrRuleField` 中的值。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_rule_field(rule: *const WcrRule, field: WcrRuleField) -> *const c_char {
+    as_ptr((*rule).fields[field as usize].as_ref())
+}
+
+/// 适用系统数
+///
+/// # Safety
+///
+/// `rule` 必须是有效的规则。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_rule_systeminfo_count(rule: *const WcrRule) -> usize {
+    (*rule).systeminfo.len()
+}
+
+/// 第 `index` 个适用系统，越界时为 NULL
+///
+/// # Safety
+///
+/// `rule` 必须是有效的规则。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_rule_systeminfo(rule: *const WcrRule, index: usize) -> *const c_char {
+    as_ptr((&*rule).systeminfo.get(index))
+}
+
+/// 路径模式数
+///
+/// # Safety
+///
+/// `rule` 必须是有效的规则。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_rule_path_count(rule: *const WcrRule) -> usize {
+    (*rule).paths.len()
+}
+
+/// 第 `index` 个路径模式，越界时为 NULL
+///
+/// # Safety
+///
+/// `rule` 必须是有效的规则。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_rule_path(rule: *const WcrRule, index: usize) -> *const c_char {
+    as_ptr((&*rule).paths.get(index))
+}
+
+/// 注册表条目数
+///
+/// # Safety
+///
+/// `rule` 必须是有效的规则。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_rule_registry_count(rule: *const WcrRule) -> usize {
+    (*rule).registry.len()
+}
+
+/// 第 `index` 个注册表条目，越界时为 NULL
+///
+/// # Safety
+///
+/// `rule` 必须是有效的规则。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_rule_registry(rule: *const WcrRule, index: usize) -> *const WcrRegistryEntry {
+    (&*rule).registry.get(index).map_or(ptr::null(), |e| e as *const WcrRegistryEntry)
+}
+
+/// 注册表条目的字符串字段，可选字段未填写时为 NULL
+///
+/// # Safety
+///
+/// `entry` 必须是有效的注册表条目，`field` 必须是 `WcrRegistryField` 中的值。
+#[no_mangle]
+pub unsafe extern "C" fn wcr_registry_field(entry: *const WcrRegistryEntry, field: WcrRegistryField) -> *const c_char {
+    as_ptr((*entry).fields[field as usize].as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use winclean_rules::package::{write_package, Compression, Encoding};
+    use winclean_rules::signing::SigningKey;
+    use winclean_rules::source::load_rules;
+
+    const SERIAL: u64 = 20260101;
+
+    /// 临时文件，测试结束时删除
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, data: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("winclean-rules-ffi-{}-{}", std::process::id(), name));
+            fs::write(&path, data).unwrap();
+            TempFile(path)
+        }
+
+        fn c_path(&self) -> CString {
+            CString::new(self.0.to_str().unwrap()).unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// 用仓库中的 `rules` 目录打包
+    fn fixture(key: &SigningKey) -> (Vec<SerializedRule>, Vec<u8>) {
+        let (files, _) = load_rules(&PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../rules")).unwrap();
+        let rules: Vec<SerializedRule> = files.iter().map(|f| f.to_serialized()).collect();
+        assert!(!rules.is_empty());
+        let header = PackageHeader::new(&rules, SERIAL, 1_700_000_000, None);
+        let (data, _) = write_package(&header, &rules, Compression::Zstd, Encoding::Bincode, Some(key)).unwrap();
+        (rules, data)
+    }
+
+    unsafe fn text<'a>(ptr: *const c_char) -> Option<&'a str> {
+        (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_str().unwrap())
+    }
+
+    unsafe fn open(file: &TempFile, key: &SigningKey, last_serial: u64) -> (WcrStatus, *mut WcrPackage) {
+        let mut package = ptr::null_mut();
+        let public_key = key.verifying_key().to_bytes();
+        let status = wcr_package_open_verified(file.c_path().as_ptr(), public_key.as_ptr(), last_serial, &mut package);
+        (status, package)
+    }
+
+    #[test]
+    fn header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/winclean_rules.h"));
+        let committed = include_str!("../include/winclean_rules.h");
+        assert!(
+            generated == committed,
+            "include/winclean_rules.h 已过期，请执行 cargo build -p winclean-rules-ffi --features update-header"
+        );
+    }
+
+    #[test]
+    fn opens_signed_package() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let (rules, data) = fixture(&key);
+        let file = TempFile::new("signed.bin", &data);
+
+        unsafe {
+            let (status, package) = open(&file, &key, SERIAL);
+            assert_eq!(status, WcrStatus::Ok, "{:?}", text(wcr_last_error()));
+            assert_eq!(text(wcr_last_error()), Some(""));
+            assert_eq!(wcr_package_serial(package), SERIAL);
+            assert_eq!(wcr_package_expires_at(package), 0);
+            assert_eq!(wcr_package_rule_count(package), rules.len());
+            assert!(wcr_package_rule(package, rules.len()).is_null());
+
+            for (i, expected) in rules.iter().enumerate() {
+                let rule = wcr_package_rule(package, i);
+                assert_eq!(text(wcr_rule_field(rule, WcrRuleField::Id)), Some(expected.metadata.id.as_str()));
+                assert_eq!(text(wcr_rule_field(rule, WcrRuleField::Yaml)), Some(expected.yaml_content.as_str()));
+                assert_eq!(text(wcr_rule_field(rule, WcrRuleField::Author)), expected.metadata.author.as_deref());
+                assert_eq!(wcr_rule_path_count(rule), expected.paths.len());
+                assert_eq!(text(wcr_rule_path(rule, 0)), expected.paths.first().map(String::as_str));
+                assert_eq!(wcr_rule_registry_count(rule), expected.registry_entries.len());
+                if let Some(entry) = expected.registry_entries.first() {
+                    let registry = wcr_rule_registry(rule, 0);
+                    assert_eq!(text(wcr_registry_field(registry, WcrRegistryField::Key)), Some(entry.key.as_str()));
+                    assert_eq!(text(wcr_registry_field(registry, WcrRegistryField::Value)), entry.value.as_deref());
+                }
+
+                let id = CString::new(expected.metadata.id.as_str()).unwrap();
+                assert_eq!(wcr_package_find_rule(package, id.as_ptr()), rule);
+            }
+            assert!(wcr_package_find_rule(package, c"missing".as_ptr()).is_null());
+            wcr_package_free(package);
+
+            let mut report = std::mem::zeroed::<WcrVerifyReport>();
+            let public_key = key.verifying_key().to_bytes();
+            assert_eq!(wcr_package_verify(file.c_path().as_ptr(), public_key.as_ptr(), &mut report), WcrStatus::Ok);
+            assert!(report.ok);
+            assert_eq!(report.signature, WcrSignatureStatus::Valid);
+            assert_eq!(report.serial, SERIAL);
+        }
+    }
+
+    #[test]
+    fn rejects_untrusted_packages() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let (_, data) = fixture(&key);
+        let file = TempFile::new("untrusted.bin", &data);
+
+        unsafe {
+            let (status, package) = open(&file, &SigningKey::from_bytes(&[8; 32]), 0);
+            assert_eq!(status, WcrStatus::BadSignature);
+            assert!(package.is_null());
+            assert!(!text(wcr_last_error()).unwrap().is_empty());
+
+            assert_eq!(open(&file, &key, SERIAL + 1).0, WcrStatus::Rollback);
+
+            let mut tampered = data.clone();
+            *tampered.last_mut().unwrap() ^= 1;
+            let tampered = TempFile::new("tampered.bin", &tampered);
+            assert_eq!(open(&tampered, &key, 0).0, WcrStatus::Corrupt);
+
+            let missing = TempFile(std::env::temp_dir().join("winclean-rules-ffi-missing.bin"));
+            assert_eq!(open(&missing, &key, 0).0, WcrStatus::Io);
+
+            let public_key = key.verifying_key().to_bytes();
+            let path = file.c_path();
+            assert_eq!(
+                wcr_package_open_verified(path.as_ptr(), public_key.as_ptr(), 0, ptr::null_mut()),
+                WcrStatus::InvalidArgument
+            );
+            let mut package = ptr::null_mut();
+            assert_eq!(
+                wcr_package_open_verified(path.as_ptr(), ptr::null(), 0, &mut package),
+                WcrStatus::InvalidArgument
+            );
+        }
+    }
+}
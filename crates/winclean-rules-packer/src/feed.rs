@@ -0,0 +1,425 @@
+//! 发布元数据
+//! 生成与校验规则包旁的 root/targets/timestamp 元数据
+
+use crate::output::Output;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use winclean_rules::feed::{
+    self, Client, DirMirror, FileInfo, MetaInfo, Metadata, Mirror, Role, RoleKeys, Root, Signed, Targets, Timestamp,
+    TrustedState,
+};
+use winclean_rules::signing::{self, SigningKey};
+
+/// 根元数据配置
+///
+/// ```yaml
+/// expires_in_days: 365
+/// roles:
+///   root:
+///     threshold: 2
+///     keys: [root1.pub.pem, root2.pub.pem, root3.pub.pem]
+///   targets:
+///     keys: [targets.pub.pem]
+///   timestamp:
+///     keys: [timestamp.pub.pem]
+/// ```
+///
+/// 公钥路径相对于配置文件所在目录。
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RootConfig {
+    #[serde(default = "default_root_expiry")]
+    expires_in_days: u64,
+    roles: BTreeMap<Role, RoleConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RoleConfig {
+    #[serde(default = "default_threshold")]
+    threshold: u32,
+    keys: Vec<PathBuf>,
+}
+
+fn default_root_expiry() -> u64 {
+    365
+}
+
+fn default_threshold() -> u32 {
+    1
+}
+
+/// 发布选项
+pub struct PublishOptions {
+    /// targets 与 timestamp 的版本，默认在已有版本上加一
+    pub version: Option<u64>,
+    pub targets_days: u64,
+    pub timestamp_days: u64,
+}
+
+/// feed-root 的结果
+#[derive(Serialize)]
+struct RootReport<'a> {
+    version: u64,
+    expires: &'a str,
+    roles: &'a BTreeMap<Role, RoleKeys>,
+    signatures: usize,
+    files: Vec<PathBuf>,
+}
+
+/// feed-publish 的结果
+#[derive(Serialize)]
+struct PublishReport<'a> {
+    targets: &'a Targets,
+    timestamp: &'a Timestamp,
+    files: Vec<PathBuf>,
+}
+
+/// feed-verify 的结果
+#[derive(Serialize)]
+struct VerifyReport<'a> {
+    /// 更新前信任的根元数据版本
+    root_version_from: u64,
+    root_version: u64,
+    root_expires: &'a str,
+    timestamp_version: u64,
+    targets: &'a Targets,
+    /// 已校验的发布文件
+    target: Option<&'a str>,
+    saved: Option<&'a Path>,
+    state: Option<&'a Path>,
+}
+
+/// 按配置生成下一版本的根元数据
+pub fn feed_root(config_path: &Path, dir: &Path, sign_keys: &[PathBuf], out: Output) -> Result<()> {
+    say!(out, "生成根元数据: {:?}", dir);
+
+    let content = fs::read_to_string(config_path)
+        .with_context(|| format!("读取配置失败: {}", config_path.display()))?;
+    let config: RootConfig = serde_yaml::from_str(&content)
+        .with_context(|| format!("配置格式错误: {}", config_path.display()))?;
+    let base = config_path.parent().unwrap_or(Path::new("."));
+
+    let previous = read_local::<Root>(dir, feed::ROOT)?;
+    let mut root = Root {
+        version: previous.as_ref().map_or(1, |p| p.signed.version + 1),
+        expires: expires_in_days(config.expires_in_days)?,
+        keys: BTreeMap::new(),
+        roles: BTreeMap::new(),
+    };
+    for role in [Role::Root, Role::Targets, Role::Timestamp] {
+        let role_config = config.roles.get(&role).with_context(|| format!("配置缺少角色 {}", role))?;
+        for path in &role_config.keys {
+            root.add_key(role, &signing::load_verifying_key(&base.join(path))?);
+        }
+        // 同一公钥列出多次只计一次，门限按去重后的公钥 id 检查
+        let distinct = root.roles.get(&role).map_or(0, |keys| keys.keyids.len());
+        if role_config.threshold == 0 || role_config.threshold as usize > distinct {
+            anyhow::bail!("角色 {} 的门限 {} 无效: 共 {} 个不同的公钥", role, role_config.threshold, distinct);
+        }
+        if let Some(keys) = root.roles.get_mut(&role) {
+            keys.threshold = role_config.threshold;
+        }
+    }
+
+    let mut signed = Signed::new(root);
+    for key in load_signing_keys(sign_keys)? {
+        signed.sign(&key);
+    }
+    signed.verify(&signed.signed).context("新根元数据未满足自身的根门限")?;
+    if let Some(previous) = &previous {
+        signed
+            .verify(&previous.signed)
+            .with_context(|| format!("新根元数据未满足版本 {} 的根门限", previous.signed.version))?;
+    }
+
+    let root = &signed.signed;
+    fs::create_dir_all(dir)?;
+    let files = vec![
+        write_metadata(dir, &feed::versioned_root(root.version), &signed, out)?,
+        write_metadata(dir, feed::ROOT, &signed, out)?,
+    ];
+
+    say!(out, "版本: {}", root.version);
+    say!(out, "过期时间: {}", root.expires);
+    for (role, keys) in &root.roles {
+        let fingerprints: Vec<&str> = keys.keyids.iter().map(|id| &id[..16]).collect();
+        say!(out, "  {}: 门限 {}/{} {:?}", role, keys.threshold, keys.keyids.len(), fingerprints);
+    }
+    say!(out, "签名: {} 个", signed.signatures.len());
+
+    out.json(&RootReport {
+        version: root.version,
+        expires: &root.expires,
+        roles: &root.roles,
+        signatures: signed.signatures.len(),
+        files,
+    })
+}
+
+/// 为发布文件生成 targets 与 timestamp 元数据
+pub fn feed_publish(
+    dir: &Path,
+    names: &[String],
+    targets_keys: &[PathBuf],
+    timestamp_keys: &[PathBuf],
+    options: PublishOptions,
+    out: Output,
+) -> Result<()> {
+    let PublishOptions { version, targets_days, timestamp_days } = options;
+    say!(out, "生成发布元数据: {:?}", dir);
+
+    let root = read_local::<Root>(dir, feed::ROOT)?
+        .with_context(|| format!("缺少根元数据，请先运行 feed-root: {}", dir.join(feed::ROOT).display()))?;
+    let previous_targets = read_local::<Targets>(dir, feed::TARGETS)?;
+    let previous_timestamp = read_local::<Timestamp>(dir, feed::TIMESTAMP)?;
+
+    let mut targets = Targets {
+        version: next_version(Role::Targets, version, previous_targets.as_ref())?,
+        expires: expires_in_days(targets_days)?,
+        targets: BTreeMap::new(),
+    };
+    for name in names {
+        let data = fs::read(dir.join(name)).with_context(|| format!("读取发布文件失败: {}", name))?;
+        targets.targets.insert(name.clone(), FileInfo::new(&data));
+    }
+    let targets = sign_metadata(targets, targets_keys, &root.signed)?;
+    let targets_json = targets.to_json();
+
+    let timestamp = Timestamp {
+        version: next_version(Role::Timestamp, version, previous_timestamp.as_ref())?,
+        expires: expires_in_days(timestamp_days)?,
+        targets: MetaInfo::new(targets.signed.version, targets_json.as_bytes()),
+    };
+    let timestamp = sign_metadata(timestamp, timestamp_keys, &root.signed)?;
+    let timestamp_json = timestamp.to_json();
+
+    // 写出前以已发布的版本为信任状态走一遍客户端校验，已接受旧版本的客户端也必须能接受
+    let state = TrustedState {
+        timestamp_version: previous_timestamp.map_or(0, |m| m.signed.version),
+        targets_version: previous_targets.map_or(0, |m| m.signed.version),
+        ..TrustedState::pinned(root)
+    };
+    let staged = StagedMirror {
+        dir: DirMirror::new(dir),
+        files: BTreeMap::from([(feed::TARGETS, targets_json.as_bytes()), (feed::TIMESTAMP, timestamp_json.as_bytes())]),
+    };
+    Client::new(state)?.update(&staged).context("生成的元数据未通过校验")?;
+
+    let files = vec![
+        write_metadata(dir, feed::TARGETS, &targets, out)?,
+        write_metadata(dir, feed::TIMESTAMP, &timestamp, out)?,
+    ];
+
+    say!(out, "targets: 版本 {}，过期时间 {}", targets.signed.version, targets.signed.expires);
+    for (name, info) in &targets.signed.targets {
+        say!(out, "  {} ({} bytes) {}", name, info.length, info.blake3);
+    }
+    say!(out, "timestamp: 版本 {}，过期时间 {}", timestamp.signed.version, timestamp.signed.expires);
+
+    out.json(&PublishReport { targets: &targets.signed, timestamp: &timestamp.signed, files })
+}
+
+/// 从内置根元数据（或保存的信任状态）出发校验镜像
+pub fn feed_verify(
+    mirror: &Path,
+    root: Option<&Path>,
+    state_path: Option<&Path>,
+    target: Option<&str>,
+    output: Option<&Path>,
+    out: Output,
+) -> Result<()> {
+    say!(out, "校验镜像: {:?}", mirror);
+
+    let state = match (state_path.filter(|p| p.exists()), root) {
+        (Some(path), _) => {
+            say!(out, "信任状态: {:?}", path);
+            let content = fs::read(path).with_context(|| format!("读取信任状态失败: {}", path.display()))?;
+            serde_json::from_slice(&content).with_context(|| format!("信任状态格式错误: {}", path.display()))?
+        }
+        (None, Some(path)) => {
+            say!(out, "内置根元数据: {:?}", path);
+            let content = fs::read(path).with_context(|| format!("读取根元数据失败: {}", path.display()))?;
+            TrustedState::pinned(Signed::from_slice(&path.display().to_string(), &content)?)
+        }
+        (None, None) => anyhow::bail!("需要指定 --root 或已存在的 --state"),
+    };
+
+    let mirror = DirMirror::new(mirror);
+    let mut client = Client::new(state)?;
+    let from = client.state().root.signed.version;
+    let targets = client.update(&mirror).context("校验失败")?;
+    let root = &client.state().root.signed;
+
+    if root.version > from {
+        say!(out, "root: 版本 {} -> {}，过期时间 {}", from, root.version, root.expires);
+    } else {
+        say!(out, "root: 版本 {}，过期时间 {}", root.version, root.expires);
+    }
+    say!(out, "timestamp: 版本 {}", client.state().timestamp_version);
+    say!(out, "targets: 版本 {}，过期时间 {}", targets.version(), targets.expires());
+    for (name, info) in &targets.targets {
+        say!(out, "  {} ({} bytes) {}", name, info.length, info.blake3);
+    }
+
+    let mut saved = None;
+    if let Some(name) = target {
+        let data = targets.fetch(&mirror, name).context("校验失败")?;
+        say!(out, "发布文件: {} 正常", name);
+        if let Some(output) = output {
+            fs::write(output, data).with_context(|| format!("写入文件失败: {}", output.display()))?;
+            say!(out, "已保存: {:?}", output);
+            saved = Some(output);
+        }
+    }
+
+    if let Some(path) = state_path {
+        let json = serde_json::to_string_pretty(client.state())?;
+        fs::write(path, json + "\n").with_context(|| format!("写入信任状态失败: {}", path.display()))?;
+        say!(out, "已更新信任状态: {:?}", path);
+    }
+    say!(out, "校验通过");
+
+    out.json(&VerifyReport {
+        root_version_from: from,
+        root_version: root.version,
+        root_expires: &root.expires,
+        timestamp_version: client.state().timestamp_version,
+        targets: &targets,
+        target,
+        saved,
+        state: state_path,
+    })
+}
+
+fn expires_in_days(days: u64) -> Result<String> {
+    feed::expires_in_days(days).with_context(|| format!("有效天数 {} 过大", days))
+}
+
+/// 新发布的版本：默认在已发布的版本上加一，指定的版本必须大于已发布的版本
+///
+/// 已接受旧版本的客户端会拒绝版本不增加的元数据。
+fn next_version<T: Metadata>(role: Role, version: Option<u64>, previous: Option<&Signed<T>>) -> Result<u64> {
+    let previous = previous.map(|p| p.signed.version());
+    match (version, previous) {
+        (Some(version), Some(previous)) if version <= previous => {
+            anyhow::bail!("{} 的版本 {} 必须大于已发布的版本 {}", role, version, previous)
+        }
+        (Some(version), _) => Ok(version),
+        (None, previous) => Ok(previous.map_or(1, |p| p + 1)),
+    }
+}
+
+/// 签名并确认满足根元数据中该角色的门限
+fn sign_metadata<T: Metadata>(metadata: T, keys: &[PathBuf], root: &Root) -> Result<Signed<T>> {
+    let mut signed = Signed::new(metadata);
+    for key in load_signing_keys(keys)? {
+        signed.sign(&key);
+    }
+    signed.verify(root)?;
+    Ok(signed)
+}
+
+fn load_signing_keys(paths: &[PathBuf]) -> Result<Vec<SigningKey>> {
+    paths.iter().map(|p| signing::load_signing_key(p)).collect()
+}
+
+/// 以新生成的元数据替换目录中同名文件的镜像，用于写出前校验
+struct StagedMirror<'a> {
+    dir: DirMirror,
+    files: BTreeMap<&'a str, &'a [u8]>,
+}
+
+impl Mirror for StagedMirror<'_> {
+    fn fetch(&self, name: &str) -> std::io::Result<Option<Vec<u8>>> {
+        match self.files.get(name) {
+            Some(data) => Ok(Some(data.to_vec())),
+            None => self.dir.fetch(name),
+        }
+    }
+}
+
+/// 读取目录中的元数据，不存在时返回 `None`
+fn read_local<T: Metadata>(dir: &Path, name: &str) -> Result<Option<Signed<T>>> {
+    let path = dir.join(name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read(&path).with_context(|| format!("读取元数据失败: {}", path.display()))?;
+    Ok(Some(Signed::from_slice(name, &content)?))
+}
+
+/// 写出元数据，返回写入的路径
+///
+/// 先写入临时文件再重命名，镜像中不会出现写了一半的元数据。
+fn write_metadata<T: Metadata>(dir: &Path, name: &str, metadata: &Signed<T>, out: Output) -> Result<PathBuf> {
+    let path = dir.join(name);
+    let temp = dir.join(format!("{}.tmp", name));
+    fs::write(&temp, metadata.to_json()).with_context(|| format!("写入元数据失败: {}", temp.display()))?;
+    fs::rename(&temp, &path).with_context(|| format!("写入元数据失败: {}", path.display()))?;
+    say!(out, "已生成: {:?}", path);
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Format;
+
+    /// 写出第 n 个测试密钥的私钥与公钥，返回私钥路径
+    fn write_key(dir: &Path, name: &str, n: u8) -> PathBuf {
+        let key = SigningKey::from_bytes(&[n; 32]);
+        let private = dir.join(format!("{}.key.pem", name));
+        fs::write(&private, signing::signing_key_pem(&key).unwrap()).unwrap();
+        fs::write(dir.join(format!("{}.pub.pem", name)), signing::verifying_key_pem(&key.verifying_key()).unwrap())
+            .unwrap();
+        private
+    }
+
+    fn write_config(dir: &Path, root_threshold: u32, root_keys: &[&str]) -> PathBuf {
+        let keys: Vec<String> = root_keys.iter().map(|name| format!("{}.pub.pem", name)).collect();
+        let config = format!(
+            "roles:\n  root:\n    threshold: {}\n    keys: [{}]\n  targets:\n    keys: [targets.pub.pem]\n  \
+             timestamp:\n    keys: [timestamp.pub.pem]\n",
+            root_threshold,
+            keys.join(", ")
+        );
+        let path = dir.join("root.yaml");
+        fs::write(&path, config).unwrap();
+        path
+    }
+
+    #[test]
+    fn threshold_counts_distinct_keys() {
+        let temp = tempfile::tempdir().unwrap();
+        let keys = temp.path();
+        let root1 = write_key(keys, "root1", 1);
+        let root2 = write_key(keys, "root2", 2);
+        write_key(keys, "targets", 3);
+        write_key(keys, "timestamp", 4);
+        // 同一公钥的两个副本
+        fs::copy(keys.join("root1.pub.pem"), keys.join("root1-copy.pub.pem")).unwrap();
+        let out = Output::new(Format::Json);
+
+        let root1_only = [root1.clone()];
+        let feed_dir = temp.path().join("feed");
+        let config = write_config(keys, 2, &["root1", "root1-copy"]);
+        let error = feed_root(&config, &feed_dir, &root1_only, out).unwrap_err();
+        assert_eq!(error.to_string(), "角色 root 的门限 2 无效: 共 1 个不同的公钥");
+        assert!(!feed_dir.exists());
+
+        let config = write_config(keys, 0, &["root1"]);
+        assert!(feed_root(&config, &feed_dir, &root1_only, out).is_err());
+        let config = write_config(keys, 1, &[]);
+        assert!(feed_root(&config, &feed_dir, &root1_only, out).is_err());
+
+        let config = write_config(keys, 2, &["root1", "root1-copy", "root2"]);
+        feed_root(&config, &feed_dir, &[root1, root2], out).unwrap();
+        let root = read_local::<Root>(&feed_dir, feed::ROOT).unwrap().unwrap();
+        let root_keys = &root.signed.roles[&Role::Root];
+        assert_eq!((root_keys.threshold, root_keys.keyids.len()), (2, 2));
+    }
+}
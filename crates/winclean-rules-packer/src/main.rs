@@ -0,0 +1,1317 @@
+//! WinClean Rules Packer
+//! 将YAML规则打包为二进制格式的工具
+
+#[macro_use]
+mod output;
+mod feed;
+mod scan;
+mod simulate;
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use output::{Format, Output, Reported};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use winclean_rules::diagnostic::Diagnostic;
+use winclean_rules::pack::{self, PackOptions, Packed};
+use winclean_rules::package::{
+    self, PackageReader, RuleMetadata, RulesPackage, SerializedRule, SignatureStatus, TrustPolicy, VerifyReport,
+};
+use winclean_rules::sample::{self, SampleOutcome};
+use winclean_rules::signing::{self, hex, VerifyingKey};
+use winclean_rules::source::{load_rules, RuleFile};
+use winclean_rules::time::format_time;
+
+/// 命令行参数
+#[derive(Parser, Debug)]
+#[command(name = "winclean-rules-packer")]
+#[command(author = "WinClean Contributors")]
+#[command(version = "0.1.0")]
+#[command(about = "WinClean Rules Packer - 将YAML规则打包为二进制格式", long_about = None)]
+struct Args {
+    /// 输出格式: text, json
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// 打包规则
+    Pack {
+        /// 输入目录（YAML规则所在目录）
+        #[arg(short, long, default_value = "./rules")]
+        input: PathBuf,
+
+        /// 输出文件路径
+        #[arg(short, long, default_value = "./dist/rules.bin")]
+        output: PathBuf,
+
+        /// 压缩算法: none, zstd
+        #[arg(short, long, default_value = "zstd")]
+        compress: String,
+
+        /// 编码: bincode（仅 Rust 客户端）, protobuf（按 proto/winclean_rules.proto，供其他语言读取）
+        #[arg(long, default_value = "bincode")]
+        encoding: String,
+
+        /// 签名私钥（Ed25519 PKCS#8 PEM），未指定时生成未签名的规则包
+        #[arg(long)]
+        sign_key: Option<PathBuf>,
+
+        /// 内容序号，每次发布必须不小于上一次，默认为当天日期 YYYYMMDD（UTC）
+        #[arg(long)]
+        serial: Option<u64>,
+
+        /// 有效天数，过期后客户端拒绝使用，未指定时不过期
+        #[arg(long)]
+        expires_in_days: Option<u64>,
+
+        /// 可复现构建：未设置 SOURCE_DATE_EPOCH 时以规则中最新的 update 日期作为创建时间
+        #[arg(long)]
+        reproducible: bool,
+    },
+
+    /// 生成签名密钥对
+    Keygen {
+        /// 私钥输出路径
+        #[arg(long, default_value = "./keys/rules.key.pem")]
+        private: PathBuf,
+
+        /// 公钥输出路径
+        #[arg(long, default_value = "./keys/rules.pub.pem")]
+        public: PathBuf,
+    },
+
+    /// 解包规则
+    Unpack {
+        /// 输入文件路径（二进制规则包）
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// 输出目录
+        #[arg(short, long, default_value = "./rules_unpacked")]
+        output: PathBuf,
+
+        /// 按规则模型重新生成YAML（不保留原文件注释与格式）
+        #[arg(long)]
+        normalize: bool,
+
+        /// 只解包指定 id 的规则
+        #[arg(long, conflicts_with = "category")]
+        id: Option<String>,
+
+        /// 只解包指定分类的规则
+        #[arg(long)]
+        category: Option<String>,
+
+        /// 覆盖已存在的文件
+        #[arg(long, conflicts_with = "no_clobber")]
+        overwrite: bool,
+
+        /// 跳过已存在的文件
+        #[arg(long)]
+        no_clobber: bool,
+    },
+
+    /// 显示规则包信息
+    Info {
+        /// 输入文件路径（二进制规则包）
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+
+    /// 校验规则包完整性与签名，列出损坏的规则
+    Verify {
+        /// 输入文件路径（二进制规则包）
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// 签名公钥（Ed25519 SPKI PEM），指定时要求规则包签名有效且未过期
+        #[arg(long)]
+        pubkey: Option<PathBuf>,
+
+        /// 上次接受的内容序号，更旧的规则包视为回滚
+        #[arg(long, requires = "pubkey")]
+        last_serial: Option<u64>,
+    },
+
+    /// 校验规则
+    Validate {
+        /// 输入目录（YAML规则所在目录）
+        #[arg(short, long, default_value = "./rules")]
+        input: PathBuf,
+    },
+
+    /// 运行规则自带的测试样例
+    Test {
+        /// 输入目录（YAML规则所在目录）
+        #[arg(short, long, default_value = "./rules")]
+        input: PathBuf,
+
+        /// 只测试指定 id 的规则
+        #[arg(long)]
+        id: Option<String>,
+    },
+
+    /// 在挂载的Windows文件系统上试运行规则（只列出，不删除）
+    Scan {
+        /// 规则来源（YAML规则目录或二进制规则包）
+        #[arg(short, long, default_value = "./dist/rules.bin")]
+        rules: PathBuf,
+
+        /// Windows系统盘的挂载目录
+        #[arg(long)]
+        root: PathBuf,
+
+        /// 用户名，未指定时扫描 Users 下的所有用户
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+
+    /// 在夹具描述的虚拟Windows环境上模拟全部规则，生成确定性报告
+    Simulate {
+        /// 规则来源（YAML规则目录或二进制规则包）
+        #[arg(short, long, default_value = "./rules")]
+        rules: PathBuf,
+
+        /// 夹具文件（YAML）
+        #[arg(short, long)]
+        fixture: PathBuf,
+
+        /// 快照文件：存在时与报告比对，不存在时写入
+        #[arg(short, long)]
+        snapshot: Option<PathBuf>,
+
+        /// 用本次报告覆盖快照
+        #[arg(long)]
+        update: bool,
+    },
+
+    /// 在离线注册表配置单元上试运行注册表规则（只列出，不修改）
+    ScanRegistry {
+        /// 规则来源（YAML规则目录或二进制规则包）
+        #[arg(short, long, default_value = "./dist/rules.bin")]
+        rules: PathBuf,
+
+        /// SOFTWARE 配置单元（HKLM\SOFTWARE 与 HKCR 的机器部分）
+        #[arg(long)]
+        software: Option<PathBuf>,
+
+        /// SYSTEM 配置单元（HKLM\SYSTEM）
+        #[arg(long)]
+        system: Option<PathBuf>,
+
+        /// NTUSER.DAT（HKCU）
+        #[arg(long)]
+        ntuser: Option<PathBuf>,
+
+        /// UsrClass.dat（HKCU\Software\Classes 与 HKCR 的用户部分）
+        #[arg(long)]
+        usrclass: Option<PathBuf>,
+    },
+
+    /// 按配置生成下一版本的根元数据（root.json 与 <版本>.root.json）
+    FeedRoot {
+        /// 根元数据配置（各角色的公钥与门限）
+        #[arg(long, default_value = "./feed/root.yaml")]
+        config: PathBuf,
+
+        /// 元数据目录
+        #[arg(long, default_value = "./feed")]
+        dir: PathBuf,
+
+        /// 根私钥，可重复指定；轮换时需同时满足新旧两个版本的门限
+        #[arg(long = "sign-key", required = true)]
+        sign_keys: Vec<PathBuf>,
+    },
+
+    /// 为发布文件生成 targets.json 与 timestamp.json
+    FeedPublish {
+        /// 发布目录，需已包含 root.json
+        #[arg(long, default_value = "./dist")]
+        dir: PathBuf,
+
+        /// 发布文件名（位于发布目录中），可重复指定
+        #[arg(long = "target", default_value = "rules.bin")]
+        targets: Vec<String>,
+
+        /// targets 私钥，可重复指定
+        #[arg(long = "targets-key", required = true)]
+        targets_keys: Vec<PathBuf>,
+
+        /// timestamp 私钥，可重复指定
+        #[arg(long = "timestamp-key", required = true)]
+        timestamp_keys: Vec<PathBuf>,
+
+        /// targets 与 timestamp 的版本，默认在目录中已有的版本上加一；必须大于已发布的版本
+        #[arg(long)]
+        metadata_version: Option<u64>,
+
+        /// targets.json 有效天数
+        #[arg(long, default_value_t = 90)]
+        targets_expires_in_days: u64,
+
+        /// timestamp.json 有效天数，需在过期前重新发布
+        #[arg(long, default_value_t = 7)]
+        timestamp_expires_in_days: u64,
+    },
+
+    /// 从内置根元数据出发校验镜像中的元数据与发布文件
+    FeedVerify {
+        /// 镜像目录
+        #[arg(long, default_value = "./dist")]
+        mirror: PathBuf,
+
+        /// 内置的根元数据
+        #[arg(long)]
+        root: Option<PathBuf>,
+
+        /// 信任状态文件，存在时代替 --root 作为起点，校验通过后更新
+        #[arg(long)]
+        state: Option<PathBuf>,
+
+        /// 校验指定发布文件
+        #[arg(long)]
+        target: Option<String>,
+
+        /// 校验通过后保存发布文件
+        #[arg(long, requires = "target")]
+        output: Option<PathBuf>,
+    },
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let out = Output::new(args.format);
+
+    match run(args.command, out) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            if out.is_json() {
+                out.error(&e);
+            } else {
+                eprintln!("Error: {:?}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Commands, out: Output) -> Result<()> {
+    match command {
+        Commands::Pack { input, output, compress, encoding, sign_key, serial, expires_in_days, reproducible } => {
+            let options = PackOptions {
+                compression: compress.parse()?,
+                encoding: encoding.parse()?,
+                serial,
+                created_at: None,
+                expires_in_days,
+                reproducible,
+            };
+            pack_rules(&input, &output, &options, sign_key.as_deref(), out)
+        }
+        Commands::Keygen { private, public } => {
+            generate_keys(&private, &public, out)
+        }
+        Commands::Unpack { input, output, normalize, id, category, overwrite, no_clobber } => {
+            let filter = match (id, category) {
+                (Some(id), _) => RuleFilter::Id(id),
+                (None, Some(category)) => RuleFilter::Category(category),
+                (None, None) => RuleFilter::All,
+            };
+            let existing = match (overwrite, no_clobber) {
+                (true, _) => Existing::Overwrite,
+                (_, true) => Existing::Skip,
+                _ => Existing::Refuse,
+            };
+            unpack_rules(&input, &output, normalize, &filter, existing, out)
+        }
+        Commands::Info { input } => {
+            show_info(&input, out)
+        }
+        Commands::Verify { input, pubkey, last_serial } => {
+            verify_package(&input, pubkey.as_deref(), last_serial, out)
+        }
+        Commands::Validate { input } => {
+            validate_rules(&input, out)
+        }
+        Commands::Test { input, id } => {
+            test_rules(&input, id.as_deref(), out)
+        }
+        Commands::Scan { rules, root, user } => {
+            scan::scan_rules(&rules, &root, user.as_deref(), out)
+        }
+        Commands::Simulate { rules, fixture, snapshot, update } => {
+            simulate::simulate(&rules, &fixture, snapshot.as_deref(), update, out)
+        }
+        Commands::ScanRegistry { rules, software, system, ntuser, usrclass } => {
+            let hives = scan::HivePaths { software, system, ntuser, usrclass };
+            scan::scan_registry(&rules, &hives, out)
+        }
+        Commands::FeedRoot { config, dir, sign_keys } => {
+            feed::feed_root(&config, &dir, &sign_keys, out)
+        }
+        Commands::FeedPublish {
+            dir,
+            targets,
+            targets_keys,
+            timestamp_keys,
+            metadata_version,
+            targets_expires_in_days,
+            timestamp_expires_in_days,
+        } => feed::feed_publish(
+            &dir,
+            &targets,
+            &targets_keys,
+            &timestamp_keys,
+            feed::PublishOptions {
+                version: metadata_version,
+                targets_days: targets_expires_in_days,
+                timestamp_days: timestamp_expires_in_days,
+            },
+            out,
+        ),
+        Commands::FeedVerify { mirror, root, state, target, output } => {
+            feed::feed_verify(&mirror, root.as_deref(), state.as_deref(), target.as_deref(), output.as_deref(), out)
+        }
+    }
+}
+
+/// 打包结果
+#[derive(Serialize)]
+struct PackReport<'a> {
+    output: &'a Path,
+    rule_count: usize,
+    categories: &'a [String],
+    serial: u64,
+    created_at: u64,
+    expires_at: Option<u64>,
+    compression: String,
+    encoding: String,
+    original_size: u64,
+    compressed_size: usize,
+    header_digest: String,
+    /// 签名公钥指纹，未签名时为 null
+    signing_key: Option<String>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl<'a> PackReport<'a> {
+    fn new(
+        output: &'a Path,
+        packed: &'a Packed,
+        options: &PackOptions,
+        signing_key: Option<String>,
+        warnings: Vec<Diagnostic>,
+    ) -> Self {
+        let header = &packed.header;
+        PackReport {
+            output,
+            rule_count: header.rule_count,
+            categories: &header.categories,
+            serial: header.serial,
+            created_at: header.created_at,
+            expires_at: header.expires_at,
+            compression: options.compression.to_string(),
+            encoding: options.encoding.to_string(),
+            original_size: packed.original_size,
+            compressed_size: packed.data.len(),
+            header_digest: hex(&packed.header_digest),
+            signing_key,
+            warnings,
+        }
+    }
+}
+
+/// 打包规则
+fn pack_rules(
+    input: &PathBuf,
+    output: &PathBuf,
+    options: &PackOptions,
+    sign_key: Option<&Path>,
+    out: Output,
+) -> Result<()> {
+    say!(out, "打包规则: {:?}", input);
+
+    let signer = sign_key.map(signing::load_signing_key).transpose()?;
+
+    // 创建输出目录
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // 加载、校验并打包，规则文件已按路径排序，相同输入总是得到相同的规则包
+    let mut packed = pack::pack(input, options, signer.as_ref()).map_err(|e| out.failure(e))?;
+    let warnings = out.diagnostics(std::mem::take(&mut packed.warnings))?;
+    for file in &packed.files {
+        say!(out, "  处理: {:?}", file.path);
+    }
+
+    // 写入输出文件
+    fs::write(output, &packed.data)
+        .with_context(|| format!("写入规则包失败: {}", output.display()))?;
+    let signing_key = signer.as_ref().map(|key| signing::fingerprint(&key.verifying_key()));
+    let report = PackReport::new(output, &packed, options, signing_key, warnings);
+
+    say!(out, "已生成规则包: {:?}", output);
+    say!(out, "规则数量: {}", report.rule_count);
+    say!(out, "内容序号: {}", report.serial);
+    say!(out, "过期时间: {}", expiry_text(report.expires_at));
+    say!(out, "编码: {}", report.encoding);
+    say!(out, "压缩前大小: {} bytes", report.original_size);
+    say!(out, "压缩后大小: {} bytes", report.compressed_size);
+    say!(out, "创建时间: {}", format_time(report.created_at));
+    say!(out, "包头摘要: {}", report.header_digest);
+    match &report.signing_key {
+        Some(fingerprint) => say!(out, "签名: Ed25519 (公钥 {})", fingerprint),
+        None => say!(out, "签名: 未签名"),
+    }
+
+    out.json(&report)
+}
+
+/// 过期时间说明
+fn expiry_text(expires_at: Option<u64>) -> String {
+    expires_at.map_or_else(|| "永不过期".to_string(), format_time)
+}
+
+/// 生成的密钥对
+#[derive(Serialize)]
+struct KeygenReport<'a> {
+    private: &'a Path,
+    public: &'a Path,
+    fingerprint: String,
+}
+
+/// 生成签名密钥对
+fn generate_keys(private: &Path, public: &Path, out: Output) -> Result<()> {
+    for path in [private, public] {
+        if path.exists() {
+            anyhow::bail!("文件已存在，拒绝覆盖: {}", path.display());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+
+    let key = signing::generate_key();
+    write_private_file(private, &signing::signing_key_pem(&key)?)
+        .with_context(|| format!("写入私钥失败: {}", private.display()))?;
+    fs::write(public, signing::verifying_key_pem(&key.verifying_key())?)
+        .with_context(|| format!("写入公钥失败: {}", public.display()))?;
+    let fingerprint = signing::fingerprint(&key.verifying_key());
+
+    say!(out, "已生成私钥: {:?}", private);
+    say!(out, "已生成公钥: {:?}", public);
+    say!(out, "公钥指纹: {}", fingerprint);
+    say!(out, "请妥善保管私钥，不要提交到仓库");
+
+    out.json(&KeygenReport { private, public, fingerprint })
+}
+
+/// 写入仅当前用户可读的文件
+fn write_private_file(path: &Path, content: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    io::Write::write_all(&mut options.open(path)?, content.as_bytes())
+}
+
+/// 解包结果
+#[derive(Serialize)]
+struct UnpackReport<'a> {
+    output: &'a Path,
+    /// 写入的文件
+    extracted: Vec<PathBuf>,
+    /// 已存在而跳过的文件
+    skipped: Vec<PathBuf>,
+}
+
+/// 解包规则
+fn unpack_rules(
+    input: &PathBuf,
+    output: &PathBuf,
+    normalize: bool,
+    filter: &RuleFilter,
+    existing: Existing,
+    out: Output,
+) -> Result<()> {
+    say!(out, "解包规则: {:?}", input);
+
+    // 按目录只读取需要的规则
+    let mut reader = PackageReader::open(input)
+        .with_context(|| format!("无法读取规则包: {}", input.display()))?;
+    let rules = match filter {
+        RuleFilter::All => reader.rules(),
+        RuleFilter::Id(id) => match reader.rule(id) {
+            Ok(Some(rule)) => Ok(vec![rule]),
+            Ok(None) => anyhow::bail!("规则包中没有规则: {}", id),
+            Err(e) => Err(e),
+        },
+        RuleFilter::Category(category) => reader.category(category),
+    }
+    .with_context(|| format!("无法读取规则包: {}", input.display()))?;
+    if rules.is_empty() {
+        anyhow::bail!("规则包中没有符合条件的规则");
+    }
+
+    // 写入前检查全部路径，任何一条不安全都不写入
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    let mut files = Vec::with_capacity(rules.len());
+    for rule in &rules {
+        let relative = rule.metadata.relative_path()?;
+        let key = relative.to_string_lossy().to_lowercase();
+        if let Some(other) = seen.insert(key, &rule.metadata.id) {
+            anyhow::bail!("规则 {} 与 {} 的解包路径相同: {}", other, rule.metadata.id, relative.display());
+        }
+
+        let content = if normalize {
+            let model = rule.to_rule()
+                .with_context(|| format!("规则 {} 无法还原为规则模型", rule.metadata.id))?;
+            serde_yaml::to_string(&model)?
+        } else {
+            rule.yaml_content.clone()
+        };
+        files.push((relative, content));
+    }
+
+    // 创建输出目录
+    fs::create_dir_all(output)?;
+    let root = output.canonicalize()?;
+
+    // 已存在的分类目录（如符号链接）指向输出目录之外时不写入任何文件
+    for category_dir in files.iter().filter_map(|(r, _)| r.parent()).map(|c| output.join(c)) {
+        if fs::symlink_metadata(&category_dir).is_err() {
+            continue;
+        }
+        let resolved = category_dir.canonicalize()
+            .with_context(|| format!("无法解析分类目录: {}", category_dir.display()))?;
+        if !resolved.starts_with(&root) {
+            anyhow::bail!("分类目录指向输出目录之外: {}", category_dir.display());
+        }
+    }
+
+    if existing == Existing::Refuse {
+        if let Some(path) = files.iter().map(|(r, _)| output.join(r)).find(|p| fs::symlink_metadata(p).is_ok()) {
+            anyhow::bail!("文件已存在: {}（使用 --overwrite 覆盖或 --no-clobber 跳过）", path.display());
+        }
+    }
+
+    // 写入规则文件
+    let mut extracted = Vec::new();
+    let mut skipped = Vec::new();
+    for (relative, content) in &files {
+        let output_path = output.join(relative);
+        let category_dir = output_path.parent().unwrap_or(output);
+        fs::create_dir_all(category_dir)?;
+        if !category_dir.canonicalize()?.starts_with(&root) {
+            anyhow::bail!("分类目录指向输出目录之外: {}", category_dir.display());
+        }
+
+        if write_unpacked(&output_path, content, existing)? {
+            say!(out, "  提取: {:?}", output_path);
+            extracted.push(output_path);
+        } else {
+            say!(out, "  跳过已存在: {:?}", output_path);
+            skipped.push(output_path);
+        }
+    }
+
+    say!(out, "已解包到: {:?}", output);
+    say!(out, "规则数量: {}", extracted.len());
+    if !skipped.is_empty() {
+        say!(out, "跳过: {}", skipped.len());
+    }
+
+    out.json(&UnpackReport { output, extracted, skipped })
+}
+
+/// 写入解包的规则文件，返回是否写入
+fn write_unpacked(path: &Path, content: &str, existing: Existing) -> Result<bool> {
+    if existing == Existing::Overwrite {
+        if fs::symlink_metadata(path).is_ok_and(|m| !m.is_file()) {
+            anyhow::bail!("拒绝覆盖非普通文件: {}", path.display());
+        }
+        fs::write(path, content).with_context(|| format!("写入失败: {}", path.display()))?;
+        return Ok(true);
+    }
+
+    match fs::OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(mut file) => {
+            io::Write::write_all(&mut file, content.as_bytes())
+                .with_context(|| format!("写入失败: {}", path.display()))?;
+            Ok(true)
+        }
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists && existing == Existing::Skip => Ok(false),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            anyhow::bail!("文件已存在: {}（使用 --overwrite 覆盖或 --no-clobber 跳过）", path.display())
+        }
+        Err(e) => Err(e).with_context(|| format!("写入失败: {}", path.display())),
+    }
+}
+
+/// 规则包信息
+#[derive(Serialize)]
+struct InfoReport<'a> {
+    file: &'a Path,
+    size: u64,
+    version: u32,
+    serial: u64,
+    created_at: u64,
+    expires_at: Option<u64>,
+    rule_count: usize,
+    compression: &'a str,
+    encoding: &'a str,
+    categories: &'a [String],
+    payload_len: u64,
+    payload_digest: String,
+    header_digest: String,
+    signature: SignatureStatus,
+    /// 全部摘要是否相符
+    intact: bool,
+    rules: Vec<InfoRule<'a>>,
+}
+
+/// 规则包信息中的规则
+#[derive(Serialize)]
+struct InfoRule<'a> {
+    id: &'a str,
+    name: &'a str,
+    risk: &'a str,
+    category: &'a str,
+    offset: u64,
+    length: u64,
+    digest: String,
+    /// 完整的规则元数据，规则包损坏时为 null
+    metadata: Option<RuleMetadata>,
+}
+
+impl<'a> InfoReport<'a> {
+    fn new(file: &'a Path, size: u64, report: &'a VerifyReport, mut metadata: HashMap<String, RuleMetadata>) -> Self {
+        let header = &report.header;
+        InfoReport {
+            file,
+            size,
+            version: header.version,
+            serial: header.serial,
+            created_at: header.created_at,
+            expires_at: header.expires_at,
+            rule_count: header.rule_count,
+            compression: &header.compression,
+            encoding: &header.encoding,
+            categories: &header.categories,
+            payload_len: header.payload_len,
+            payload_digest: hex(&header.payload_digest),
+            header_digest: hex(&report.header_digest),
+            signature: report.signature,
+            intact: report.is_ok(),
+            rules: header.entries.iter().map(|entry| InfoRule {
+                id: &entry.id,
+                name: &entry.name,
+                risk: &entry.risk,
+                category: &entry.category,
+                offset: entry.offset,
+                length: entry.length,
+                digest: hex(&entry.digest),
+                metadata: metadata.remove(&entry.id),
+            }).collect(),
+        }
+    }
+}
+
+/// 显示规则包信息（校验摘要；文本格式下不解压规则数据）
+fn show_info(input: &PathBuf, out: Output) -> Result<()> {
+    say!(out, "规则包信息: {:?}", input);
+
+    let (report, size) = read_verify_report(input, None)?;
+    let header = &report.header;
+
+    say!(out, "版本: {}", header.version);
+    say!(out, "内容序号: {}", header.serial);
+    say!(out, "创建时间: {}", header.created_at);
+    say!(out, "过期时间: {}", expiry_text(header.expires_at));
+    say!(out, "规则数量: {}", header.rule_count);
+    say!(out, "压缩算法: {}", header.compression);
+    say!(out, "编码: {}", header.encoding);
+    say!(out, "分类: {:?}", header.categories);
+    say!(out, "大小: {} bytes", size);
+    say!(out, "摘要: {}", hex(&header.payload_digest));
+    say!(out, "包头摘要: {}", hex(&report.header_digest));
+    say!(out, "签名: {}", signature_text(report.signature));
+
+    say!(out, "\n规则列表:");
+    for entry in &header.entries {
+        say!(out, "  - [{}] {} (风险: {})", entry.id, entry.name, entry.risk);
+    }
+
+    if out.is_json() {
+        // 完整的元数据在规则数据中，需要解压
+        let mut metadata: HashMap<String, RuleMetadata> = HashMap::new();
+        if report.is_ok() {
+            let rules = PackageReader::open(input)
+                .and_then(|mut reader| reader.rules())
+                .with_context(|| format!("无法读取规则包: {}", input.display()))?;
+            metadata.extend(rules.into_iter().map(|r| (r.metadata.id.clone(), r.metadata)));
+        }
+        out.json(&InfoReport::new(input, size, &report, metadata))?;
+    }
+
+    if !report.is_ok() {
+        return Err(Reported("规则包已损坏，请运行 verify 查看详情".to_string()).into());
+    }
+
+    Ok(())
+}
+
+/// 校验结果
+#[derive(Serialize)]
+struct VerifyOutput<'a> {
+    file: &'a Path,
+    /// 校验签名所用公钥的指纹
+    key: Option<String>,
+    signature: SignatureStatus,
+    serial: u64,
+    expires_at: Option<u64>,
+    payload_intact: bool,
+    payload_len: u64,
+    expected_payload_len: u64,
+    rules: Vec<VerifyRule<'a>>,
+    ok: bool,
+    /// 失败原因
+    error: Option<String>,
+}
+
+/// 校验结果中的规则
+#[derive(Serialize)]
+struct VerifyRule<'a> {
+    id: &'a str,
+    digest: String,
+    intact: bool,
+}
+
+/// 校验规则包完整性与签名
+fn verify_package(input: &PathBuf, pubkey: Option<&Path>, last_serial: Option<u64>, out: Output) -> Result<()> {
+    say!(out, "校验规则包: {:?}", input);
+
+    let key = pubkey.map(signing::load_verifying_key).transpose()?;
+    if let Some(key) = &key {
+        say!(out, "公钥: {}", signing::fingerprint(key));
+    }
+    let (report, _) = read_verify_report(input, key.as_ref())?;
+    let header = &report.header;
+
+    say!(out, "包头: 正常");
+    say!(out, "签名: {}", signature_text(report.signature));
+    say!(out, "内容序号: {}", header.serial);
+    say!(out, "过期时间: {}", expiry_text(header.expires_at));
+    if report.payload_intact {
+        say!(out, "规则数据: 正常 ({} bytes)", report.payload_len);
+    } else if report.payload_len < header.payload_len {
+        say!(out, "规则数据: 不完整 ({}/{} bytes)", report.payload_len, header.payload_len);
+    } else {
+        say!(out, "规则数据: 已损坏 (摘要不符)");
+    }
+
+    for entry in &header.entries {
+        let status = if report.damaged.contains(&entry.id) { "损坏" } else { "正常" };
+        say!(out, "  [{}] {} {}", status, entry.id, hex(&entry.digest));
+    }
+
+    let failure = if !report.damaged.is_empty() {
+        Some(format!("校验失败: {}/{} 个规则损坏", report.damaged.len(), header.entries.len()))
+    } else if !report.payload_intact {
+        Some("校验失败: 规则数据已损坏".to_string())
+    } else {
+        match (report.signature, &key) {
+            (SignatureStatus::Invalid, _) => Some("校验失败: 签名无效，规则包可能被篡改".to_string()),
+            (SignatureStatus::Unsigned, Some(_)) => Some("校验失败: 规则包未签名".to_string()),
+            (_, Some(key)) => {
+                let policy = TrustPolicy { last_serial, ..TrustPolicy::new(*key) };
+                policy.check_freshness(header).err().map(|e| format!("校验失败: {}", e))
+            }
+            (_, None) => None,
+        }
+    };
+    if failure.is_none() {
+        say!(out, "校验通过: {} 个规则", header.entries.len());
+    }
+
+    out.json(&VerifyOutput {
+        file: input,
+        key: key.as_ref().map(signing::fingerprint),
+        signature: report.signature,
+        serial: header.serial,
+        expires_at: header.expires_at,
+        payload_intact: report.payload_intact,
+        payload_len: report.payload_len,
+        expected_payload_len: header.payload_len,
+        rules: header.entries.iter().map(|entry| VerifyRule {
+            id: &entry.id,
+            digest: hex(&entry.digest),
+            intact: !report.damaged.contains(&entry.id),
+        }).collect(),
+        ok: failure.is_none(),
+        error: failure.clone(),
+    })?;
+
+    match failure {
+        Some(failure) => Err(Reported(failure).into()),
+        None => Ok(()),
+    }
+}
+
+/// 读取规则包并校验全部摘要（提供公钥时校验签名），返回校验结果与文件大小
+fn read_verify_report(input: &Path, key: Option<&VerifyingKey>) -> Result<(VerifyReport, u64)> {
+    let file = fs::File::open(input)
+        .with_context(|| format!("读取规则包失败: {}", input.display()))?;
+    let size = file.metadata()?.len();
+    let report = package::verify_package(&mut io::BufReader::new(file), key)
+        .with_context(|| format!("无法读取规则包: {}", input.display()))?;
+
+    Ok((report, size))
+}
+
+/// 签名状态说明
+fn signature_text(status: SignatureStatus) -> &'static str {
+    match status {
+        SignatureStatus::Unsigned => "未签名",
+        SignatureStatus::Unchecked => "已签名（未提供公钥，未校验）",
+        SignatureStatus::Valid => "有效",
+        SignatureStatus::Invalid => "无效",
+    }
+}
+
+/// 读取完整的规则包
+fn read_package(input: &Path) -> Result<RulesPackage> {
+    let file = fs::File::open(input)
+        .with_context(|| format!("读取规则包失败: {}", input.display()))?;
+    let package = package::read_package(&mut io::BufReader::new(file))
+        .with_context(|| format!("无法读取规则包: {}", input.display()))?;
+
+    Ok(package)
+}
+
+/// 加载规则：目录按YAML源文件加载（需通过校验），文件按规则包读取
+fn load_rule_set(input: &Path, out: Output) -> Result<Vec<SerializedRule>> {
+    if input.is_dir() {
+        let (files, diagnostics) = load_rules(input)?;
+        out.diagnostics(diagnostics)?;
+        Ok(files.iter().map(RuleFile::to_serialized).collect())
+    } else {
+        Ok(read_package(input)?.rules)
+    }
+}
+
+/// 校验结果
+#[derive(Serialize)]
+struct ValidateReport {
+    files: Vec<PathBuf>,
+    warnings: Vec<Diagnostic>,
+}
+
+/// 校验规则
+fn validate_rules(input: &PathBuf, out: Output) -> Result<()> {
+    say!(out, "校验规则: {:?}", input);
+
+    let (rules, diagnostics) = load_rules(input)?;
+    for rule in &rules {
+        say!(out, "  检查: {:?}", rule.path);
+    }
+    let warnings = out.diagnostics(diagnostics)?;
+
+    say!(out, "校验通过: {} 个规则文件", rules.len());
+
+    out.json(&ValidateReport { files: rules.into_iter().map(|r| r.path).collect(), warnings })
+}
+
+/// 测试结果
+#[derive(Serialize)]
+struct TestReport<'a> {
+    rules: Vec<TestedRule<'a>>,
+    passed: usize,
+    failed: usize,
+    /// 没有测试样例的规则数
+    untested: usize,
+}
+
+/// 单个规则的测试结果
+#[derive(Serialize)]
+struct TestedRule<'a> {
+    id: &'a str,
+    name: &'a str,
+    outcomes: Vec<TestedSample>,
+}
+
+/// 单个样例的测试结果
+#[derive(Serialize)]
+struct TestedSample {
+    #[serde(flatten)]
+    outcome: SampleOutcome,
+    passed: bool,
+}
+
+/// 运行规则自带的测试样例
+fn test_rules(input: &Path, id: Option<&str>, out: Output) -> Result<()> {
+    say!(out, "测试规则: {:?}", input);
+
+    let (files, diagnostics) = load_rules(input)?;
+    out.diagnostics(diagnostics)?;
+
+    let files: Vec<&RuleFile> = files.iter().filter(|f| id.is_none_or(|id| f.rule.id == id)).collect();
+    if let Some(id) = id {
+        if files.is_empty() {
+            anyhow::bail!("未找到规则: {}", id);
+        }
+    }
+
+    let mut report = TestReport { rules: Vec::new(), passed: 0, failed: 0, untested: 0 };
+    for file in files {
+        let rule = &file.rule;
+        if rule.tests.is_empty() {
+            report.untested += 1;
+            continue;
+        }
+
+        say!(out, "\n[{}] {}", rule.id, rule.name);
+        let outcomes = sample::run_samples(rule)
+            .with_context(|| format!("规则 {} 的匹配模式无效", rule.id))?;
+        let mut tested = TestedRule { id: &rule.id, name: &rule.name, outcomes: Vec::new() };
+        for outcome in outcomes {
+            let status = if outcome.passed() { "通过" } else { "失败" };
+            let expectation = if outcome.expected { "应匹配" } else { "不应匹配" };
+            match &outcome.matched_by {
+                Some(by) => say!(out, "  {} {}{}: {} <- {}", status, outcome.kind, expectation, outcome.sample, by),
+                None => say!(out, "  {} {}{}: {}", status, outcome.kind, expectation, outcome.sample),
+            }
+            if outcome.passed() {
+                report.passed += 1;
+            } else {
+                report.failed += 1;
+            }
+            tested.outcomes.push(TestedSample { passed: outcome.passed(), outcome });
+        }
+        report.rules.push(tested);
+    }
+
+    say!(out, "\n结果: {} 个样例通过, {} 个失败, {} 个规则没有测试样例", report.passed, report.failed, report.untested);
+    out.json(&report)?;
+    if report.failed > 0 {
+        return Err(Reported(format!("测试失败: {} 个样例未通过", report.failed)).into());
+    }
+
+    Ok(())
+}
+
+/// 解包时选取的规则
+enum RuleFilter {
+    All,
+    Id(String),
+    Category(String),
+}
+
+/// 解包时已存在文件的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Existing {
+    /// 报错
+    Refuse,
+    Overwrite,
+    Skip,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winclean_rules::package::{Compression, Encoding, PackageHeader};
+    use winclean_rules::rule::Rule;
+
+    fn rule_yaml(id: &str) -> String {
+        format!(
+            "id: {id}\nname: 演示 {id}\nrisk: high\nupdate: 2026-01-01\nmatch:\n  path:\n    - \"%TEMP%\\\\{id}\"\n",
+            id = id
+        )
+    }
+
+    /// 写出包含 apps/alpha.yaml 与 system/beta.yaml 的规则包
+    fn write_test_package(dir: &Path) -> PathBuf {
+        let rules: Vec<SerializedRule> = [("alpha", "apps"), ("beta", "system")]
+            .iter()
+            .map(|(id, category)| {
+                let yaml = rule_yaml(id);
+                let rule: Rule = serde_yaml::from_str(&yaml).unwrap();
+                SerializedRule::new(&rule, category, &format!("{}.yaml", id), &yaml)
+            })
+            .collect();
+        let header = PackageHeader::new(&rules, 20260101, 1_700_000_000, None);
+        let (data, _) = package::write_package(&header, &rules, Compression::None, Encoding::Bincode, None).unwrap();
+        let path = dir.join("rules.bin");
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn unpack(input: &PathBuf, output: &PathBuf, existing: Existing) -> Result<()> {
+        unpack_rules(input, output, false, &RuleFilter::All, existing, Output::new(Format::Json))
+    }
+
+    /// 目录下的普通文件（不跟随符号链接），按相对路径排序
+    fn files_under(dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            for entry in fs::read_dir(&current).unwrap() {
+                let path = entry.unwrap().path();
+                let meta = fs::symlink_metadata(&path).unwrap();
+                if meta.is_dir() {
+                    pending.push(path);
+                } else if meta.is_file() {
+                    files.push(path.strip_prefix(dir).unwrap().to_path_buf());
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn unpacks_every_rule() {
+        let temp = tempfile::tempdir().unwrap();
+        let input = write_test_package(temp.path());
+        let output = temp.path().join("out");
+
+        unpack(&input, &output, Existing::Refuse).unwrap();
+        assert_eq!(files_under(&output), [Path::new("apps/alpha.yaml"), Path::new("system/beta.yaml")]);
+        assert_eq!(fs::read_to_string(output.join("apps/alpha.yaml")).unwrap(), rule_yaml("alpha"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_category_fails_without_writing() {
+        let temp = tempfile::tempdir().unwrap();
+        let input = write_test_package(temp.path());
+        let outside = temp.path().join("outside");
+        let output = temp.path().join("out");
+        fs::create_dir_all(&outside).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        std::os::unix::fs::symlink(&outside, output.join("system")).unwrap();
+
+        for existing in [Existing::Refuse, Existing::Skip, Existing::Overwrite] {
+            let error = unpack(&input, &output, existing).unwrap_err();
+            assert!(error.to_string().contains("分类目录指向输出目录之外"), "{:#}", error);
+            // apps 排在 system 之前，也不能先写入
+            assert!(files_under(&output).is_empty());
+            assert!(files_under(&outside).is_empty());
+        }
+    }
+
+    #[test]
+    fn existing_file_fails_without_writing() {
+        let temp = tempfile::tempdir().unwrap();
+        let input = write_test_package(temp.path());
+        let output = temp.path().join("out");
+        fs::create_dir_all(output.join("system")).unwrap();
+        fs::write(output.join("system/beta.yaml"), "local edit\n").unwrap();
+
+        let error = unpack(&input, &output, Existing::Refuse).unwrap_err();
+        assert!(error.to_string().contains("文件已存在"), "{:#}", error);
+        assert_eq!(files_under(&output), [Path::new("system/beta.yaml")]);
+        assert_eq!(fs::read_to_string(output.join("system/beta.yaml")).unwrap(), "local edit\n");
+    }
+
+    #[test]
+    fn no_clobber_keeps_existing_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let input = write_test_package(temp.path());
+        let output = temp.path().join("out");
+        fs::create_dir_all(output.join("system")).unwrap();
+        fs::write(output.join("system/beta.yaml"), "local edit\n").unwrap();
+
+        unpack(&input, &output, Existing::Skip).unwrap();
+        assert_eq!(files_under(&output), [Path::new("apps/alpha.yaml"), Path::new("system/beta.yaml")]);
+        assert_eq!(fs::read_to_string(output.join("system/beta.yaml")).unwrap(), "local edit\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn no_clobber_does_not_follow_existing_symlink() {
+        let temp = tempfile::tempdir().unwrap();
+        let input = write_test_package(temp.path());
+        let target = temp.path().join("target.yaml");
+        let output = temp.path().join("out");
+        fs::create_dir_all(output.join("system")).unwrap();
+        std::os::unix::fs::symlink(&target, output.join("system/beta.yaml")).unwrap();
+
+        unpack(&input, &output, Existing::Skip).unwrap();
+        assert!(!target.exists());
+        let error = unpack(&input, &output, Existing::Overwrite).unwrap_err();
+        assert!(error.to_string().contains("拒绝覆盖非普通文件"), "{:#}", error);
+        assert!(!target.exists());
+    }
+
+    /// 打包 apps/alpha.yaml（含一条重复路径警告）与 system/beta.yaml
+    fn pack_test_rules(dir: &Path) -> (Packed, PackOptions) {
+        let rules = dir.join("rules");
+        fs::create_dir_all(rules.join("apps")).unwrap();
+        fs::create_dir_all(rules.join("system")).unwrap();
+        fs::write(rules.join("apps/alpha.yaml"), rule_yaml("alpha") + "    - \"%TEMP%\\\\alpha\"\n").unwrap();
+        fs::write(rules.join("system/beta.yaml"), rule_yaml("beta")).unwrap();
+        let options = PackOptions { created_at: Some(1_700_000_000), expires_in_days: Some(30), ..PackOptions::default() };
+        (pack::pack(&rules, &options, None).unwrap(), options)
+    }
+
+    #[test]
+    fn pack_report_json_shape() {
+        let temp = tempfile::tempdir().unwrap();
+        let (packed, options) = pack_test_rules(temp.path());
+        let warnings = packed.warnings.clone();
+        let report = PackReport::new(Path::new("dist/rules.bin"), &packed, &options, Some("ab12".to_string()), warnings);
+        let file = temp.path().join("rules/apps/alpha.yaml");
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "output": "dist/rules.bin",
+                "rule_count": 2,
+                "categories": ["apps", "system"],
+                "serial": 20231114,
+                "created_at": 1_700_000_000,
+                "expires_at": 1_700_000_000 + 30 * 86400,
+                "compression": "zstd",
+                "encoding": "bincode",
+                "original_size": packed.original_size,
+                "compressed_size": packed.data.len(),
+                "header_digest": hex(&packed.header_digest),
+                "signing_key": "ab12",
+                "warnings": [{
+                    "severity": "warning",
+                    "file": file,
+                    "message": "match.path[1] 与前面的路径重复",
+                    "span": {"line": 8, "column": 7, "len": 15},
+                    "excerpt": "    - \"%TEMP%\\\\alpha\"",
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn info_report_json_shape() {
+        let temp = tempfile::tempdir().unwrap();
+        let (packed, _) = pack_test_rules(temp.path());
+        let input = temp.path().join("rules.bin");
+        fs::write(&input, &packed.data).unwrap();
+
+        let (report, size) = read_verify_report(&input, None).unwrap();
+        let rules = PackageReader::open(&input).unwrap().rules().unwrap();
+        let metadata = rules.into_iter().map(|r| (r.metadata.id.clone(), r.metadata)).collect();
+        let header = &report.header;
+        let rule = |i: usize, category: &str| {
+            let entry = &header.entries[i];
+            serde_json::json!({
+                "id": entry.id,
+                "name": format!("演示 {}", entry.id),
+                "risk": "high",
+                "category": category,
+                "offset": entry.offset,
+                "length": entry.length,
+                "digest": hex(&entry.digest),
+                "metadata": {
+                    "id": entry.id,
+                    "name": format!("演示 {}", entry.id),
+                    "risk": "high",
+                    "systeminfo": [],
+                    "update": "2026-01-01",
+                    "author": null,
+                    "description": null,
+                    "category": category,
+                    "filename": format!("{}.yaml", entry.id),
+                },
+            })
+        };
+
+        assert_eq!(
+            serde_json::to_value(InfoReport::new(&input, size, &report, metadata)).unwrap(),
+            serde_json::json!({
+                "file": input,
+                "size": packed.data.len(),
+                "version": header.version,
+                "serial": 20231114,
+                "created_at": 1_700_000_000,
+                "expires_at": 1_700_000_000 + 30 * 86400,
+                "rule_count": 2,
+                "compression": "zstd",
+                "encoding": "bincode",
+                "categories": ["apps", "system"],
+                "payload_len": header.payload_len,
+                "payload_digest": hex(&header.payload_digest),
+                "header_digest": hex(&packed.header_digest),
+                "signature": "unsigned",
+                "intact": true,
+                "rules": [rule(0, "apps"), rule(1, "system")],
+            })
+        );
+
+        // 规则包损坏时不读取规则数据，元数据为 null
+        let mut data = packed.data.clone();
+        *data.last_mut().unwrap() ^= 1;
+        fs::write(&input, data).unwrap();
+        let (report, size) = read_verify_report(&input, None).unwrap();
+        assert!(!report.is_ok());
+        let value = serde_json::to_value(InfoReport::new(&input, size, &report, HashMap::new())).unwrap();
+        assert_eq!(value["intact"], false);
+        assert!(value["rules"].as_array().unwrap().iter().all(|r| r["metadata"].is_null()));
+    }
+
+    /// 同一个规则目录的两份副本，以相反的顺序写入
+    fn write_rule_trees(dir: &Path) -> [PathBuf; 2] {
+        let rules = [("apps", "alpha", "2026-01-03"), ("apps", "gamma", "2025-06-30"), ("system", "beta", "2026-02-14")];
+        let trees = [dir.join("first"), dir.join("second")];
+        for (tree, reversed) in trees.iter().zip([false, true]) {
+            let mut order: Vec<_> = rules.iter().collect();
+            if reversed {
+                order.reverse();
+            }
+            for (category, id, update) in order {
+                fs::create_dir_all(tree.join(category)).unwrap();
+                let yaml = rule_yaml(id).replace("2026-01-01", update);
+                fs::write(tree.join(category).join(format!("{}.yaml", id)), yaml).unwrap();
+            }
+        }
+        trees
+    }
+
+    #[test]
+    fn packs_identical_bytes_twice() {
+        let temp = tempfile::tempdir().unwrap();
+        let trees = write_rule_trees(temp.path());
+        let pack_both = |options: &PackOptions, name: &str| {
+            let outputs: Vec<Vec<u8>> = trees
+                .iter()
+                .map(|tree| {
+                    let output = temp.path().join(format!("{}-{}.bin", name, tree.file_name().unwrap().to_str().unwrap()));
+                    pack_rules(tree, &output, options, None, Output::new(Format::Json)).unwrap();
+                    fs::read(output).unwrap()
+                })
+                .collect();
+            assert_eq!(outputs[0], outputs[1], "{}", name);
+            package::read_package(&mut outputs[0].as_slice()).unwrap().header
+        };
+
+        // 其余测试都显式指定创建时间，不读取该变量
+        std::env::set_var("SOURCE_DATE_EPOCH", "1700000000");
+        let header = pack_both(&PackOptions::default(), "epoch");
+        std::env::remove_var("SOURCE_DATE_EPOCH");
+        assert_eq!((header.created_at, header.serial), (1_700_000_000, 20231114));
+
+        let options = PackOptions { reproducible: true, ..PackOptions::default() };
+        let header = pack_both(&options, "reproducible");
+        // 2026-02-14T00:00:00Z，与当前时间无关
+        assert_eq!((header.created_at, header.serial), (1_771_027_200, 20260214));
+    }
+}
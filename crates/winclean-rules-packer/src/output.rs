@@ -0,0 +1,124 @@
+//! 输出格式
+//! 文本输出供人阅读；JSON 输出供脚本与看板使用，字段名不随界面文字变化
+//!
+//! JSON 格式下每个命令只在标准输出写一个 JSON 文档：成功时为命令的结果，
+//! 失败时为 `{"error": ..., "causes": [...]}`，校验失败时附带 `diagnostics`。
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt;
+use winclean_rules::diagnostic::{Diagnostic, ValidationFailed};
+
+/// 文本格式下输出一行，JSON 格式下忽略
+macro_rules! say {
+    ($out:expr) => {
+        $out.line(format_args!(""))
+    };
+    ($out:expr, $($arg:tt)*) => {
+        $out.line(format_args!($($arg)*))
+    };
+}
+
+/// 输出格式
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+/// 命令输出
+#[derive(Debug, Clone, Copy)]
+pub struct Output {
+    format: Format,
+}
+
+impl Output {
+    pub fn new(format: Format) -> Self {
+        Output { format }
+    }
+
+    pub fn is_json(self) -> bool {
+        self.format == Format::Json
+    }
+
+    /// 文本格式下输出一行
+    pub fn line(self, args: fmt::Arguments<'_>) {
+        if !self.is_json() {
+            println!("{}", args);
+        }
+    }
+
+    /// JSON 格式下输出命令结果
+    pub fn json<T: Serialize>(self, value: &T) -> Result<()> {
+        if self.is_json() {
+            println!("{}", serde_json::to_string_pretty(value)?);
+        }
+        Ok(())
+    }
+
+    /// 输出诊断并在有错误时返回失败，否则返回警告
+    ///
+    /// 文本格式下诊断写入标准错误；JSON 格式下由调用方放进结果，校验失败时随错误输出。
+    pub fn diagnostics(self, diagnostics: Vec<Diagnostic>) -> Result<Vec<Diagnostic>> {
+        if !self.is_json() {
+            for diagnostic in &diagnostics {
+                eprintln!("{}\n", diagnostic);
+            }
+        }
+
+        let warnings = ValidationFailed::check(diagnostics)?;
+        if !warnings.is_empty() && !self.is_json() {
+            eprintln!("校验警告: {} 个警告", warnings.len());
+        }
+
+        Ok(warnings)
+    }
+
+    /// 文本格式下输出校验失败附带的诊断，原样返回错误
+    pub fn failure(self, error: anyhow::Error) -> anyhow::Error {
+        if let Some(failed) = error.downcast_ref::<ValidationFailed>() {
+            if !self.is_json() {
+                for diagnostic in &failed.diagnostics {
+                    eprintln!("{}\n", diagnostic);
+                }
+            }
+        }
+        error
+    }
+
+    /// JSON 格式下输出错误（结果中已包含失败原因的除外）
+    pub fn error(self, error: &anyhow::Error) {
+        if error.downcast_ref::<Reported>().is_some() {
+            return;
+        }
+        let report = ErrorReport {
+            error: error.to_string(),
+            causes: error.chain().skip(1).map(ToString::to_string).collect(),
+            diagnostics: error.downcast_ref::<ValidationFailed>().map(|v| v.diagnostics.as_slice()),
+        };
+        if let Ok(json) = serde_json::to_string_pretty(&report) {
+            println!("{}", json);
+        }
+    }
+}
+
+/// 结果已经输出的失败，JSON 格式下不再单独输出错误
+#[derive(Debug)]
+pub struct Reported(pub String);
+
+impl fmt::Display for Reported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Reported {}
+
+#[derive(Serialize)]
+struct ErrorReport<'a> {
+    error: String,
+    causes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diagnostics: Option<&'a [Diagnostic]>,
+}
@@ -0,0 +1,370 @@
+//! 规则试运行
+//! 在挂载的Windows系统盘或离线注册表配置单元上展开规则，列出将被删除的对象
+
+use super::load_rule_set;
+use crate::output::Output;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use winclean_rules::filesystem::{
+    dedup_matches, find_paths, FileMatch, FileTree, MountedTree, WindowsEnv,
+};
+use winclean_rules::hive::{Hive, HiveSet};
+use winclean_rules::package::SerializedRule;
+use winclean_rules::pattern::PathPattern;
+use winclean_rules::registry::RegistryMatcher;
+
+/// 不属于真实用户的配置目录
+const SYSTEM_PROFILES: &[&str] = &["Public", "Default", "Default User", "All Users"];
+
+/// 试运行结果
+#[derive(Serialize)]
+struct ScanReport<'a> {
+    root: &'a Path,
+    users: Vec<String>,
+    /// 与规则无关的警告（如列出用户时跳过的目录）
+    warnings: Vec<String>,
+    rules: Vec<ScannedRule<'a>>,
+    /// 去重后的合计
+    count: usize,
+    bytes: u64,
+}
+
+/// 单个规则的试运行结果
+#[derive(Serialize)]
+struct ScannedRule<'a> {
+    id: &'a str,
+    name: &'a str,
+    paths: Vec<ScannedPath<'a>>,
+    warnings: Vec<String>,
+    count: usize,
+    bytes: u64,
+}
+
+/// 单个路径模式的匹配结果
+#[derive(Serialize)]
+struct ScannedPath<'a> {
+    pattern: &'a str,
+    matches: Vec<FileMatch>,
+}
+
+/// 试运行规则
+pub fn scan_rules(rules: &Path, root: &Path, user: Option<&str>, out: Output) -> Result<()> {
+    say!(out, "扫描规则: {:?}", rules);
+    say!(out, "挂载目录: {:?}", root);
+
+    if !root.is_dir() {
+        anyhow::bail!("挂载目录不存在: {}", root.display());
+    }
+
+    let rules = load_rule_set(rules, out)?;
+    let report = scan_tree(&rules, root, user, out)?;
+    out.json(&report)
+}
+
+/// 在挂载目录上展开全部规则
+fn scan_tree<'a>(
+    rules: &'a [SerializedRule],
+    root: &'a Path,
+    user: Option<&str>,
+    out: Output,
+) -> Result<ScanReport<'a>> {
+    let tree = MountedTree::new(root);
+
+    let users = match user {
+        Some(user) => vec![user.to_string()],
+        None => list_users(&tree)?,
+    };
+    let user_warnings = tree.take_warnings();
+    for warning in &user_warnings {
+        say!(out, "警告: {}", warning);
+    }
+    let envs: Vec<WindowsEnv> = if users.is_empty() {
+        vec![WindowsEnv::system()]
+    } else {
+        users.iter().map(|u| WindowsEnv::for_user(u)).collect()
+    };
+    say!(out, "用户: {:?}", users);
+
+    let mut all = Vec::new();
+    let mut scanned = Vec::new();
+    for rule in rules {
+        say!(out, "\n[{}] {}", rule.metadata.id, rule.metadata.name);
+
+        let mut paths = Vec::new();
+        let mut warnings = Vec::new();
+        let mut rule_matches = Vec::new();
+        for path in &rule.paths {
+            let pattern = match PathPattern::parse(path) {
+                Ok(pattern) => pattern,
+                Err(e) => {
+                    let warning = format!("路径 `{}` 无效: {}", path, e);
+                    say!(out, "  警告: {}", warning);
+                    warnings.push(warning);
+                    continue;
+                }
+            };
+
+            let mut matches = Vec::new();
+            for env in &envs {
+                match env.expand(&pattern)? {
+                    Some(expanded) => matches.extend(find_paths(&expanded, &tree)?),
+                    None => {
+                        let warning = format!("未知环境变量 %{}%，跳过 `{}`", pattern.env().unwrap_or(""), path);
+                        say!(out, "  警告: {}", warning);
+                        warnings.push(warning);
+                        break;
+                    }
+                }
+            }
+
+            for warning in tree.take_warnings() {
+                say!(out, "  警告: {}", warning);
+                warnings.push(warning);
+            }
+
+            let matches = dedup_matches(matches);
+            if matches.is_empty() {
+                continue;
+            }
+            say!(out, "  {}", path);
+            for m in &matches {
+                let kind = if m.is_dir { "目录" } else { "文件" };
+                say!(out, "    {} {} ({} bytes)", kind, m.path, m.size);
+            }
+            rule_matches.extend(matches.iter().cloned());
+            paths.push(ScannedPath { pattern: path, matches });
+        }
+
+        let rule_matches = dedup_matches(rule_matches);
+        say!(out, "  小计: {} 项, {} bytes", rule_matches.len(), total_bytes(&rule_matches));
+        scanned.push(ScannedRule {
+            id: &rule.metadata.id,
+            name: &rule.metadata.name,
+            paths,
+            warnings,
+            count: rule_matches.len(),
+            bytes: total_bytes(&rule_matches),
+        });
+        all.extend(rule_matches);
+    }
+
+    let all = dedup_matches(all);
+    say!(out, "\n合计: {} 项, {} bytes", all.len(), total_bytes(&all));
+
+    Ok(ScanReport { root, users, warnings: user_warnings, rules: scanned, count: all.len(), bytes: total_bytes(&all) })
+}
+
+/// 列出 Users 下的用户目录
+fn list_users(tree: &MountedTree) -> Result<Vec<String>> {
+    let Some(users_dir) = tree
+        .entries("C:")?
+        .into_iter()
+        .find(|e| e.is_dir && e.name.eq_ignore_ascii_case("Users"))
+    else {
+        return Ok(Vec::new());
+    };
+
+    Ok(tree
+        .entries(&format!("C:\\{}", users_dir.name))?
+        .into_iter()
+        .filter(|e| e.is_dir && !SYSTEM_PROFILES.iter().any(|p| p.eq_ignore_ascii_case(&e.name)))
+        .map(|e| e.name)
+        .collect())
+}
+
+/// 匹配结果的总大小
+pub fn total_bytes(matches: &[FileMatch]) -> u64 {
+    matches.iter().map(|m| m.size).sum()
+}
+
+/// 离线配置单元文件
+pub struct HivePaths {
+    pub software: Option<PathBuf>,
+    pub system: Option<PathBuf>,
+    pub ntuser: Option<PathBuf>,
+    pub usrclass: Option<PathBuf>,
+}
+
+impl HivePaths {
+    /// 读取并按默认布局挂载
+    fn load(&self, out: Output) -> Result<HiveSet> {
+        let open = |path: &PathBuf| {
+            say!(out, "配置单元: {:?}", path);
+            Hive::open(path).with_context(|| format!("读取配置单元失败: {}", path.display()))
+        };
+
+        let mut hives = HiveSet::new();
+        if let Some(path) = &self.software {
+            hives.mount_software(open(path)?);
+        }
+        if let Some(path) = &self.system {
+            hives.mount_system(open(path)?);
+        }
+        if let Some(path) = &self.ntuser {
+            hives.mount_ntuser(open(path)?);
+        }
+        if let Some(path) = &self.usrclass {
+            hives.mount_usrclass(open(path)?);
+        }
+
+        if hives.mount_points().next().is_none() {
+            anyhow::bail!("至少需要指定一个配置单元: --software/--system/--ntuser/--usrclass");
+        }
+
+        Ok(hives)
+    }
+}
+
+/// 注册表试运行结果
+#[derive(Serialize)]
+struct RegistryScanReport<'a> {
+    rules: Vec<RegistryScannedRule<'a>>,
+    /// 命中的注册表条目数
+    matched: usize,
+    total: usize,
+}
+
+/// 单个规则的注册表试运行结果
+#[derive(Serialize)]
+struct RegistryScannedRule<'a> {
+    id: &'a str,
+    name: &'a str,
+    entries: Vec<RegistryScannedEntry<'a>>,
+}
+
+/// 单个注册表条目的命中对象
+#[derive(Serialize)]
+struct RegistryScannedEntry<'a> {
+    path: &'a str,
+    key: &'a str,
+    action: &'a str,
+    targets: Vec<String>,
+}
+
+/// 试运行注册表规则
+pub fn scan_registry(rules: &Path, hives: &HivePaths, out: Output) -> Result<()> {
+    say!(out, "扫描注册表规则: {:?}", rules);
+
+    let rules = load_rule_set(rules, out)?;
+    let tree = hives.load(out)?;
+
+    let mut report = RegistryScanReport { rules: Vec::new(), matched: 0, total: 0 };
+    for rule in &rules {
+        if rule.registry_entries.is_empty() {
+            continue;
+        }
+        say!(out, "\n[{}] {}", rule.metadata.id, rule.metadata.name);
+
+        let mut entries = Vec::new();
+        for entry in &rule.registry_entries {
+            report.total += 1;
+            let matcher = entry
+                .to_rule()
+                .and_then(|r| Ok(RegistryMatcher::new(&r)?))
+                .with_context(|| format!("规则 {} 的注册表条目无效: {}", rule.metadata.id, entry.path))?;
+            let targets = matcher.find(&tree)?;
+
+            let status = if targets.is_empty() { "未命中" } else { "命中" };
+            say!(out, "  [{}] {} {} ({})", status, entry.path, entry.key, entry.action);
+            for target in &targets {
+                say!(out, "    {}", target);
+            }
+            if !targets.is_empty() {
+                report.matched += 1;
+            }
+            entries.push(RegistryScannedEntry {
+                path: &entry.path,
+                key: &entry.key,
+                action: &entry.action,
+                targets: targets.iter().map(ToString::to_string).collect(),
+            });
+        }
+        report.rules.push(RegistryScannedRule { id: &rule.metadata.id, name: &rule.metadata.name, entries });
+    }
+
+    say!(out, "\n覆盖: {}/{} 条注册表规则命中", report.matched, report.total);
+
+    out.json(&report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Format;
+    use std::fs;
+    use winclean_rules::rule::Rule;
+
+    fn rule(id: &str, paths: &[&str]) -> SerializedRule {
+        let paths: Vec<String> = paths.iter().map(|p| format!("    - \"{}\"\n", p.replace('\\', "\\\\"))).collect();
+        let yaml = format!("id: {}\nname: {}\nrisk: high\nupdate: 2026-01-01\nmatch:\n  path:\n{}", id, id, paths.concat());
+        let model: Rule = serde_yaml::from_str(&yaml).unwrap();
+        SerializedRule::new(&model, "apps", &format!("{}.yaml", id), &yaml)
+    }
+
+    fn write(root: &Path, path: &str, size: usize) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; size]).unwrap();
+    }
+
+    #[test]
+    fn totals_bytes_across_users_and_rules() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        write(root, "Users/alice/AppData/Roaming/SoftMgr2/a.txt", 100);
+        write(root, "Users/alice/AppData/Roaming/SoftMgr2/sub/b.bin", 50);
+        write(root, "Users/bob/AppData/Roaming/SoftMgrX/c.txt", 25);
+        write(root, "Users/Public/AppData/Roaming/SoftMgrP/d.txt", 1000);
+        write(root, "Windows/Prefetch/SOFTMGR.EXE-1A2B.pf", 8);
+        let rules = [
+            rule("soft_mgr", &["%APPDATA%\\<SoftMgr.+>", "C:\\Windows\\Prefetch\\<SOFTMGR.+>", "%NOPE%\\x"]),
+            // 与上一个规则重叠，合计中只计一次
+            rule("prefetch", &["C:\\Windows\\Prefetch\\<.+\\.pf>"]),
+        ];
+
+        let report = scan_tree(&rules, root, None, Output::new(Format::Json)).unwrap();
+        assert_eq!(report.users, ["alice", "bob"]);
+        assert!(report.warnings.is_empty());
+
+        let soft_mgr = &report.rules[0];
+        assert_eq!((soft_mgr.count, soft_mgr.bytes), (3, 100 + 50 + 25 + 8));
+        assert_eq!(soft_mgr.paths.len(), 2);
+        assert_eq!(soft_mgr.warnings, ["未知环境变量 %NOPE%，跳过 `%NOPE%\\x`"]);
+        assert_eq!((report.rules[1].count, report.rules[1].bytes), (1, 8));
+        assert_eq!((report.count, report.bytes), (3, 183));
+
+        let report = scan_tree(&rules, root, Some("bob"), Output::new(Format::Json)).unwrap();
+        assert_eq!((report.rules[0].count, report.rules[0].bytes), (2, 25 + 8));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_directory_becomes_rule_warning() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        write(root, "Users/alice/AppData/Roaming/SoftMgr2/a.txt", 100);
+        write(root, "Users/alice/AppData/Roaming/SoftMgr2/locked/b.txt", 50);
+        let locked = root.join("Users/alice/AppData/Roaming/SoftMgr2/locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        let unreadable = fs::read_dir(&locked).is_err();
+
+        let rules = [rule("soft_mgr", &["%APPDATA%\\<SoftMgr.+>"])];
+        let report = scan_tree(&rules, root, None, Output::new(Format::Json));
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // 以 root 运行时权限不生效，只检查扫描没有中断
+        let report = report.unwrap();
+        let rule = &report.rules[0];
+        if unreadable {
+            assert_eq!(rule.bytes, 100);
+            assert_eq!(rule.warnings.len(), 1);
+            assert!(rule.warnings[0].contains("SoftMgr2\\locked"), "{}", rule.warnings[0]);
+        } else {
+            assert_eq!(rule.bytes, 150);
+            assert!(rule.warnings.is_empty());
+        }
+    }
+}
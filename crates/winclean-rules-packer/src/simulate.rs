@@ -0,0 +1,364 @@
+//! 规则模拟
+//! 在夹具描述的虚拟 Windows 环境上运行全部规则，生成可存档比对的确定性报告
+
+use super::load_rule_set;
+use super::scan::total_bytes;
+use crate::output::{Output, Reported};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use winclean_rules::filesystem::{dedup_matches, find_paths, FileMatch, WindowsEnv};
+use winclean_rules::fixture::Fixture;
+use winclean_rules::pattern::PathPattern;
+use winclean_rules::registry::RegistryMatcher;
+use winclean_rules::time::format_time;
+
+/// 模拟结果
+#[derive(Serialize)]
+struct Simulation<'a> {
+    fixture: &'a str,
+    users: &'a [String],
+    rules: Vec<SimulatedRule>,
+    /// 命中的规则数
+    matched_rules: usize,
+    /// 去重后的合计
+    files: usize,
+    bytes: u64,
+    registry: usize,
+    snapshot: Option<SnapshotStatus<'a>>,
+}
+
+/// 单个规则的模拟结果
+#[derive(Serialize)]
+struct SimulatedRule {
+    id: String,
+    name: String,
+    warnings: Vec<String>,
+    files: Vec<FileMatch>,
+    /// 注册表操作与对象
+    registry: Vec<String>,
+}
+
+/// 与快照比对的结果
+#[derive(Serialize)]
+struct SnapshotStatus<'a> {
+    path: &'a Path,
+    /// created、updated、matched 或 mismatched
+    status: &'static str,
+    /// 第一处不一致
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mismatch: Option<Mismatch<'a>>,
+}
+
+#[derive(Serialize)]
+struct Mismatch<'a> {
+    line: usize,
+    expected: Option<&'a str>,
+    actual: Option<&'a str>,
+}
+
+/// 模拟规则并输出报告，指定快照时与快照比对
+pub fn simulate(rules: &Path, fixture_path: &Path, snapshot: Option<&Path>, update: bool, out: Output) -> Result<()> {
+    let content = fs::read_to_string(fixture_path)
+        .with_context(|| format!("读取夹具失败: {}", fixture_path.display()))?;
+    let fixture: Fixture = serde_yaml::from_str(&content)
+        .with_context(|| format!("夹具格式错误: {}", fixture_path.display()))?;
+
+    let name = fixture_path.file_stem().and_then(|n| n.to_str()).unwrap_or("fixture");
+    let mut simulation = simulate_rules(rules, name, &fixture, out)?;
+    let report = simulation.render()?;
+    if !out.is_json() {
+        print!("{}", report);
+    }
+
+    let Some(snapshot) = snapshot else { return out.json(&simulation) };
+    if update || !snapshot.exists() {
+        let status = if snapshot.exists() { "updated" } else { "created" };
+        if let Some(parent) = snapshot.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(snapshot, &report)
+            .with_context(|| format!("写入快照失败: {}", snapshot.display()))?;
+        say!(out, "\n已更新快照: {:?}", snapshot);
+        simulation.snapshot = Some(SnapshotStatus { path: snapshot, status, mismatch: None });
+        return out.json(&simulation);
+    }
+
+    let expected = fs::read_to_string(snapshot)
+        .with_context(|| format!("读取快照失败: {}", snapshot.display()))?;
+    if expected == report {
+        say!(out, "\n与快照一致: {:?}", snapshot);
+        simulation.snapshot = Some(SnapshotStatus { path: snapshot, status: "matched", mismatch: None });
+        return out.json(&simulation);
+    }
+
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = report.lines();
+    for line in 1.. {
+        let (old, new) = (expected_lines.next(), actual_lines.next());
+        if old != new {
+            if !out.is_json() {
+                eprintln!("第 {} 行不一致:", line);
+                eprintln!("  快照: {}", old.unwrap_or("(文件结束)"));
+                eprintln!("  实际: {}", new.unwrap_or("(文件结束)"));
+            }
+            let mismatch = Mismatch { line, expected: old, actual: new };
+            simulation.snapshot = Some(SnapshotStatus { path: snapshot, status: "mismatched", mismatch: Some(mismatch) });
+            break;
+        }
+    }
+    out.json(&simulation)?;
+    Err(Reported(format!(
+        "模拟结果与快照不一致: {}（确认无误后使用 --update 更新快照）",
+        snapshot.display()
+    ))
+    .into())
+}
+
+/// 在夹具上模拟全部规则
+fn simulate_rules<'a>(rules: &Path, name: &'a str, fixture: &'a Fixture, out: Output) -> Result<Simulation<'a>> {
+    let rules = load_rule_set(rules, out)?;
+    let (tree, registry) = fixture.build().context("夹具内容无效")?;
+
+    let envs: Vec<WindowsEnv> = if fixture.users.is_empty() {
+        vec![WindowsEnv::system()]
+    } else {
+        fixture.users.iter().map(|u| WindowsEnv::for_user(u)).collect()
+    };
+
+    let mut simulated = Vec::new();
+    let mut all_files = Vec::new();
+    let mut all_keys = BTreeSet::new();
+    let mut matched_rules = 0;
+    for rule in &rules {
+        let mut warnings = Vec::new();
+        let mut files = Vec::new();
+        for path in &rule.paths {
+            let pattern = match PathPattern::parse(path) {
+                Ok(pattern) => pattern,
+                Err(e) => {
+                    warnings.push(format!("路径 `{}` 无效: {}", path, e));
+                    continue;
+                }
+            };
+            for env in &envs {
+                match env.expand(&pattern)? {
+                    Some(expanded) => files.extend(find_paths(&expanded, &tree)?),
+                    None => {
+                        let env_name = pattern.env().unwrap_or("");
+                        warnings.push(format!("未知环境变量 %{}%，跳过 `{}`", env_name, path));
+                        break;
+                    }
+                }
+            }
+        }
+        let files = dedup_matches(files);
+
+        let mut keys = BTreeSet::new();
+        for entry in &rule.registry_entries {
+            let matcher = entry
+                .to_rule()
+                .and_then(|r| Ok(RegistryMatcher::new(&r)?))
+                .with_context(|| format!("规则 {} 的注册表条目无效: {}", rule.metadata.id, entry.path))?;
+            for target in matcher.find(&registry)? {
+                keys.insert(format!("{} {}", entry.action, target));
+            }
+        }
+
+        if !files.is_empty() || !keys.is_empty() {
+            matched_rules += 1;
+        }
+        all_files.extend(files.iter().cloned());
+        all_keys.extend(keys.iter().cloned());
+        simulated.push(SimulatedRule {
+            id: rule.metadata.id.clone(),
+            name: rule.metadata.name.clone(),
+            warnings,
+            files,
+            registry: keys.into_iter().collect(),
+        });
+    }
+
+    let all_files = dedup_matches(all_files);
+    Ok(Simulation {
+        fixture: name,
+        users: &fixture.users,
+        rules: simulated,
+        matched_rules,
+        files: all_files.len(),
+        bytes: total_bytes(&all_files),
+        registry: all_keys.len(),
+        snapshot: None,
+    })
+}
+
+impl Simulation<'_> {
+    /// 生成确定性的文本报告（即快照内容）
+    fn render(&self) -> Result<String> {
+        let mut report = String::new();
+        writeln!(report, "夹具: {}", self.fixture)?;
+        writeln!(report, "用户: {:?}", self.users)?;
+
+        for rule in &self.rules {
+            writeln!(report, "\n[{}] {}", rule.id, rule.name)?;
+            for warning in &rule.warnings {
+                writeln!(report, "  警告: {}", warning)?;
+            }
+
+            if rule.files.is_empty() && rule.registry.is_empty() {
+                writeln!(report, "  未命中")?;
+                continue;
+            }
+
+            if !rule.files.is_empty() {
+                writeln!(report, "  文件:")?;
+                for m in &rule.files {
+                    let kind = if m.is_dir { "目录" } else { "文件" };
+                    match m.modified {
+                        Some(time) => writeln!(report, "    {} {} ({} bytes, {})", kind, m.path, m.size, format_time(time))?,
+                        None => writeln!(report, "    {} {} ({} bytes)", kind, m.path, m.size)?,
+                    }
+                }
+            }
+            if !rule.registry.is_empty() {
+                writeln!(report, "  注册表:")?;
+                for key in &rule.registry {
+                    writeln!(report, "    {}", key)?;
+                }
+            }
+            writeln!(
+                report,
+                "  小计: 文件 {} 项, {} bytes, 注册表 {} 项",
+                rule.files.len(),
+                total_bytes(&rule.files),
+                rule.registry.len()
+            )?;
+        }
+
+        writeln!(
+            report,
+            "\n合计: {}/{} 个规则命中, 文件 {} 项, {} bytes, 注册表 {} 项",
+            self.matched_rules,
+            self.rules.len(),
+            self.files,
+            self.bytes,
+            self.registry
+        )?;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::Format;
+
+    const FIXTURE: &str = r"
+users: [alice, bob]
+files:
+  - path: 'C:\Users\alice\AppData\Roaming\SoftMgr2\a.txt'
+    size: 100
+    modified: 2025-12-01T08:00:00Z
+  - path: 'C:\Users\bob\AppData\Roaming\SoftMgrX\b.txt'
+    size: 20
+  - path: 'C:\Windows\Prefetch\SOFTMGR.EXE-1A2B.pf'
+    size: 8
+registry:
+  - key: 'HKCU\Software\SoftMgr'
+    values:
+      - name: Flags
+        type: dword
+        data: '1'
+";
+
+    /// 写出两个规则：soft_mgr 命中文件与注册表，unused 未命中
+    fn write_rules(dir: &Path) -> std::path::PathBuf {
+        let rules = dir.join("rules");
+        fs::create_dir_all(rules.join("apps")).unwrap();
+        fs::write(
+            rules.join("apps/soft_mgr.yaml"),
+            "id: soft_mgr\nname: 软件管家\nrisk: high\nupdate: 2026-01-01\nmatch:\n  path:\n\
+             \x20   - \"%APPDATA%\\\\<SoftMgr.+>\"\n    - \"C:\\\\Windows\\\\Prefetch\\\\<SOFTMGR.+>\"\n\
+             \x20   - \"%NOPE%\\\\x\"\n  registry:\n    - path: \"HKCU\\\\Software\\\\\"\n      key: SoftMgr\n\
+             \x20     action: delete_value\n",
+        )
+        .unwrap();
+        fs::write(
+            rules.join("apps/unused.yaml"),
+            "id: unused\nname: 未命中\nrisk: high\nupdate: 2026-01-01\nmatch:\n  path:\n    - \"%TEMP%\\\\unused\"\n",
+        )
+        .unwrap();
+        rules
+    }
+
+    #[test]
+    fn simulates_rules_on_fixture() {
+        let temp = tempfile::tempdir().unwrap();
+        let rules = write_rules(temp.path());
+        let fixture: Fixture = serde_yaml::from_str(FIXTURE).unwrap();
+        let simulation = simulate_rules(&rules, "demo", &fixture, Output::new(Format::Json)).unwrap();
+
+        assert_eq!((simulation.matched_rules, simulation.files, simulation.bytes, simulation.registry), (1, 3, 128, 1));
+        assert_eq!(
+            simulation.render().unwrap(),
+            "夹具: demo\n\
+             用户: [\"alice\", \"bob\"]\n\
+             \n\
+             [soft_mgr] 软件管家\n\
+             \x20 警告: 未知环境变量 %NOPE%，跳过 `%NOPE%\\x`\n\
+             \x20 文件:\n\
+             \x20   目录 C:\\Users\\alice\\AppData\\Roaming\\SoftMgr2 (100 bytes)\n\
+             \x20   目录 C:\\Users\\bob\\AppData\\Roaming\\SoftMgrX (20 bytes)\n\
+             \x20   文件 C:\\Windows\\Prefetch\\SOFTMGR.EXE-1A2B.pf (8 bytes)\n\
+             \x20 注册表:\n\
+             \x20   delete_value HKEY_CURRENT_USER\\Software\\SoftMgr -> Flags\n\
+             \x20 小计: 文件 3 项, 128 bytes, 注册表 1 项\n\
+             \n\
+             [unused] 未命中\n\
+             \x20 未命中\n\
+             \n\
+             合计: 1/2 个规则命中, 文件 3 项, 128 bytes, 注册表 1 项\n"
+        );
+    }
+
+    #[test]
+    fn creates_compares_and_updates_snapshot() {
+        let temp = tempfile::tempdir().unwrap();
+        let rules = write_rules(temp.path());
+        let fixture = temp.path().join("demo.yaml");
+        fs::write(&fixture, FIXTURE).unwrap();
+        let snapshot = temp.path().join("snapshots/demo.txt");
+        let run = |update| simulate(&rules, &fixture, Some(&snapshot), update, Output::new(Format::Json));
+
+        run(false).unwrap();
+        let created = fs::read_to_string(&snapshot).unwrap();
+        assert!(created.starts_with("夹具: demo\n"));
+        run(false).unwrap();
+
+        fs::write(&snapshot, created.replace("128 bytes", "64 bytes")).unwrap();
+        let error = run(false).unwrap_err();
+        assert!(error.to_string().contains("模拟结果与快照不一致"), "{}", error);
+        run(true).unwrap();
+        assert_eq!(fs::read_to_string(&snapshot).unwrap(), created);
+
+        fs::write(&fixture, "files: [{path: 'C:\\a', modified: never}]\n").unwrap();
+        assert!(format!("{:#}", run(false).unwrap_err()).contains("修改时间 `never` 无效"));
+    }
+
+    #[test]
+    fn checked_in_fixture_matches_snapshot() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        simulate(
+            &root.join("rules"),
+            &root.join("fixtures/win11_default.yaml"),
+            Some(&root.join("fixtures/win11_default.snapshot.txt")),
+            false,
+            Output::new(Format::Json),
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,416 @@
+//! WinClean Rules Python
+//! 供分析脚本使用的 PyO3 模块：读取规则包、遍历规则、匹配路径模式与打包规则目录
+//!
+//! ```python
+//! import winclean_rules as wr
+//!
+//! package = wr.RulesPackage.load("dist/rules.bin", pubkey="keys/rules.pub.pem")
+//! for rule in package:
+//!     print(rule.id, rule.category, len(rule.paths))
+//!
+//! pattern = wr.PathPattern(r"%APPDATA%\<SoftMgr.+>")
+//! pattern.expand_env({"APPDATA": r"C:\Users\a\AppData\Roaming"}).matches(r"C:\Users\a\AppData\Roaming\SoftMgr2")
+//!
+//! data = wr.pack("rules", serial=20260113)
+//! ```
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyIndexError, PyOSError, PyUserWarning, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+use winclean_rules::diagnostic::{Severity, ValidationFailed};
+use winclean_rules::pack::{pack as pack_rules, PackOptions};
+use winclean_rules::package::{self, PackageHeader, RegistryEntry, SerializedRule, TrustPolicy};
+use winclean_rules::pattern;
+use winclean_rules::signing;
+
+create_exception!(winclean_rules, PackageError, PyValueError, "规则包无法读取或未通过校验");
+
+fn package_error(error: package::PackageError) -> PyErr {
+    match error {
+        package::PackageError::Io(e) => PyOSError::new_err(e.to_string()),
+        e => PackageError::new_err(e.to_string()),
+    }
+}
+
+fn value_error(error: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// 规则包
+#[pyclass(module = "winclean_rules", name = "RulesPackage", frozen)]
+struct PyRulesPackage {
+    header: PackageHeader,
+    rules: Vec<Py<PySerializedRule>>,
+}
+
+#[pymethods]
+impl PyRulesPackage {
+    /// 读取规则包文件；指定 `pubkey`（PEM 公钥文件）时要求签名有效、未过期且内容序号不小于 `last_serial`
+    #[staticmethod]
+    #[pyo3(signature = (path, pubkey=None, last_serial=None))]
+    fn load(py: Python<'_>, path: PathBuf, pubkey: Option<PathBuf>, last_serial: Option<u64>) -> PyResult<Self> {
+        let file = File::open(&path).map_err(|e| PyOSError::new_err(format!("读取规则包失败: {}: {}", path.display(), e)))?;
+        PyRulesPackage::read(py, &mut BufReader::new(file), pubkey, last_serial)
+    }
+
+    /// 从内存中的规则包读取，参数同 `load`
+    #[staticmethod]
+    #[pyo3(signature = (data, pubkey=None, last_serial=None))]
+    fn from_bytes(py: Python<'_>, data: &[u8], pubkey: Option<PathBuf>, last_serial: Option<u64>) -> PyResult<Self> {
+        PyRulesPackage::read(py, &mut &data[..], pubkey, last_serial)
+    }
+
+    /// 格式版本
+    #[getter]
+    fn version(&self) -> u32 {
+        self.header.version
+    }
+
+    /// 内容序号
+    #[getter]
+    fn serial(&self) -> u64 {
+        self.header.serial
+    }
+
+    /// 创建时间（Unix 时间戳，秒）
+    #[getter]
+    fn created_at(&self) -> u64 {
+        self.header.created_at
+    }
+
+    /// 过期时间（Unix 时间戳，秒），永不过期时为 None
+    #[getter]
+    fn expires_at(&self) -> Option<u64> {
+        self.header.expires_at
+    }
+
+    #[getter]
+    fn compression(&self) -> &str {
+        &self.header.compression
+    }
+
+    #[getter]
+    fn encoding(&self) -> &str {
+        &self.header.encoding
+    }
+
+    #[getter]
+    fn categories(&self) -> Vec<String> {
+        self.header.categories.clone()
+    }
+
+    /// 按 id 查找规则，不存在时为 None
+    fn rule(&self, py: Python<'_>, id: &str) -> Option<Py<PySerializedRule>> {
+        self.rules.iter().find(|r| r.get().inner.metadata.id == id).map(|r| r.clone_ref(py))
+    }
+
+    /// 某个分类下的全部规则
+    fn category(&self, py: Python<'_>, category: &str) -> Vec<Py<PySerializedRule>> {
+        self.rules
+            .iter()
+            .filter(|r| r.get().inner.metadata.category == category)
+            .map(|r| r.clone_ref(py))
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.rules.len()
+    }
+
+    fn __getitem__(&self, py: Python<'_>, index: isize) -> PyResult<Py<PySerializedRule>> {
+        let len = self.rules.len() as isize;
+        let index = if index < 0 { index + len } else { index };
+        if !(0..len).contains(&index) {
+            return Err(PyIndexError::new_err("规则序号超出范围"));
+        }
+        Ok(self.rules[index as usize].clone_ref(py))
+    }
+
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let rules: Vec<Py<PySerializedRule>> = self.rules.iter().map(|r| r.clone_ref(py)).collect();
+        rules.into_pyobject(py)?.into_any().try_iter().map(Bound::into_any)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<RulesPackage serial={} rules={}>", self.header.serial, self.rules.len())
+    }
+}
+
+impl PyRulesPackage {
+    fn read(py: Python<'_>, reader: &mut impl io::Read, pubkey: Option<PathBuf>, last_serial: Option<u64>) -> PyResult<Self> {
+        let package = match pubkey {
+            Some(path) => {
+                let key = signing::load_verifying_key(&path).map_err(|e| value_error(format!("{:#}", e)))?;
+                let policy = TrustPolicy { last_serial, ..TrustPolicy::new(key) };
+                package::read_package_verified(reader, &policy)
+            }
+            None => package::read_package(reader),
+        }
+        .map_err(package_error)?;
+
+        let rules = package
+            .rules
+            .into_iter()
+            .map(|inner| Py::new(py, PySerializedRule { inner }))
+            .collect::<PyResult<_>>()?;
+        Ok(PyRulesPackage { header: package.header, rules })
+    }
+}
+
+/// 规则包中的一条规则
+#[pyclass(module = "winclean_rules", name = "SerializedRule", frozen)]
+struct PySerializedRule {
+    inner: SerializedRule,
+}
+
+#[pymethods]
+impl PySerializedRule {
+    #[getter]
+    fn id(&self) -> &str {
+        &self.inner.metadata.id
+    }
+
+    #[getter]
+    fn name(&self) -> &str {
+        &self.inner.metadata.name
+    }
+
+    #[getter]
+    fn risk(&self) -> &str {
+        &self.inner.metadata.risk
+    }
+
+    #[getter]
+    fn systeminfo(&self) -> Vec<String> {
+        self.inner.metadata.systeminfo.clone()
+    }
+
+    #[getter]
+    fn update(&self) -> &str {
+        &self.inner.metadata.update
+    }
+
+    #[getter]
+    fn author(&self) -> Option<&str> {
+        self.inner.metadata.author.as_deref()
+    }
+
+    #[getter]
+    fn description(&self) -> Option<&str> {
+        self.inner.metadata.description.as_deref()
+    }
+
+    #[getter]
+    fn category(&self) -> &str {
+        &self.inner.metadata.category
+    }
+
+    #[getter]
+    fn filename(&self) -> &str {
+        &self.inner.metadata.filename
+    }
+
+    /// 原始 YAML 内容
+    #[getter]
+    fn yaml_content(&self) -> &str {
+        &self.inner.yaml_content
+    }
+
+    /// 文件路径模式
+    #[getter]
+    fn paths(&self) -> Vec<String> {
+        self.inner.paths.clone()
+    }
+
+    #[getter]
+    fn registry_entries(&self) -> Vec<PyRegistryEntry> {
+        self.inner.registry_entries.iter().cloned().map(|inner| PyRegistryEntry { inner }).collect()
+    }
+
+    /// 解析后的文件路径模式
+    fn path_patterns(&self) -> PyResult<Vec<PyPathPattern>> {
+        self.inner
+            .paths
+            .iter()
+            .map(|p| pattern::PathPattern::parse(p).map(|inner| PyPathPattern { inner }).map_err(value_error))
+            .collect()
+    }
+
+    /// 解包时的相对路径 `分类/文件名`
+    fn relative_path(&self) -> PyResult<PathBuf> {
+        self.inner.metadata.relative_path().map_err(package_error)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<SerializedRule id={:?} category={:?}>", self.inner.metadata.id, self.inner.metadata.category)
+    }
+}
+
+/// 注册表条目
+#[pyclass(module = "winclean_rules", name = "RegistryEntry", frozen)]
+struct PyRegistryEntry {
+    inner: RegistryEntry,
+}
+
+#[pymethods]
+impl PyRegistryEntry {
+    #[getter]
+    fn path(&self) -> &str {
+        &self.inner.path
+    }
+
+    #[getter]
+    fn key(&self) -> &str {
+        &self.inner.key
+    }
+
+    #[getter]
+    fn value(&self) -> Option<&str> {
+        self.inner.value.as_deref()
+    }
+
+    #[getter]
+    fn value_data(&self) -> Option<&str> {
+        self.inner.value_data.as_deref()
+    }
+
+    #[getter]
+    fn action(&self) -> &str {
+        &self.inner.action
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<RegistryEntry {} {:?}>", self.inner.action, self.inner.path)
+    }
+}
+
+/// 路径模式
+#[pyclass(module = "winclean_rules", name = "PathPattern", frozen)]
+struct PyPathPattern {
+    inner: pattern::PathPattern,
+}
+
+#[pymethods]
+impl PyPathPattern {
+    /// 解析文件路径模式
+    #[new]
+    fn new(pattern: &str) -> PyResult<Self> {
+        pattern::PathPattern::parse(pattern).map(|inner| PyPathPattern { inner }).map_err(value_error)
+    }
+
+    /// 解析注册表路径模式
+    #[staticmethod]
+    fn registry(pattern: &str) -> PyResult<Self> {
+        pattern::PathPattern::parse_registry(pattern).map(|inner| PyPathPattern { inner }).map_err(value_error)
+    }
+
+    /// 环境变量前缀（不含百分号）
+    #[getter]
+    fn env(&self) -> Option<&str> {
+        self.inner.env()
+    }
+
+    /// 完整匹配路径（忽略大小写，未展开的环境变量按字面比较）
+    fn matches(&self, path: &str) -> bool {
+        self.inner.matches(path)
+    }
+
+    /// 用给定的取值展开环境变量前缀（变量名忽略大小写），无法展开时为 None
+    fn expand_env(&self, values: &Bound<'_, PyDict>) -> PyResult<Option<Self>> {
+        let mut lookup = None;
+        if let Some(name) = self.inner.env() {
+            for (key, value) in values {
+                if key.extract::<String>()?.eq_ignore_ascii_case(name) {
+                    lookup = Some(value.extract::<String>()?);
+                    break;
+                }
+            }
+        }
+        self.inner
+            .expand_env(|_| lookup)
+            .transpose()
+            .map(|p| p.map(|inner| PyPathPattern { inner }))
+            .map_err(value_error)
+    }
+
+    fn __str__(&self) -> &str {
+        self.inner.as_str()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PathPattern({:?})", self.inner.as_str())
+    }
+}
+
+/// 校验并打包规则目录，返回规则包内容；指定 `output` 时同时写入文件
+///
+/// 校验错误时抛出 ValueError，警告通过 `warnings` 发出。创建时间与内容序号的规则与命令行工具相同：
+/// 未指定 `created_at` 时优先取 `SOURCE_DATE_EPOCH`，`reproducible` 时取规则中最新的 update 日期，
+/// 否则取当前时间；未指定 `serial` 时取创建日期 `YYYYMMDD`。
+#[pyfunction]
+#[pyo3(signature = (
+    directory, output=None, compress="zstd", encoding="bincode", serial=None, created_at=None, expires_in_days=None,
+    sign_key=None, reproducible=false
+))]
+#[allow(clippy::too_many_arguments)]
+fn pack<'py>(
+    py: Python<'py>,
+    directory: PathBuf,
+    output: Option<PathBuf>,
+    compress: &str,
+    encoding: &str,
+    serial: Option<u64>,
+    created_at: Option<u64>,
+    expires_in_days: Option<u64>,
+    sign_key: Option<PathBuf>,
+    reproducible: bool,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let options = PackOptions {
+        compression: compress.parse().map_err(value_error)?,
+        encoding: encoding.parse().map_err(value_error)?,
+        serial,
+        created_at,
+        expires_in_days,
+        reproducible,
+    };
+    let signer = sign_key
+        .map(|path| signing::load_signing_key(&path))
+        .transpose()
+        .map_err(|e| value_error(format!("{:#}", e)))?;
+
+    let packed = pack_rules(&directory, &options, signer.as_ref()).map_err(|e| match e.downcast::<ValidationFailed>() {
+        Ok(failed) => {
+            let errors: Vec<String> =
+                failed.diagnostics.iter().filter(|d| d.severity == Severity::Error).map(ToString::to_string).collect();
+            value_error(format!("校验失败: {} 个错误\n\n{}", errors.len(), errors.join("\n\n")))
+        }
+        Err(e) => value_error(format!("{:#}", e)),
+    })?;
+    for warning in &packed.warnings {
+        let message = CString::new(warning.to_string().replace('\0', " ")).unwrap_or_default();
+        PyErr::warn(py, &py.get_type::<PyUserWarning>(), &message, 1)?;
+    }
+
+    if let Some(output) = output {
+        fs::write(&output, &packed.data)
+            .map_err(|e| PyOSError::new_err(format!("写入规则包失败: {}: {}", output.display(), e)))?;
+    }
+    Ok(PyBytes::new(py, &packed.data))
+}
+
+#[pymodule]
+#[pyo3(name = "winclean_rules")]
+fn winclean_rules_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyRulesPackage>()?;
+    m.add_class::<PySerializedRule>()?;
+    m.add_class::<PyRegistryEntry>()?;
+    m.add_class::<PyPathPattern>()?;
+    m.add_function(wrap_pyfunction!(pack, m)?)?;
+    m.add("PackageError", m.py().get_type::<PackageError>())?;
+    Ok(())
+}
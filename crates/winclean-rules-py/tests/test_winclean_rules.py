@@ -0,0 +1,163 @@
+"""winclean_rules 模块测试
+
+先构建模块（maturin develop -m crates/winclean-rules-py/Cargo.toml），再在仓库根目录运行：
+
+    python -m unittest discover -s crates/winclean-rules-py/tests
+"""
+
+import os
+import pathlib
+import tempfile
+import unittest
+import warnings
+from unittest import mock
+
+import winclean_rules as wr
+
+ROOT = pathlib.Path(__file__).resolve().parents[3]
+RULES = ROOT / "rules"
+EPOCH = 1_700_000_000
+
+RULE = """\
+id: {id}
+name: 演示 {id}
+risk: high
+update: {update}
+match:
+  path:
+    - "%TEMP%\\\\{id}"
+"""
+
+
+def write_rules(directory, rules):
+    """按 {(分类, id): 规则内容} 写出规则目录"""
+    for (category, rule_id), content in rules.items():
+        path = pathlib.Path(directory, category, rule_id + ".yaml")
+        path.parent.mkdir(parents=True, exist_ok=True)
+        path.write_text(content, encoding="utf-8")
+
+
+def without_epoch():
+    """移除 SOURCE_DATE_EPOCH，避免外部环境影响创建时间"""
+    env = {k: v for k, v in os.environ.items() if k != "SOURCE_DATE_EPOCH"}
+    return mock.patch.dict(os.environ, env, clear=True)
+
+
+class PackTest(unittest.TestCase):
+    def setUp(self):
+        self.temp = tempfile.TemporaryDirectory()
+        self.addCleanup(self.temp.cleanup)
+        self.dir = pathlib.Path(self.temp.name)
+        write_rules(self.dir / "rules", {
+            ("apps", "alpha"): RULE.format(id="alpha", update="2026-01-03"),
+            ("system", "beta"): RULE.format(id="beta", update="2026-02-14"),
+        })
+
+    def pack(self, **kwargs):
+        with warnings.catch_warnings():
+            warnings.simplefilter("error")
+            return wr.pack(str(self.dir / "rules"), **kwargs)
+
+    def test_round_trip(self):
+        output = self.dir / "rules.bin"
+        data = self.pack(output=str(output), serial=20260113, created_at=EPOCH, expires_in_days=30)
+        self.assertEqual(output.read_bytes(), data)
+
+        package = wr.RulesPackage.load(str(output))
+        self.assertEqual(package.serial, 20260113)
+        self.assertEqual(package.created_at, EPOCH)
+        self.assertEqual(package.expires_at, EPOCH + 30 * 86400)
+        self.assertEqual(package.compression, "zstd")
+        self.assertEqual(package.encoding, "bincode")
+        self.assertEqual(package.categories, ["apps", "system"])
+        self.assertEqual([rule.id for rule in package], ["alpha", "beta"])
+        self.assertEqual(pathlib.Path(package.rule("beta").relative_path()), pathlib.Path("system", "beta.yaml"))
+
+    def test_protobuf_without_compression(self):
+        data = self.pack(compress="none", encoding="protobuf", created_at=EPOCH)
+        package = wr.RulesPackage.from_bytes(data)
+        self.assertEqual((package.compression, package.encoding), ("none", "protobuf"))
+        self.assertEqual(package.serial, 20231114)
+
+    def test_source_date_epoch(self):
+        with mock.patch.dict(os.environ, {"SOURCE_DATE_EPOCH": str(EPOCH)}):
+            first = self.pack()
+            second = self.pack()
+        self.assertEqual(first, second)
+        package = wr.RulesPackage.from_bytes(first)
+        self.assertEqual(package.created_at, EPOCH)
+        self.assertEqual(package.serial, 20231114)
+
+    def test_reproducible_uses_latest_update(self):
+        with without_epoch():
+            first = self.pack(reproducible=True)
+            second = self.pack(reproducible=True)
+        self.assertEqual(first, second)
+        package = wr.RulesPackage.from_bytes(first)
+        self.assertEqual(package.created_at, 1_771_027_200)  # 2026-02-14T00:00:00Z
+        self.assertEqual(package.serial, 20260214)
+
+    def test_created_at_overrides_source_date_epoch(self):
+        with mock.patch.dict(os.environ, {"SOURCE_DATE_EPOCH": "1"}):
+            data = self.pack(created_at=EPOCH, reproducible=True)
+        self.assertEqual(wr.RulesPackage.from_bytes(data).created_at, EPOCH)
+
+    def test_invalid_source_date_epoch(self):
+        with mock.patch.dict(os.environ, {"SOURCE_DATE_EPOCH": "yesterday"}):
+            with self.assertRaisesRegex(ValueError, "SOURCE_DATE_EPOCH"):
+                self.pack()
+
+    def test_validation_errors(self):
+        write_rules(self.dir / "rules", {
+            ("apps", "gamma"): "id: Gamma\nname: x\nrisk: low\nupdate: 2026-02-30\n",
+        })
+        with self.assertRaises(ValueError) as raised:
+            self.pack(created_at=EPOCH)
+        message = str(raised.exception)
+        self.assertTrue(message.startswith("校验失败: 3 个错误"), message)
+        for expected in ("risk `low`", "id `Gamma`", "update `2026-02-30`"):
+            self.assertIn(expected, message)
+
+    def test_warnings(self):
+        write_rules(self.dir / "rules", {
+            ("apps", "alpha"): RULE.format(id="alpha", update="2026-01-03") + '    - "%TEMP%\\\\alpha"\n',
+        })
+        with warnings.catch_warnings(record=True) as caught:
+            warnings.simplefilter("always")
+            wr.pack(str(self.dir / "rules"), created_at=EPOCH)
+        self.assertEqual(len(caught), 1)
+        self.assertIs(caught[0].category, UserWarning)
+        self.assertIn("match.path[1] 与前面的路径重复", str(caught[0].message))
+
+    def test_bad_options(self):
+        with self.assertRaisesRegex(ValueError, "gzip"):
+            self.pack(compress="gzip")
+        with self.assertRaisesRegex(ValueError, "json"):
+            self.pack(encoding="json")
+
+
+class PackageTest(unittest.TestCase):
+    def test_tampered_package(self):
+        with warnings.catch_warnings():
+            warnings.simplefilter("ignore")
+            data = bytearray(wr.pack(str(RULES), created_at=EPOCH))
+        data[-1] ^= 1
+        with self.assertRaises(wr.PackageError):
+            wr.RulesPackage.from_bytes(bytes(data))
+
+    def test_missing_file(self):
+        with self.assertRaises(OSError):
+            wr.RulesPackage.load("/nonexistent/rules.bin")
+
+
+class PathPatternTest(unittest.TestCase):
+    def test_expand_env_and_match(self):
+        pattern = wr.PathPattern(r"%APPDATA%\<SoftMgr.+>")
+        expanded = pattern.expand_env({"appdata": r"C:\Users\a\AppData\Roaming"})
+        self.assertTrue(expanded.matches(r"C:\Users\a\AppData\Roaming\SoftMgr2"))
+        self.assertFalse(expanded.matches(r"C:\Users\a\AppData\Roaming\SoftMgr"))
+        self.assertIsNone(pattern.expand_env({}))
+
+
+if __name__ == "__main__":
+    unittest.main()
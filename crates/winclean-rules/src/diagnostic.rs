@@ -0,0 +1,409 @@
+//! 规则诊断信息
+//! 带文件、行列位置的错误报告，按 rustc 风格输出源码片段
+
+use serde::Serialize;
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// 诊断级别
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// 字段路径中的一段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldSegment {
+    Key(Cow<'static, str>),
+    Index(usize),
+}
+
+/// 字段路径，如 `match.registry[0].key`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldPath(pub Vec<FieldSegment>);
+
+impl FieldPath {
+    pub fn key(mut self, key: impl Into<Cow<'static, str>>) -> Self {
+        self.0.push(FieldSegment::Key(key.into()));
+        self
+    }
+
+    pub fn index(mut self, index: usize) -> Self {
+        self.0.push(FieldSegment::Index(index));
+        self
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                FieldSegment::Key(key) if i == 0 => write!(f, "{}", key)?,
+                FieldSegment::Key(key) => write!(f, ".{}", key)?,
+                FieldSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 校验问题（尚未定位到源码）
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    pub field: FieldPath,
+    pub message: String,
+}
+
+impl Issue {
+    pub fn error(field: FieldPath, message: impl Into<String>) -> Self {
+        Issue { severity: Severity::Error, field, message: message.into() }
+    }
+
+    pub fn warning(field: FieldPath, message: impl Into<String>) -> Self {
+        Issue { severity: Severity::Warning, field, message: message.into() }
+    }
+}
+
+/// 规则未通过校验，附带全部诊断（含警告）
+#[derive(Debug)]
+pub struct ValidationFailed {
+    pub errors: usize,
+    pub warnings: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ValidationFailed {
+    /// 有错误时返回失败，否则原样返回诊断（只含警告）
+    pub fn check(diagnostics: Vec<Diagnostic>) -> Result<Vec<Diagnostic>, ValidationFailed> {
+        let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+        if errors > 0 {
+            let warnings = diagnostics.len() - errors;
+            return Err(ValidationFailed { errors, warnings, diagnostics });
+        }
+        Ok(diagnostics)
+    }
+}
+
+impl fmt::Display for ValidationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "校验失败: {} 个错误, {} 个警告", self.errors, self.warnings)
+    }
+}
+
+impl std::error::Error for ValidationFailed {}
+
+/// 源码位置（行列从1开始，长度按字符计）
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+/// 诊断信息
+#[derive(Serialize, Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: PathBuf,
+    pub message: String,
+    pub span: Option<Span>,
+    /// 出错行的源码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    excerpt: Option<String>,
+}
+
+impl Diagnostic {
+    /// 无位置信息的诊断（如读取文件失败）
+    pub fn new(severity: Severity, file: &Path, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            file: file.to_path_buf(),
+            message: message.into(),
+            span: None,
+            excerpt: None,
+        }
+    }
+
+    /// 指向源码中某个位置的诊断
+    pub fn at(
+        severity: Severity,
+        file: &Path,
+        source: &str,
+        span: Span,
+        message: impl Into<String>,
+    ) -> Self {
+        Diagnostic {
+            severity,
+            file: file.to_path_buf(),
+            message: message.into(),
+            span: Some(span),
+            excerpt: source.lines().nth(span.line - 1).map(|l| l.to_string()),
+        }
+    }
+
+    /// 由YAML解析错误构建
+    pub fn from_yaml_error(file: &Path, source: &str, error: &serde_yaml::Error) -> Self {
+        let message = strip_yaml_location(&error.to_string());
+        match error.location() {
+            Some(location) => {
+                let span = Span { line: location.line(), column: location.column(), len: 1 };
+                Diagnostic::at(Severity::Error, file, source, span, message)
+            }
+            None => Diagnostic::new(Severity::Error, file, message),
+        }
+    }
+
+    /// 由校验问题构建，定位失败时退化为文件级诊断
+    pub fn from_issue(file: &Path, source: &str, issue: &Issue) -> Self {
+        match locate(source, &issue.field) {
+            Some(span) => Diagnostic::at(issue.severity, file, source, span, &issue.message),
+            None => Diagnostic::new(issue.severity, file, &issue.message),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "错误",
+            Severity::Warning => "警告",
+        };
+        writeln!(f, "{}: {}", level, self.message)?;
+
+        let (span, excerpt) = match (self.span, &self.excerpt) {
+            (Some(span), Some(excerpt)) => (span, excerpt),
+            _ => return write!(f, "  --> {}", self.file.display()),
+        };
+
+        let gutter = span.line.to_string().len();
+        writeln!(f, "{:>w$}--> {}:{}:{}", "", self.file.display(), span.line, span.column, w = gutter)?;
+        writeln!(f, "{:>w$} |", "", w = gutter)?;
+        writeln!(f, "{} | {}", span.line, excerpt)?;
+
+        // 按显示宽度对齐插入符，中文字符占两列
+        let before: String = excerpt.chars().take(span.column - 1).collect();
+        let marked: String = excerpt.chars().skip(span.column - 1).take(span.len).collect();
+        let carets = display_width(&marked).max(1);
+        write!(
+            f,
+            "{:>w$} | {:pad$}{}",
+            "",
+            "",
+            "^".repeat(carets),
+            w = gutter,
+            pad = display_width(&before)
+        )
+    }
+}
+
+/// 去掉 serde_yaml 错误信息末尾的 " at line X column Y"
+fn strip_yaml_location(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(pos) => message[..pos].to_string(),
+        None => message.to_string(),
+    }
+}
+
+/// 字符串的终端显示宽度（近似：CJK 等宽字符按两列计）
+fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if c as u32 >= 0x2E80 { 2 } else { 1 }).sum()
+}
+
+/// 一行源码的缩进与内容
+struct SourceLine<'a> {
+    indent: usize,
+    text: &'a str,
+}
+
+fn source_line(line: &str) -> Option<SourceLine<'_>> {
+    let text = line.trim_start();
+    if text.is_empty() || text.starts_with('#') {
+        return None;
+    }
+    Some(SourceLine { indent: line.len() - text.len(), text })
+}
+
+/// 在块格式的YAML源码中定位字段
+///
+/// 仅识别规则文件使用的块格式（缩进映射与 `- ` 列表），
+/// 无法继续深入时返回已定位到的最深一层。
+pub fn locate(source: &str, field: &FieldPath) -> Option<Span> {
+    let lines: Vec<&str> = source.lines().collect();
+    // 当前查找范围 [start, end)，以及该范围首行是否为列表项（需跳过 "- "）
+    let mut start = 0;
+    let mut end = lines.len();
+    let mut item_line: Option<usize> = None;
+    let mut found: Option<Span> = None;
+
+    for segment in &field.0 {
+        // 计算范围内每行的有效缩进与内容
+        let effective = |n: usize| -> Option<(usize, &str)> {
+            let line = source_line(lines[n])?;
+            if item_line == Some(n) {
+                let text = line.text.strip_prefix('-')?.trim_start();
+                let indent = lines[n].len() - text.len();
+                return Some((indent, text));
+            }
+            Some((line.indent, line.text))
+        };
+
+        match segment {
+            FieldSegment::Key(key) => {
+                let Some((block_indent, _)) = (start..end).find_map(&effective) else {
+                    return found;
+                };
+                let mut hit = None;
+                for n in start..end {
+                    let Some((indent, text)) = effective(n) else { continue };
+                    if indent < block_indent {
+                        break;
+                    }
+                    if indent == block_indent
+                        && text.strip_prefix(&**key).is_some_and(|rest| rest.starts_with(':'))
+                    {
+                        hit = Some((n, indent, text));
+                        break;
+                    }
+                }
+                let Some((n, indent, text)) = hit else { return found };
+
+                // 行内有值时指向值，否则指向键
+                let value = text[key.len() + 1..].trim();
+                let value = value.split(" #").next().unwrap_or("").trim_end();
+                let span = if value.is_empty() {
+                    Span { line: n + 1, column: column_of(lines[n], indent), len: key.chars().count() }
+                } else {
+                    let offset = lines[n].len() - text.len() + text.find(value).unwrap_or(0);
+                    Span { line: n + 1, column: column_of(lines[n], offset), len: value.chars().count() }
+                };
+                found = Some(span);
+
+                start = n + 1;
+                end = (start..end)
+                    .find(|&m| {
+                        effective(m).is_some_and(|(i, text)| {
+                            i < block_indent || (i == block_indent && !text.starts_with('-'))
+                        })
+                    })
+                    .unwrap_or(end);
+                item_line = None;
+            }
+            FieldSegment::Index(index) => {
+                let items: Vec<usize> = (start..end)
+                    .filter(|&n| effective(n).is_some_and(|(_, text)| text.starts_with('-')))
+                    .collect();
+                let Some((list_indent, _)) = items.first().and_then(|&n| effective(n)) else {
+                    return found;
+                };
+                let items: Vec<usize> = items
+                    .into_iter()
+                    .filter(|&n| effective(n).is_some_and(|(i, _)| i == list_indent))
+                    .collect();
+                let Some(&n) = items.get(*index) else { return found };
+
+                let text = lines[n].trim_start();
+                let content = text[1..].trim_start();
+                let offset = lines[n].len() - content.len();
+                found = Some(Span {
+                    line: n + 1,
+                    column: column_of(lines[n], offset),
+                    len: content.chars().count(),
+                });
+
+                let next = items.get(index + 1).copied().unwrap_or(end);
+                start = n;
+                end = next;
+                item_line = Some(n);
+            }
+        }
+    }
+
+    found
+}
+
+/// 字节偏移转换为从1开始的字符列号
+fn column_of(line: &str, byte_offset: usize) -> usize {
+    line[..byte_offset].chars().count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"id: soft_mgr
+name: 软件管家
+risk: high
+update: 2026-01-01
+match:
+  path:
+    - "%APPDATA%\\SoftMgr"
+  registry:
+    - path: HKCU\Software
+      key: SoftMgr
+      action: delete_key
+    - path: HKCR\*\shellex  # 右键菜单
+      key: SoftMgr*
+      action: delete_valu
+"#;
+
+    fn field(segments: &[&str]) -> FieldPath {
+        segments.iter().fold(FieldPath::default(), |field, s| match s.parse() {
+            Ok(index) => field.index(index),
+            Err(_) => field.key(s.to_string()),
+        })
+    }
+
+    fn span(line: usize, column: usize, len: usize) -> Option<Span> {
+        Some(Span { line, column, len })
+    }
+
+    #[test]
+    fn locates_nested_fields() {
+        assert_eq!(field(&["match", "registry", "1", "action"]).to_string(), "match.registry[1].action");
+        assert_eq!(locate(SOURCE, &field(&["match", "registry", "1", "action"])), span(14, 15, 11));
+        assert_eq!(locate(SOURCE, &field(&["match", "registry", "0", "key"])), span(10, 12, 7));
+        // 与 `- ` 同行的字段，行内注释不计入
+        assert_eq!(locate(SOURCE, &field(&["match", "registry", "1", "path"])), span(12, 13, 14));
+        assert_eq!(locate(SOURCE, &field(&["match", "registry", "0"])), span(9, 7, 19));
+        assert_eq!(locate(SOURCE, &field(&["match", "path", "0"])), span(7, 7, 20));
+        assert_eq!(locate(SOURCE, &field(&["name"])), span(2, 7, 4));
+    }
+
+    #[test]
+    fn falls_back_to_deepest_located_field() {
+        assert_eq!(locate(SOURCE, &field(&["match", "registry", "5"])), span(8, 3, 8));
+        assert_eq!(locate(SOURCE, &field(&["match", "registry", "1", "value"])), span(12, 7, 28));
+        assert_eq!(locate(SOURCE, &field(&["tests"])), None);
+    }
+
+    #[test]
+    fn renders_caret_under_field() {
+        let file = Path::new("apps/soft_mgr.yaml");
+        let issue = Issue::error(field(&["match", "registry", "1", "action"]), "action `delete_valu` 不合法");
+        assert_eq!(
+            Diagnostic::from_issue(file, SOURCE, &issue).to_string(),
+            "错误: action `delete_valu` 不合法\n\
+             \x20 --> apps/soft_mgr.yaml:14:15\n\
+             \x20  |\n\
+             14 |       action: delete_valu\n\
+             \x20  |               ^^^^^^^^^^^"
+        );
+
+        // 中文字符按两列对齐
+        let issue = Issue::warning(field(&["name"]), "名称过短");
+        assert_eq!(
+            Diagnostic::from_issue(file, SOURCE, &issue).to_string(),
+            "警告: 名称过短\n --> apps/soft_mgr.yaml:2:7\n  |\n2 | name: 软件管家\n  |       ^^^^^^^^"
+        );
+        let source = "name: 软件管家\nrisk: 高\n";
+        let diagnostic = Diagnostic::at(Severity::Error, file, source, Span { line: 1, column: 9, len: 2 }, "x");
+        assert!(diagnostic.to_string().ends_with("\n  |           ^^^^"), "{}", diagnostic);
+
+        let issue = Issue::error(field(&["tests"]), "缺少测试样例");
+        assert_eq!(Diagnostic::from_issue(file, SOURCE, &issue).to_string(), "错误: 缺少测试样例\n  --> apps/soft_mgr.yaml");
+    }
+}
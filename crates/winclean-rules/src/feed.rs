@@ -0,0 +1,745 @@
+//! 发布元数据
+//! 规则包旁的 root/targets/timestamp 元数据，支持多密钥门限签名与密钥轮换
+//!
+//! 角色划分参照 TUF：
+//!
+//! - `root.json`：各角色的公钥与签名门限，由根密钥签名。每个版本同时保存为 `<版本>.root.json`，
+//!   新版本须同时满足上一版本与自身根角色的门限，客户端从内置的根元数据逐版本更新
+//! - `targets.json`：发布文件（如 `rules.bin`）的长度与摘要，由 targets 密钥签名
+//! - `timestamp.json`：当前 `targets.json` 的版本、长度与摘要，由 timestamp 密钥签名，有效期短，用于发现冻结
+//!
+//! 各文件为 JSON，签名覆盖 `signed` 字段的紧凑 JSON 编码。镜像通过 [`Mirror`] 抽象，
+//! [`DirMirror`] 以本地目录充当镜像。
+
+use crate::signing::hex;
+use crate::time::{self, format_time, parse_time};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 最新根元数据的文件名
+pub const ROOT: &str = "root.json";
+
+/// 发布文件元数据的文件名
+pub const TARGETS: &str = "targets.json";
+
+/// 时间戳元数据的文件名
+pub const TIMESTAMP: &str = "timestamp.json";
+
+/// 指定版本的根元数据文件名
+pub fn versioned_root(version: u64) -> String {
+    format!("{}.root.json", version)
+}
+
+/// 角色
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Root,
+    Targets,
+    Timestamp,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Role::Root => "root",
+            Role::Targets => "targets",
+            Role::Timestamp => "timestamp",
+        })
+    }
+}
+
+/// 带签名的元数据
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Signed<T> {
+    pub signed: T,
+    pub signatures: Vec<KeySignature>,
+}
+
+/// 单个密钥的签名
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct KeySignature {
+    /// 公钥 id，见 [`key_id`]
+    pub keyid: String,
+    /// 签名（十六进制）
+    pub sig: String,
+}
+
+/// 根元数据
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Root {
+    pub version: u64,
+    /// 过期时间，格式为 `YYYY-MM-DDTHH:MM:SSZ`
+    pub expires: String,
+    /// 公钥 id 到公钥（十六进制）
+    pub keys: BTreeMap<String, String>,
+    pub roles: BTreeMap<Role, RoleKeys>,
+}
+
+/// 角色的公钥与门限
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RoleKeys {
+    pub keyids: Vec<String>,
+    /// 至少需要的有效签名数量
+    pub threshold: u32,
+}
+
+/// 发布文件元数据
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Targets {
+    pub version: u64,
+    pub expires: String,
+    /// 文件名到长度与摘要
+    pub targets: BTreeMap<String, FileInfo>,
+}
+
+/// 时间戳元数据
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Timestamp {
+    pub version: u64,
+    pub expires: String,
+    /// 当前的 `targets.json`
+    pub targets: MetaInfo,
+}
+
+/// 文件的长度与摘要
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FileInfo {
+    pub length: u64,
+    /// BLAKE3 摘要（十六进制）
+    pub blake3: String,
+}
+
+/// 元数据文件的版本、长度与摘要
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct MetaInfo {
+    pub version: u64,
+    pub length: u64,
+    pub blake3: String,
+}
+
+impl MetaInfo {
+    pub fn new(version: u64, data: &[u8]) -> Self {
+        let FileInfo { length, blake3 } = FileInfo::new(data);
+        MetaInfo { version, length, blake3 }
+    }
+}
+
+impl FileInfo {
+    pub fn new(data: &[u8]) -> Self {
+        FileInfo { length: data.len() as u64, blake3: hex(blake3::hash(data).as_bytes()) }
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        *self == FileInfo::new(data)
+    }
+}
+
+/// 元数据的公共部分
+pub trait Metadata: Serialize + DeserializeOwned {
+    /// 负责签名的角色
+    const ROLE: Role;
+
+    fn version(&self) -> u64;
+
+    fn expires(&self) -> &str;
+}
+
+impl Metadata for Root {
+    const ROLE: Role = Role::Root;
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn expires(&self) -> &str {
+        &self.expires
+    }
+}
+
+impl Metadata for Targets {
+    const ROLE: Role = Role::Targets;
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn expires(&self) -> &str {
+        &self.expires
+    }
+}
+
+impl Metadata for Timestamp {
+    const ROLE: Role = Role::Timestamp;
+
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn expires(&self) -> &str {
+        &self.expires
+    }
+}
+
+/// 元数据错误
+#[derive(Debug)]
+pub enum FeedError {
+    /// 读取镜像失败
+    Io { name: String, error: io::Error },
+    /// 镜像中缺少文件
+    Missing(String),
+    /// 文件无法解析
+    Invalid { name: String, message: String },
+    /// 有效签名不足
+    Threshold { role: Role, valid: usize, threshold: u32 },
+    /// 版本与预期不符
+    VersionMismatch { name: String, expected: u64, found: u64 },
+    /// 版本早于已接受的版本
+    Rollback { role: Role, version: u64, last: u64 },
+    /// 元数据已过期
+    Expired { role: Role, expires: String },
+    /// 文件长度或摘要与元数据不符
+    Mismatch(String),
+    /// 发布文件不在 targets 中
+    UnknownTarget(String),
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::Io { name, error } => write!(f, "读取 {} 失败: {}", name, error),
+            FeedError::Missing(name) => write!(f, "镜像中缺少 {}", name),
+            FeedError::Invalid { name, message } => write!(f, "{} 格式错误: {}", name, message),
+            FeedError::Threshold { role, valid, threshold } => {
+                write!(f, "{} 元数据签名不足: 有效签名 {} 个，门限 {}", role, valid, threshold)
+            }
+            FeedError::VersionMismatch { name, expected, found } => {
+                write!(f, "{} 的版本为 {}，应为 {}", name, found, expected)
+            }
+            FeedError::Rollback { role, version, last } => {
+                write!(f, "{} 元数据版本 {} 早于已接受的 {}，拒绝回滚", role, version, last)
+            }
+            FeedError::Expired { role, expires } => write!(f, "{} 元数据已于 {} 过期", role, expires),
+            FeedError::Mismatch(name) => write!(f, "{} 的长度或摘要与元数据不符", name),
+            FeedError::UnknownTarget(name) => write!(f, "targets 元数据中没有 {}", name),
+        }
+    }
+}
+
+impl std::error::Error for FeedError {}
+
+impl<T: Metadata> Signed<T> {
+    /// 未签名的元数据
+    pub fn new(signed: T) -> Self {
+        Signed { signed, signatures: Vec::new() }
+    }
+
+    /// 解析元数据文件（不校验签名）
+    pub fn from_slice(name: &str, data: &[u8]) -> Result<Self, FeedError> {
+        serde_json::from_slice(data).map_err(|e| FeedError::Invalid { name: name.to_string(), message: e.to_string() })
+    }
+
+    /// 元数据文件内容
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).expect("元数据可以编码为 JSON");
+        json.push('\n');
+        json
+    }
+
+    /// 追加签名，替换同一公钥的旧签名
+    pub fn sign(&mut self, key: &SigningKey) {
+        let keyid = key_id(&key.verifying_key());
+        let sig = hex(&key.sign(&self.canonical()).to_bytes());
+        self.signatures.retain(|s| s.keyid != keyid);
+        self.signatures.push(KeySignature { keyid, sig });
+    }
+
+    /// 按 `root` 中该角色的公钥统计有效签名，不足门限时返回错误
+    ///
+    /// 公钥 id 必须是公钥的摘要，有效签名按公钥去重，同一公钥以多个 id 列出也只计一次。
+    pub fn verify(&self, root: &Root) -> Result<(), FeedError> {
+        let role = root.roles.get(&T::ROLE);
+        let threshold = role.map_or(1, |r| r.threshold.max(1));
+        let allowed: BTreeSet<&str> = role.map(|r| r.keyids.iter().map(String::as_str).collect()).unwrap_or_default();
+
+        let message = self.canonical();
+        let valid: BTreeSet<[u8; 32]> = self
+            .signatures
+            .iter()
+            .filter(|s| allowed.contains(s.keyid.as_str()))
+            .filter_map(|s| {
+                let key = root.keys.get(&s.keyid).and_then(|k| parse_key(k)).filter(|k| key_id(k) == s.keyid)?;
+                let sig = unhex(&s.sig).and_then(|b| Signature::from_slice(&b).ok())?;
+                key.verify_strict(&message, &sig).is_ok().then(|| key.to_bytes())
+            })
+            .collect();
+
+        if valid.len() < threshold as usize {
+            return Err(FeedError::Threshold { role: T::ROLE, valid: valid.len(), threshold });
+        }
+        Ok(())
+    }
+
+    /// 检查是否过期
+    pub fn check_expiry(&self, now: u64) -> Result<(), FeedError> {
+        let expires = self.signed.expires();
+        match parse_time(expires) {
+            Some(time) if now < time => Ok(()),
+            Some(_) => Err(FeedError::Expired { role: T::ROLE, expires: expires.to_string() }),
+            None => Err(FeedError::Invalid {
+                name: T::ROLE.to_string(),
+                message: format!("过期时间 `{}` 格式错误", expires),
+            }),
+        }
+    }
+
+    /// 签名覆盖的内容
+    fn canonical(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.signed).expect("元数据可以编码为 JSON")
+    }
+}
+
+impl Root {
+    /// 添加公钥到角色
+    pub fn add_key(&mut self, role: Role, key: &VerifyingKey) {
+        let id = key_id(key);
+        self.keys.insert(id.clone(), hex(key.as_bytes()));
+        let keys = self.roles.entry(role).or_insert(RoleKeys { keyids: Vec::new(), threshold: 1 });
+        if !keys.keyids.contains(&id) {
+            keys.keyids.push(id);
+        }
+    }
+}
+
+impl Targets {
+    /// 从镜像读取发布文件，长度与摘要需与元数据一致
+    pub fn fetch<M: Mirror + ?Sized>(&self, mirror: &M, name: &str) -> Result<Vec<u8>, FeedError> {
+        let info = self.targets.get(name).ok_or_else(|| FeedError::UnknownTarget(name.to_string()))?;
+        let data = fetch_required(mirror, name)?;
+        if !info.matches(&data) {
+            return Err(FeedError::Mismatch(name.to_string()));
+        }
+        Ok(data)
+    }
+}
+
+/// 公钥 id：公钥的 BLAKE3 摘要（十六进制）
+pub fn key_id(key: &VerifyingKey) -> String {
+    hex(blake3::hash(key.as_bytes()).as_bytes())
+}
+
+/// 当前时间（Unix 时间戳，秒）
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// 自当前时间起若干天后的过期时间，超出可表示的范围时返回 `None`
+pub fn expires_in_days(days: u64) -> Option<String> {
+    time::add_days(now(), days).map(format_time)
+}
+
+/// 镜像
+pub trait Mirror {
+    /// 读取文件，不存在时返回 `None`
+    fn fetch(&self, name: &str) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// 以本地目录充当的镜像
+#[derive(Debug, Clone)]
+pub struct DirMirror {
+    root: PathBuf,
+}
+
+impl DirMirror {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirMirror { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Mirror for DirMirror {
+    fn fetch(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        // 元数据中的文件名只能是单个文件名
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("文件名无效: {}", name)));
+        }
+        match fs::read(self.root.join(name)) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn fetch_required<M: Mirror + ?Sized>(mirror: &M, name: &str) -> Result<Vec<u8>, FeedError> {
+    mirror
+        .fetch(name)
+        .map_err(|error| FeedError::Io { name: name.to_string(), error })?
+        .ok_or_else(|| FeedError::Missing(name.to_string()))
+}
+
+fn fetch_metadata<T: Metadata, M: Mirror + ?Sized>(mirror: &M, name: &str) -> Result<Signed<T>, FeedError> {
+    Signed::from_slice(name, &fetch_required(mirror, name)?)
+}
+
+/// 客户端保存的信任状态
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TrustedState {
+    /// 已接受的最新根元数据
+    pub root: Signed<Root>,
+    /// 已接受的 timestamp 版本
+    pub timestamp_version: u64,
+    /// 已接受的 targets 版本
+    pub targets_version: u64,
+}
+
+impl TrustedState {
+    /// 以内置的根元数据为起点
+    pub fn pinned(root: Signed<Root>) -> Self {
+        TrustedState { root, timestamp_version: 0, targets_version: 0 }
+    }
+}
+
+/// 客户端
+///
+/// 从信任状态出发逐版本更新根元数据，再校验 timestamp 与 targets：
+///
+/// ```no_run
+/// use winclean_rules::feed::{Client, DirMirror, Signed, TrustedState};
+///
+/// let root = Signed::from_slice("root.json", &std::fs::read("keys/root.json")?)?;
+/// let mirror = DirMirror::new("dist");
+/// let mut client = Client::new(TrustedState::pinned(root))?;
+/// let targets = client.update(&mirror)?;
+/// let package = targets.fetch(&mirror, "rules.bin")?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Client {
+    state: TrustedState,
+    now: u64,
+}
+
+impl Client {
+    /// 根元数据须满足自身根角色的门限
+    pub fn new(state: TrustedState) -> Result<Self, FeedError> {
+        state.root.verify(&state.root.signed)?;
+        Ok(Client { state, now: now() })
+    }
+
+    /// 以指定时间判断是否过期
+    pub fn at(self, now: u64) -> Self {
+        Client { now, ..self }
+    }
+
+    /// 当前信任状态，更新成功后应持久保存
+    pub fn state(&self) -> &TrustedState {
+        &self.state
+    }
+
+    /// 更新并校验全部元数据，返回可信的 targets
+    pub fn update<M: Mirror + ?Sized>(&mut self, mirror: &M) -> Result<Targets, FeedError> {
+        self.update_root(mirror)?;
+        let root = &self.state.root;
+        root.check_expiry(self.now)?;
+
+        let timestamp: Signed<Timestamp> = fetch_metadata(mirror, TIMESTAMP)?;
+        timestamp.verify(&root.signed)?;
+        if timestamp.signed.version < self.state.timestamp_version {
+            return Err(FeedError::Rollback {
+                role: Role::Timestamp,
+                version: timestamp.signed.version,
+                last: self.state.timestamp_version,
+            });
+        }
+        timestamp.check_expiry(self.now)?;
+
+        let meta = &timestamp.signed.targets;
+        let data = fetch_required(mirror, TARGETS)?;
+        if MetaInfo::new(meta.version, &data) != *meta {
+            return Err(FeedError::Mismatch(TARGETS.to_string()));
+        }
+        let targets: Signed<Targets> = Signed::from_slice(TARGETS, &data)?;
+        targets.verify(&root.signed)?;
+        if targets.signed.version != meta.version {
+            return Err(FeedError::VersionMismatch {
+                name: TARGETS.to_string(),
+                expected: meta.version,
+                found: targets.signed.version,
+            });
+        }
+        if targets.signed.version < self.state.targets_version {
+            return Err(FeedError::Rollback {
+                role: Role::Targets,
+                version: targets.signed.version,
+                last: self.state.targets_version,
+            });
+        }
+        targets.check_expiry(self.now)?;
+
+        self.state.timestamp_version = timestamp.signed.version;
+        self.state.targets_version = targets.signed.version;
+        Ok(targets.signed)
+    }
+
+    /// 逐版本接受新的根元数据
+    fn update_root<M: Mirror + ?Sized>(&mut self, mirror: &M) -> Result<(), FeedError> {
+        loop {
+            let version = self.state.root.signed.version + 1;
+            let name = versioned_root(version);
+            let Some(data) = mirror.fetch(&name).map_err(|error| FeedError::Io { name: name.clone(), error })? else {
+                break;
+            };
+
+            let root: Signed<Root> = Signed::from_slice(&name, &data)?;
+            root.verify(&self.state.root.signed)?;
+            root.verify(&root.signed)?;
+            if root.signed.version != version {
+                return Err(FeedError::VersionMismatch { name, expected: version, found: root.signed.version });
+            }
+
+            // 轮换了 timestamp 或 targets 密钥后，旧密钥签过的版本号不再可信
+            let old = &self.state.root.signed;
+            for (role, last) in [
+                (Role::Timestamp, &mut self.state.timestamp_version),
+                (Role::Targets, &mut self.state.targets_version),
+            ] {
+                if old.roles.get(&role) != root.signed.roles.get(&role) {
+                    *last = 0;
+                }
+            }
+
+            self.state.root = root;
+        }
+        Ok(())
+    }
+}
+
+/// 公钥的十六进制形式解析为公钥
+fn parse_key(text: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = unhex(text)?.try_into().ok()?;
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+    const EXPIRES: &str = "2100-01-01T00:00:00Z";
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn root(
+        version: u64,
+        root_keys: &[&SigningKey],
+        threshold: u32,
+        targets: &SigningKey,
+        timestamp: &SigningKey,
+    ) -> Root {
+        let mut root = Root { version, expires: EXPIRES.to_string(), keys: BTreeMap::new(), roles: BTreeMap::new() };
+        for key in root_keys {
+            root.add_key(Role::Root, &key.verifying_key());
+        }
+        root.roles.get_mut(&Role::Root).unwrap().threshold = threshold;
+        root.add_key(Role::Targets, &targets.verifying_key());
+        root.add_key(Role::Timestamp, &timestamp.verifying_key());
+        root
+    }
+
+    fn sign<T: Metadata>(value: T, keys: &[&SigningKey]) -> Signed<T> {
+        let mut signed = Signed::new(value);
+        for key in keys {
+            signed.sign(key);
+        }
+        signed
+    }
+
+    #[derive(Default)]
+    struct MemoryMirror(BTreeMap<String, Vec<u8>>);
+
+    impl Mirror for MemoryMirror {
+        fn fetch(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+            Ok(self.0.get(name).cloned())
+        }
+    }
+
+    impl MemoryMirror {
+        fn put<T: Metadata>(&mut self, name: &str, signed: &Signed<T>) {
+            self.0.insert(name.to_string(), signed.to_json().into_bytes());
+        }
+
+        /// 发布 `rules.bin` 及对应的 targets 与 timestamp
+        fn publish(&mut self, version: u64, package: &[u8], targets_key: &SigningKey, timestamp_key: &SigningKey) {
+            self.0.insert("rules.bin".to_string(), package.to_vec());
+            let targets = Targets {
+                version,
+                expires: EXPIRES.to_string(),
+                targets: BTreeMap::from([("rules.bin".to_string(), FileInfo::new(package))]),
+            };
+            let targets = sign(targets, &[targets_key]).to_json();
+            let timestamp = Timestamp {
+                version,
+                expires: EXPIRES.to_string(),
+                targets: MetaInfo::new(version, targets.as_bytes()),
+            };
+            self.0.insert(TARGETS.to_string(), targets.into_bytes());
+            self.put(TIMESTAMP, &sign(timestamp, &[timestamp_key]));
+        }
+    }
+
+    fn client(root: &Signed<Root>) -> Client {
+        Client::new(TrustedState::pinned(root.clone())).unwrap().at(NOW)
+    }
+
+    #[test]
+    fn requires_threshold_of_distinct_keys() {
+        let (a, b, c) = (key(1), key(2), key(3));
+        let root = root(1, &[&a, &b], 2, &key(10), &key(11));
+
+        assert!(sign(root.clone(), &[&a, &b]).verify(&root).is_ok());
+        assert!(matches!(
+            sign(root.clone(), &[&a]).verify(&root),
+            Err(FeedError::Threshold { role: Role::Root, valid: 1, threshold: 2 })
+        ));
+        // 不属于该角色的密钥不计入
+        assert!(matches!(sign(root.clone(), &[&a, &c]).verify(&root), Err(FeedError::Threshold { valid: 1, .. })));
+
+        // 同一签名重复列出只计一次
+        let mut signed = sign(root.clone(), &[&a]);
+        signed.signatures.push(signed.signatures[0].clone());
+        assert!(matches!(signed.verify(&root), Err(FeedError::Threshold { valid: 1, .. })));
+
+        // 篡改内容或签名后无效
+        let mut signed = sign(root.clone(), &[&a, &b]);
+        signed.signed.version = 2;
+        assert!(matches!(signed.verify(&root), Err(FeedError::Threshold { valid: 0, .. })));
+        let mut signed = sign(root.clone(), &[&a, &b]);
+        signed.signatures[1].sig = "zz".repeat(64);
+        assert!(matches!(signed.verify(&root), Err(FeedError::Threshold { valid: 1, .. })));
+    }
+
+    #[test]
+    fn rejects_key_listed_under_another_id() {
+        let a = key(1);
+        let mut root = root(1, &[&a, &key(2)], 2, &key(10), &key(11));
+
+        // 同一公钥以另一个 id 列出，不能凑够门限
+        let alias = "alias".to_string();
+        root.keys.insert(alias.clone(), hex(a.verifying_key().as_bytes()));
+        root.roles.get_mut(&Role::Root).unwrap().keyids.push(alias.clone());
+        let mut signed = sign(root.clone(), &[&a]);
+        signed.signatures.push(KeySignature { keyid: alias, sig: signed.signatures[0].sig.clone() });
+        assert!(matches!(signed.verify(&root), Err(FeedError::Threshold { valid: 1, .. })));
+
+        // id 与公钥不符时整条作废
+        let mut root = root.clone();
+        root.roles.get_mut(&Role::Root).unwrap().threshold = 1;
+        root.keys.insert(key_id(&a.verifying_key()), hex(key(2).verifying_key().as_bytes()));
+        let mut signed = sign(root.clone(), &[&key(2)]);
+        signed.signatures[0].keyid = key_id(&a.verifying_key());
+        assert!(matches!(signed.verify(&root), Err(FeedError::Threshold { valid: 0, .. })));
+    }
+
+    #[test]
+    fn client_verifies_targets_and_files() {
+        let (root_key, targets_key, timestamp_key) = (key(1), key(10), key(11));
+        let root = sign(root(1, &[&root_key], 1, &targets_key, &timestamp_key), &[&root_key]);
+        let mut mirror = MemoryMirror::default();
+        mirror.put(ROOT, &root);
+        mirror.publish(3, b"rules v3", &targets_key, &timestamp_key);
+
+        let mut client = client(&root);
+        let targets = client.update(&mirror).unwrap();
+        assert_eq!(targets.fetch(&mirror, "rules.bin").unwrap(), b"rules v3");
+        assert!(matches!(targets.fetch(&mirror, "other.bin"), Err(FeedError::UnknownTarget(_))));
+        assert_eq!((client.state().timestamp_version, client.state().targets_version), (3, 3));
+
+        mirror.0.insert("rules.bin".to_string(), b"rules v4".to_vec());
+        assert!(matches!(targets.fetch(&mirror, "rules.bin"), Err(FeedError::Mismatch(_))));
+
+        // 已接受更新的版本后，旧版本视为回滚
+        mirror.publish(2, b"rules v2", &targets_key, &timestamp_key);
+        assert!(matches!(
+            client.update(&mirror),
+            Err(FeedError::Rollback { role: Role::Timestamp, version: 2, last: 3 })
+        ));
+
+        // targets 不是由 targets 密钥签名
+        mirror.publish(4, b"rules v4", &timestamp_key, &timestamp_key);
+        assert!(matches!(client.update(&mirror), Err(FeedError::Threshold { role: Role::Targets, .. })));
+
+        // timestamp 记录的摘要与 targets.json 不符
+        mirror.publish(4, b"rules v4", &targets_key, &timestamp_key);
+        mirror.0.get_mut(TARGETS).unwrap().push(b'\n');
+        assert!(matches!(client.update(&mirror), Err(FeedError::Mismatch(_))));
+
+        mirror.publish(4, b"rules v4", &targets_key, &timestamp_key);
+        assert!(matches!(client.clone().at(4_200_000_000).update(&mirror), Err(FeedError::Expired { .. })));
+        assert!(client.update(&mirror).is_ok());
+    }
+
+    #[test]
+    fn rotates_keys_through_versioned_roots() {
+        let (old_root, new_root) = (key(1), key(2));
+        let (old_targets, new_targets) = (key(10), key(12));
+        let (old_timestamp, new_timestamp) = (key(11), key(13));
+        let v1 = sign(root(1, &[&old_root], 1, &old_targets, &old_timestamp), &[&old_root]);
+        let mut mirror = MemoryMirror::default();
+        mirror.publish(5, b"rules v5", &old_targets, &old_timestamp);
+        let mut client = client(&v1);
+        client.update(&mirror).unwrap();
+
+        // 新根元数据只有新根密钥签名，不满足上一版本的门限
+        let v2 = root(2, &[&new_root], 1, &new_targets, &new_timestamp);
+        mirror.put(&versioned_root(2), &sign(v2.clone(), &[&new_root]));
+        assert!(matches!(client.clone().update(&mirror), Err(FeedError::Threshold { role: Role::Root, .. })));
+
+        // 新旧根密钥共同签名后接受轮换；targets 与 timestamp 密钥更换后版本号从头开始
+        mirror.put(&versioned_root(2), &sign(v2, &[&old_root, &new_root]));
+        mirror.publish(1, b"rules v1", &new_targets, &new_timestamp);
+        let targets = client.update(&mirror).unwrap();
+        assert_eq!(targets.fetch(&mirror, "rules.bin").unwrap(), b"rules v1");
+        assert_eq!(client.state().root.signed.version, 2);
+        assert_eq!((client.state().timestamp_version, client.state().targets_version), (1, 1));
+
+        // 旧密钥不再有效
+        mirror.publish(2, b"rules v2", &old_targets, &new_timestamp);
+        assert!(matches!(client.update(&mirror), Err(FeedError::Threshold { role: Role::Targets, .. })));
+        mirror.publish(2, b"rules v2", &new_targets, &old_timestamp);
+        assert!(matches!(client.update(&mirror), Err(FeedError::Threshold { role: Role::Timestamp, .. })));
+
+        // 版本号与文件名不符的根元数据
+        let v3 = root(4, &[&new_root], 1, &new_targets, &new_timestamp);
+        mirror.put(&versioned_root(3), &sign(v3, &[&new_root]));
+        assert!(matches!(client.update(&mirror), Err(FeedError::VersionMismatch { expected: 3, found: 4, .. })));
+    }
+}
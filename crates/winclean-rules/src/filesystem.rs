@@ -0,0 +1,431 @@
+//! 文件系统匹配
+//! 在 Windows 目录树上展开 `match.path` 规则，找出将被删除的文件与目录
+//!
+//! 路径均为 Windows 形式（`C:\Users\alice`），匹配时按实际目录项逐级展开，
+//! 因此大小写与磁盘上不一致也能找到；返回的路径使用磁盘上的实际名称。
+
+use crate::pattern::{PathPattern, PatternError};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::UNIX_EPOCH;
+
+/// 目录项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    pub name: String,
+    pub is_dir: bool,
+    /// 文件大小，目录为 0
+    pub size: u64,
+    /// 修改时间（Unix 时间戳，秒），未知时为 `None`
+    pub modified: Option<u64>,
+}
+
+/// Windows 目录树
+///
+/// 空路径表示顶层，其目录项为各驱动器（如 `C:`）。路径不存在时返回空列表。
+pub trait FileTree {
+    /// 列出目录项
+    fn entries(&self, path: &str) -> io::Result<Vec<FileEntry>>;
+}
+
+/// 匹配结果
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileMatch {
+    pub path: String,
+    pub is_dir: bool,
+    /// 文件大小或目录内全部文件大小之和
+    pub size: u64,
+    pub modified: Option<u64>,
+}
+
+/// 在目录树上查找匹配路径模式的文件与目录
+pub fn find_paths<T: FileTree + ?Sized>(
+    pattern: &PathPattern,
+    tree: &T,
+) -> io::Result<Vec<FileMatch>> {
+    // (路径, 目录项)；顶层视为目录
+    let mut current = vec![(String::new(), None::<FileEntry>)];
+
+    for component in pattern.components() {
+        let mut next = Vec::new();
+        for (parent, entry) in &current {
+            if entry.as_ref().is_some_and(|e| !e.is_dir) {
+                continue;
+            }
+            for child in tree.entries(parent)? {
+                if component.matches(&child.name) {
+                    next.push((join_path(parent, &child.name), Some(child)));
+                }
+            }
+        }
+        current = next;
+    }
+
+    let mut matches = Vec::new();
+    for (path, entry) in current {
+        let Some(entry) = entry else { continue };
+        let size = if entry.is_dir { total_size(tree, &path)? } else { entry.size };
+        matches.push(FileMatch { path, is_dir: entry.is_dir, size, modified: entry.modified });
+    }
+
+    Ok(matches)
+}
+
+/// 目录内全部文件大小之和
+pub fn total_size<T: FileTree + ?Sized>(tree: &T, path: &str) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in tree.entries(path)? {
+        size += if entry.is_dir {
+            total_size(tree, &join_path(path, &entry.name))?
+        } else {
+            entry.size
+        };
+    }
+    Ok(size)
+}
+
+/// 连接 Windows 路径
+pub fn join_path(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}\\{}", parent, name)
+    }
+}
+
+/// 去掉已被上级目录覆盖的匹配，避免重复计算大小
+pub fn dedup_matches(matches: Vec<FileMatch>) -> Vec<FileMatch> {
+    let by_path: BTreeMap<String, FileMatch> =
+        matches.into_iter().map(|m| (m.path.to_lowercase(), m)).collect();
+
+    by_path
+        .iter()
+        .filter(|(key, _)| {
+            !by_path.iter().any(|(other, m)| {
+                m.is_dir && key.len() > other.len() && key.starts_with(&format!("{}\\", other))
+            })
+        })
+        .map(|(_, m)| m.clone())
+        .collect()
+}
+
+/// 挂载在本地目录上的 Windows 系统盘
+///
+/// 只有系统盘（默认 `C:`）映射到挂载根目录，其他驱动器视为不存在。
+/// 不跟随符号链接，链接本身按大小为 0 的文件处理。无法读取的目录与目录项
+/// 跳过并记录警告，不中断扫描。
+#[derive(Debug, Clone)]
+pub struct MountedTree {
+    root: PathBuf,
+    drive: String,
+    warnings: RefCell<Vec<String>>,
+}
+
+impl MountedTree {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        MountedTree { root: root.into(), drive: "C:".to_string(), warnings: RefCell::default() }
+    }
+
+    /// 取出目前为止跳过的目录与目录项的警告
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    fn warn(&self, path: &str, error: &io::Error) {
+        self.warnings.borrow_mut().push(format!("无法读取 `{}`，已跳过: {}", path, error));
+    }
+
+    /// 映射为本地路径，路径不在系统盘上时返回 `None`
+    fn local_path(&self, path: &str) -> Option<PathBuf> {
+        let mut components = path.split('\\').filter(|s| !s.is_empty());
+        if !components.next()?.eq_ignore_ascii_case(&self.drive) {
+            return None;
+        }
+        Some(components.fold(self.root.clone(), |local, name| local.join(name)))
+    }
+}
+
+impl FileTree for MountedTree {
+    fn entries(&self, path: &str) -> io::Result<Vec<FileEntry>> {
+        if path.is_empty() {
+            return Ok(vec![FileEntry { name: self.drive.clone(), is_dir: true, size: 0, modified: None }]);
+        }
+
+        let Some(local) = self.local_path(path) else { return Ok(Vec::new()) };
+        let dir = match fs::read_dir(&local) {
+            Ok(dir) => dir,
+            Err(e) if matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory) => {
+                return Ok(Vec::new())
+            }
+            Err(e) => {
+                self.warn(path, &e);
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut entries = Vec::new();
+        for item in dir {
+            let item = match item {
+                Ok(item) => item,
+                Err(e) => {
+                    self.warn(path, &e);
+                    continue;
+                }
+            };
+            let name = item.file_name().to_string_lossy().into_owned();
+            let metadata = match fs::symlink_metadata(item.path()) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    self.warn(&join_path(path, &name), &e);
+                    continue;
+                }
+            };
+            entries.push(FileEntry {
+                name,
+                is_dir: metadata.is_dir(),
+                size: if metadata.is_file() { metadata.len() } else { 0 },
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs()),
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(entries)
+    }
+}
+
+/// 内存中的目录树，用于测试与模拟
+///
+/// 名称不区分大小写，插入时自动创建上级目录。
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTree {
+    root: MemoryNode,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MemoryNode {
+    entry: Option<FileEntry>,
+    /// 以小写名称索引
+    children: BTreeMap<String, MemoryNode>,
+}
+
+impl MemoryTree {
+    pub fn new() -> Self {
+        MemoryTree::default()
+    }
+
+    /// 创建目录（含所有上级目录）
+    pub fn insert_dir(&mut self, path: &str, modified: Option<u64>) {
+        if let Some(entry) = &mut self.node_mut(path).entry {
+            entry.is_dir = true;
+            entry.size = 0;
+            entry.modified = modified.or(entry.modified);
+        }
+    }
+
+    /// 创建文件，上级目录不存在时自动创建
+    pub fn insert_file(&mut self, path: &str, size: u64, modified: Option<u64>) {
+        if let Some(entry) = &mut self.node_mut(path).entry {
+            *entry = FileEntry { name: entry.name.clone(), is_dir: false, size, modified };
+        }
+    }
+
+    fn node_mut(&mut self, path: &str) -> &mut MemoryNode {
+        let mut node = &mut self.root;
+        for name in path.split(['\\', '/']).filter(|s| !s.is_empty()) {
+            node = node.children.entry(name.to_lowercase()).or_insert_with(|| MemoryNode {
+                entry: Some(FileEntry { name: name.to_string(), is_dir: true, size: 0, modified: None }),
+                children: BTreeMap::new(),
+            });
+        }
+        node
+    }
+
+    fn node(&self, path: &str) -> Option<&MemoryNode> {
+        let mut node = &self.root;
+        for name in path.split(['\\', '/']).filter(|s| !s.is_empty()) {
+            node = node.children.get(&name.to_lowercase())?;
+        }
+        Some(node)
+    }
+}
+
+impl FileTree for MemoryTree {
+    fn entries(&self, path: &str) -> io::Result<Vec<FileEntry>> {
+        Ok(self
+            .node(path)
+            .filter(|node| node.entry.as_ref().is_none_or(|e| e.is_dir))
+            .map(|node| node.children.values().filter_map(|c| c.entry.clone()).collect())
+            .unwrap_or_default())
+    }
+}
+
+/// Windows 环境变量
+///
+/// 变量名不区分大小写。[`WindowsEnv::system`] 与 [`WindowsEnv::for_user`]
+/// 按默认安装布局生成常用变量。
+#[derive(Debug, Clone, Default)]
+pub struct WindowsEnv {
+    /// 以大写变量名索引
+    vars: BTreeMap<String, String>,
+}
+
+impl WindowsEnv {
+    pub fn new() -> Self {
+        WindowsEnv::default()
+    }
+
+    /// 默认布局下与用户无关的环境变量（系统盘为 `C:`）
+    pub fn system() -> Self {
+        let mut env = WindowsEnv::new();
+
+        env.set("SystemDrive", "C:");
+        env.set("SystemRoot", "C:\\Windows");
+        env.set("windir", "C:\\Windows");
+        env.set("ProgramFiles", "C:\\Program Files");
+        env.set("ProgramFiles(x86)", "C:\\Program Files (x86)");
+        env.set("ProgramW6432", "C:\\Program Files");
+        env.set("CommonProgramFiles", "C:\\Program Files\\Common Files");
+        env.set("CommonProgramFiles(x86)", "C:\\Program Files (x86)\\Common Files");
+        env.set("ProgramData", "C:\\ProgramData");
+        env.set("ALLUSERSPROFILE", "C:\\ProgramData");
+        env.set("PUBLIC", "C:\\Users\\Public");
+
+        env
+    }
+
+    /// 默认布局下某个用户的环境变量
+    pub fn for_user(user: &str) -> Self {
+        let profile = format!("C:\\Users\\{}", user);
+        let mut env = WindowsEnv::system();
+
+        env.set("USERNAME", user);
+        env.set("HOMEDRIVE", "C:");
+        env.set("HOMEPATH", &format!("\\Users\\{}", user));
+        env.set("USERPROFILE", &profile);
+        env.set("APPDATA", &format!("{}\\AppData\\Roaming", profile));
+        env.set("LOCALAPPDATA", &format!("{}\\AppData\\Local", profile));
+        env.set("TEMP", &format!("{}\\AppData\\Local\\Temp", profile));
+        env.set("TMP", &format!("{}\\AppData\\Local\\Temp", profile));
+
+        env
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.vars.insert(name.to_uppercase(), value.to_string());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(&name.to_uppercase()).map(|s| s.as_str())
+    }
+
+    /// 展开路径模式的环境变量前缀
+    ///
+    /// 没有前缀时原样返回，变量未定义时返回 `Ok(None)`。
+    pub fn expand(&self, pattern: &PathPattern) -> Result<Option<PathPattern>, PatternError> {
+        match pattern.env() {
+            None => Ok(Some(pattern.clone())),
+            Some(name) => match self.get(name) {
+                Some(value) => pattern.expand_env(|_| Some(value.to_string())).transpose(),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn write(root: &Path, path: &str, size: usize) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, vec![0u8; size]).unwrap();
+    }
+
+    fn find(tree: &MountedTree, env: &WindowsEnv, pattern: &str) -> Vec<FileMatch> {
+        let pattern = env.expand(&PathPattern::parse(pattern).unwrap()).unwrap().unwrap();
+        find_paths(&pattern, tree).unwrap()
+    }
+
+    #[test]
+    fn expands_env_against_mounted_root() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        write(root, "Users/alice/AppData/Roaming/SoftMgr2/a.txt", 100);
+        write(root, "Users/alice/AppData/Roaming/SoftMgr2/sub/b.bin", 50);
+        write(root, "Users/alice/AppData/Roaming/SoftMgr", 7);
+        write(root, "Users/alice/AppData/Local/Temp/QQSoftMgrSetup.exe", 30);
+        write(root, "Users/bob/AppData/Roaming/SoftMgr3/c.txt", 11);
+        let tree = MountedTree::new(root);
+        let alice = WindowsEnv::for_user("alice");
+
+        let matches = find(&tree, &alice, "%APPDATA%\\<SoftMgr.+>");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, "C:\\Users\\alice\\AppData\\Roaming\\SoftMgr2");
+        assert!(matches[0].is_dir);
+        assert_eq!(matches[0].size, 150);
+
+        // 名称大小写与磁盘不一致也能找到，返回磁盘上的名称
+        let matches = find(&tree, &alice, "%TEMP%\\<.+softmgr.+>");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, "C:\\Users\\alice\\AppData\\Local\\Temp\\QQSoftMgrSetup.exe");
+        assert_eq!(matches[0].size, 30);
+
+        let matches = find(&tree, &WindowsEnv::for_user("bob"), "%APPDATA%\\<SoftMgr.+>");
+        assert_eq!(matches.iter().map(|m| m.size).sum::<u64>(), 11);
+
+        // 只映射系统盘；不存在的目录与文件路径按空目录处理
+        assert!(find(&tree, &alice, "D:\\Users").is_empty());
+        assert!(tree.entries("C:\\Users\\carol").unwrap().is_empty());
+        assert!(tree.entries("C:\\Users\\alice\\AppData\\Roaming\\SoftMgr").unwrap().is_empty());
+        assert_eq!(total_size(&tree, "C:\\Users").unwrap(), 100 + 50 + 7 + 30 + 11);
+        assert!(tree.take_warnings().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_unreadable_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        write(root, "Data/readable/a.txt", 10);
+        write(root, "Data/locked/b.txt", 20);
+        let locked = root.join("Data/locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        let unreadable = fs::read_dir(&locked).is_err();
+
+        let tree = MountedTree::new(root);
+        let size = total_size(&tree, "C:\\Data");
+        let warnings = tree.take_warnings();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        // 以 root 运行时权限不生效，只检查扫描没有中断
+        if unreadable {
+            assert_eq!(size.unwrap(), 10);
+            assert_eq!(warnings.len(), 1);
+            assert!(warnings[0].starts_with("无法读取 `C:\\Data\\locked`，已跳过"), "{}", warnings[0]);
+        } else {
+            assert_eq!(size.unwrap(), 30);
+            assert!(warnings.is_empty());
+        }
+    }
+
+    #[test]
+    fn dedup_keeps_outermost_directory() {
+        let dir = FileMatch { path: "C:\\A".to_string(), is_dir: true, size: 3, modified: None };
+        let inner = FileMatch { path: "C:\\a\\b".to_string(), is_dir: false, size: 1, modified: None };
+        let sibling = FileMatch { path: "C:\\AB".to_string(), is_dir: false, size: 2, modified: None };
+        let matches = dedup_matches(vec![inner, sibling.clone(), dir.clone(), dir.clone()]);
+        assert_eq!(matches, [dir, sibling]);
+    }
+}
@@ -0,0 +1,295 @@
+//! 模拟夹具
+//! 以 YAML 描述的虚拟 Windows 环境（文件、大小、修改时间与注册表），用于可复现的规则模拟
+//!
+//! ```yaml
+//! users: [alice]
+//! files:
+//!   - path: 'C:\Users\alice\AppData\Roaming\SoftMgrUpdate\update.exe'
+//!     size: 1048576
+//!     modified: 2025-12-01T08:00:00Z
+//!   - path: 'C:\Windows\Prefetch'
+//!     dir: true
+//! registry:
+//!   - key: 'HKCR\*\shellex\ContextMenuHandlers\SoftMgrOEMExt'
+//!     values:
+//!       - name: ''
+//!         data: '{A1B2C3D4-0000-0000-0000-000000000000}'
+//!       - name: Flags
+//!         type: dword
+//!         data: '1'
+//! ```
+//!
+//! 时间一律按 UTC 解释，格式为 `YYYY-MM-DDTHH:MM:SSZ`（`T` 可写作空格，`Z` 可省略）。
+
+use crate::filesystem::MemoryTree;
+use crate::registry::{MemoryRegistry, RegistryData};
+use crate::time::parse_time;
+use serde::Deserialize;
+use std::fmt;
+
+/// 夹具
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Fixture {
+    /// 用户名，用于展开 `%APPDATA%` 等用户环境变量
+    #[serde(default)]
+    pub users: Vec<String>,
+    #[serde(default)]
+    pub files: Vec<FixtureFile>,
+    #[serde(default)]
+    pub registry: Vec<FixtureKey>,
+}
+
+/// 文件或目录
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureFile {
+    pub path: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified: Option<String>,
+    /// 是否为目录（上级目录会自动创建，无需列出）
+    #[serde(default)]
+    pub dir: bool,
+}
+
+/// 注册表键
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureKey {
+    pub key: String,
+    #[serde(default)]
+    pub values: Vec<FixtureValue>,
+}
+
+/// 注册表值
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureValue {
+    /// 值名，空字符串表示默认值
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: ValueKind,
+    /// 值数据的文本形式：多字符串以换行分隔，整数可写十进制或 `0x` 十六进制，二进制为十六进制
+    #[serde(default)]
+    pub data: String,
+}
+
+/// 注册表值类型
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    None,
+    #[default]
+    String,
+    ExpandString,
+    MultiString,
+    Dword,
+    Qword,
+    Binary,
+}
+
+/// 夹具错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixtureError {
+    /// 修改时间格式错误
+    InvalidTime { path: String, value: String },
+    /// 值数据与类型不符
+    InvalidData { key: String, name: String, data: String },
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FixtureError::InvalidTime { path, value } => {
+                write!(f, "{} 的修改时间 `{}` 无效: 格式应为 YYYY-MM-DDTHH:MM:SSZ", path, value)
+            }
+            FixtureError::InvalidData { key, name, data } => {
+                write!(f, "{} -> {} 的数据 `{}` 与值类型不符", key, name, data)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FixtureError {}
+
+impl Fixture {
+    /// 生成内存目录树与注册表
+    pub fn build(&self) -> Result<(MemoryTree, MemoryRegistry), FixtureError> {
+        let mut tree = MemoryTree::new();
+        for file in &self.files {
+            let modified = match &file.modified {
+                Some(value) => Some(parse_time(value).ok_or_else(|| FixtureError::InvalidTime {
+                    path: file.path.clone(),
+                    value: value.clone(),
+                })?),
+                None => None,
+            };
+            if file.dir {
+                tree.insert_dir(&file.path, modified);
+            } else {
+                tree.insert_file(&file.path, file.size, modified);
+            }
+        }
+
+        let mut registry = MemoryRegistry::new();
+        for key in &self.registry {
+            registry.insert_key(&key.key);
+            for value in &key.values {
+                let data = value.kind.parse(&value.data).ok_or_else(|| FixtureError::InvalidData {
+                    key: key.key.clone(),
+                    name: value.name.clone(),
+                    data: value.data.clone(),
+                })?;
+                registry.set_value(&key.key, &value.name, data);
+            }
+        }
+
+        Ok((tree, registry))
+    }
+}
+
+impl ValueKind {
+    /// 按类型解析值数据
+    fn parse(self, text: &str) -> Option<RegistryData> {
+        let integer = |text: &str| match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => text.parse().ok(),
+        };
+
+        Some(match self {
+            ValueKind::None => RegistryData::None,
+            ValueKind::String => RegistryData::String(text.to_string()),
+            ValueKind::ExpandString => RegistryData::ExpandString(text.to_string()),
+            ValueKind::MultiString => RegistryData::MultiString(text.lines().map(str::to_string).collect()),
+            ValueKind::Dword => RegistryData::Dword(u32::try_from(integer(text)?).ok()?),
+            ValueKind::Qword => RegistryData::Qword(integer(text)?),
+            ValueKind::Binary => {
+                let hex: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+                if !hex.len().is_multiple_of(2) {
+                    return None;
+                }
+                let bytes = hex
+                    .chunks(2)
+                    .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+                    .collect::<Option<Vec<u8>>>()?;
+                RegistryData::Binary(bytes)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{FileEntry, FileTree};
+    use crate::registry::RegistryTree;
+
+    fn fixture(yaml: &str) -> Fixture {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn entry(name: &str, is_dir: bool, size: u64, modified: Option<u64>) -> FileEntry {
+        FileEntry { name: name.to_string(), is_dir, size, modified }
+    }
+
+    #[test]
+    fn builds_files_with_sizes_and_times() {
+        let (tree, _) = fixture(
+            r"
+files:
+  - path: 'C:\Users\alice\AppData\Roaming\SoftMgrUpdate\update.exe'
+    size: 1048576
+    modified: 2025-12-01T08:00:00Z
+  - path: 'C:\Users\alice\AppData\Roaming\SoftMgrUpdate\config.ini'
+    modified: '2025-12-01 08:00:01'
+  - path: 'C:\Windows\Prefetch'
+    dir: true
+    modified: 1970-01-01T00:00:00Z
+",
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(tree.entries("").unwrap(), [entry("C:", true, 0, None)]);
+        assert_eq!(tree.entries("C:").unwrap(), [entry("Users", true, 0, None), entry("Windows", true, 0, None)]);
+        // 名称不区分大小写，按小写排序
+        assert_eq!(
+            tree.entries(r"c:\users\ALICE\appdata\roaming\softmgrupdate").unwrap(),
+            [entry("config.ini", false, 0, Some(1_764_576_001)), entry("update.exe", false, 1_048_576, Some(1_764_576_000))]
+        );
+        assert_eq!(tree.entries(r"C:\Windows").unwrap(), [entry("Prefetch", true, 0, Some(0))]);
+        assert!(tree.entries(r"C:\Windows\Prefetch").unwrap().is_empty());
+        // 文件不是目录
+        assert!(tree.entries(r"C:\Users\alice\AppData\Roaming\SoftMgrUpdate\update.exe").unwrap().is_empty());
+    }
+
+    #[test]
+    fn builds_registry_values_by_type() {
+        let (_, registry) = fixture(
+            r"
+registry:
+  - key: 'HKCR\*\shellex\ContextMenuHandlers\SoftMgrOEMExt'
+    values:
+      - data: '{A1B2}'
+      - name: Flags
+        type: dword
+        data: '0x10'
+      - name: Size
+        type: qword
+        data: '4294967296'
+      - name: Paths
+        type: multi_string
+        data: |-
+          C:\a
+          C:\b
+      - name: Blob
+        type: binary
+        data: 'de ad BE EF'
+      - name: Path
+        type: expand_string
+        data: '%TEMP%\x'
+      - name: Empty
+        type: none
+  - key: 'HKCU\Software\SoftMgr'
+",
+        )
+        .build()
+        .unwrap();
+
+        let values: Vec<(String, RegistryData)> = registry
+            .values(r"HKEY_CLASSES_ROOT\*\shellex\ContextMenuHandlers\SoftMgrOEMExt")
+            .unwrap()
+            .into_iter()
+            .map(|v| (v.name, v.data))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                (String::new(), RegistryData::String("{A1B2}".to_string())),
+                ("Flags".to_string(), RegistryData::Dword(16)),
+                ("Size".to_string(), RegistryData::Qword(1 << 32)),
+                ("Paths".to_string(), RegistryData::MultiString(vec![r"C:\a".to_string(), r"C:\b".to_string()])),
+                ("Blob".to_string(), RegistryData::Binary(vec![0xde, 0xad, 0xbe, 0xef])),
+                ("Path".to_string(), RegistryData::ExpandString(r"%TEMP%\x".to_string())),
+                ("Empty".to_string(), RegistryData::None),
+            ]
+        );
+        // 没有值的键同样存在
+        assert_eq!(registry.subkeys(r"HKCU\Software").unwrap(), ["SoftMgr"]);
+    }
+
+    #[test]
+    fn rejects_invalid_times_and_data() {
+        let error = fixture("files:\n  - path: 'C:\\a'\n    modified: 2025-02-30\n").build().unwrap_err();
+        assert_eq!(error, FixtureError::InvalidTime { path: r"C:\a".to_string(), value: "2025-02-30".to_string() });
+
+        for (kind, data) in [("dword", "4294967296"), ("dword", "-1"), ("qword", "0xZZ"), ("binary", "abc"), ("binary", "zz")] {
+            let yaml = format!("registry:\n  - key: 'HKCU\\k'\n    values:\n      - name: v\n        type: {}\n        data: '{}'\n", kind, data);
+            let error = fixture(&yaml).build().unwrap_err();
+            assert_eq!(error.to_string(), format!(r"HKCU\k -> v 的数据 `{}` 与值类型不符", data));
+        }
+    }
+}
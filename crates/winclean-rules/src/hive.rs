@@ -0,0 +1,753 @@
+//! 离线注册表配置单元
+//! 只读解析 regf 格式的配置单元文件（SOFTWARE、SYSTEM、NTUSER.DAT、UsrClass.dat），
+//! 并按 Windows 默认布局把它们挂载为 `HKLM`、`HKCU`、`HKCR` 下的注册表树
+//!
+//! 只读取主文件，不回放 `.LOG1`/`.LOG2` 事务日志；从运行中系统复制出的
+//! 配置单元可能缺少最近的修改。
+
+use crate::registry::{canonical_root, RegistryData, RegistryTree, RegistryValue};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// 基本块大小，hbin 数据从此偏移开始
+const BASE_BLOCK_SIZE: usize = 4096;
+
+/// 大数据（db）每段的最大长度
+const BIG_DATA_SEGMENT: usize = 16344;
+
+/// 子键索引（ri）最大嵌套层数
+const MAX_INDEX_DEPTH: usize = 8;
+
+/// nk 标志：键名为 ASCII（Latin-1）编码
+const KEY_COMP_NAME: u16 = 0x0020;
+
+/// vk 标志：值名为 ASCII（Latin-1）编码
+const VALUE_COMP_NAME: u16 = 0x0001;
+
+/// 注册表值类型
+const REG_NONE: u32 = 0;
+const REG_SZ: u32 = 1;
+const REG_EXPAND_SZ: u32 = 2;
+const REG_DWORD: u32 = 4;
+const REG_DWORD_BIG_ENDIAN: u32 = 5;
+const REG_MULTI_SZ: u32 = 7;
+const REG_QWORD: u32 = 11;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> io::Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| invalid(format!("读取越界: 偏移 {:#x}", offset)))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> io::Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| invalid(format!("读取越界: 偏移 {:#x}", offset)))
+}
+
+/// 解码键名或值名
+fn decode_name(bytes: &[u8], compressed: bool) -> String {
+    if compressed {
+        bytes.iter().map(|&b| b as char).collect()
+    } else {
+        decode_utf16(bytes)
+    }
+}
+
+/// 解码 UTF-16LE，忽略末尾的奇数字节
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// regf 配置单元
+#[derive(Debug)]
+pub struct Hive {
+    data: Vec<u8>,
+    root: u32,
+}
+
+/// 键节点（nk 单元）
+struct KeyNode<'a> {
+    cell: &'a [u8],
+}
+
+impl KeyNode<'_> {
+    fn name(&self) -> io::Result<String> {
+        let flags = read_u16(self.cell, 2)?;
+        let len = read_u16(self.cell, 72)? as usize;
+        let bytes = self.cell.get(76..76 + len).ok_or_else(|| invalid("键名越界"))?;
+        Ok(decode_name(bytes, flags & KEY_COMP_NAME != 0))
+    }
+
+    fn subkey_count(&self) -> io::Result<u32> {
+        read_u32(self.cell, 20)
+    }
+
+    fn subkey_list(&self) -> io::Result<u32> {
+        read_u32(self.cell, 28)
+    }
+
+    fn value_count(&self) -> io::Result<u32> {
+        read_u32(self.cell, 36)
+    }
+
+    fn value_list(&self) -> io::Result<u32> {
+        read_u32(self.cell, 40)
+    }
+}
+
+/// 展开子键索引时的状态
+struct SubkeyExpansion {
+    /// 键节点声明的子键数量
+    limit: usize,
+    /// 已展开的索引单元
+    visited: HashSet<u32>,
+    offsets: Vec<u32>,
+}
+
+impl Hive {
+    /// 读取配置单元文件
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Hive::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Self> {
+        if data.len() < BASE_BLOCK_SIZE || &data[..4] != b"regf" {
+            return Err(invalid("不是 regf 配置单元文件"));
+        }
+
+        let root = read_u32(&data, 0x24)?;
+        let hive = Hive { data, root };
+        hive.key(root)?;
+
+        Ok(hive)
+    }
+
+    /// 读取单元数据（不含4字节长度头）
+    fn cell(&self, offset: u32) -> io::Result<&[u8]> {
+        let start = BASE_BLOCK_SIZE
+            .checked_add(offset as usize)
+            .ok_or_else(|| invalid("单元偏移溢出"))?;
+        let size = read_u32(&self.data, start)? as i32;
+        let len = size.unsigned_abs() as usize;
+        if len < 4 || start + len > self.data.len() {
+            return Err(invalid(format!("单元长度无效: 偏移 {:#x}", offset)));
+        }
+        Ok(&self.data[start + 4..start + len])
+    }
+
+    fn key(&self, offset: u32) -> io::Result<KeyNode<'_>> {
+        let cell = self.cell(offset)?;
+        if cell.len() < 76 || &cell[..2] != b"nk" {
+            return Err(invalid(format!("不是键节点: 偏移 {:#x}", offset)));
+        }
+        Ok(KeyNode { cell })
+    }
+
+    /// 收集子键偏移
+    ///
+    /// 每个索引单元只展开一次，子键总数不超过键节点声明的数量，
+    /// 以免构造的 ri 索引反复引用同一列表造成指数级展开。
+    fn subkey_offsets(&self, list: u32, expand: &mut SubkeyExpansion, depth: usize) -> io::Result<()> {
+        if depth > MAX_INDEX_DEPTH {
+            return Err(invalid("子键索引嵌套过深"));
+        }
+        if !expand.visited.insert(list) {
+            return Err(invalid(format!("子键索引重复引用: 偏移 {:#x}", list)));
+        }
+
+        let cell = self.cell(list)?;
+        let count = read_u16(cell, 2)? as usize;
+        let stride = match cell.get(..2) {
+            Some(b"lf") | Some(b"lh") => 8,
+            Some(b"li") => 4,
+            Some(b"ri") => {
+                for i in 0..count {
+                    self.subkey_offsets(read_u32(cell, 4 + i * 4)?, expand, depth + 1)?;
+                }
+                return Ok(());
+            }
+            _ => return Err(invalid(format!("未知的子键索引: 偏移 {:#x}", list))),
+        };
+
+        if expand.offsets.len() + count > expand.limit {
+            return Err(invalid(format!("子键数量超过键节点声明的 {}: 偏移 {:#x}", expand.limit, list)));
+        }
+        for i in 0..count {
+            expand.offsets.push(read_u32(cell, 4 + i * stride)?);
+        }
+
+        Ok(())
+    }
+
+    /// 子键（名称, 偏移）
+    fn subkeys_of(&self, offset: u32) -> io::Result<Vec<(String, u32)>> {
+        let key = self.key(offset)?;
+        let mut expand = SubkeyExpansion {
+            limit: key.subkey_count()? as usize,
+            visited: HashSet::new(),
+            offsets: Vec::new(),
+        };
+        if expand.limit > 0 {
+            self.subkey_offsets(key.subkey_list()?, &mut expand, 0)?;
+        }
+
+        expand
+            .offsets
+            .into_iter()
+            .map(|child| Ok((self.key(child)?.name()?, child)))
+            .collect()
+    }
+
+    /// 按相对于配置单元根键的路径查找键（忽略大小写）
+    fn find_key(&self, path: &str) -> io::Result<Option<u32>> {
+        let mut offset = self.root;
+        for name in path.split('\\').filter(|s| !s.is_empty()) {
+            let name = name.to_lowercase();
+            match self.subkeys_of(offset)?.into_iter().find(|(n, _)| n.to_lowercase() == name) {
+                Some((_, child)) => offset = child,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(offset))
+    }
+
+    fn values_of(&self, offset: u32) -> io::Result<Vec<RegistryValue>> {
+        let key = self.key(offset)?;
+        let count = key.value_count()? as usize;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let list = self.cell(key.value_list()?)?;
+        (0..count).map(|i| self.value(read_u32(list, i * 4)?)).collect()
+    }
+
+    /// 解析值（vk 单元）
+    fn value(&self, offset: u32) -> io::Result<RegistryValue> {
+        let cell = self.cell(offset)?;
+        if cell.len() < 20 || &cell[..2] != b"vk" {
+            return Err(invalid(format!("不是值节点: 偏移 {:#x}", offset)));
+        }
+
+        let name_len = read_u16(cell, 2)? as usize;
+        let data_size = read_u32(cell, 4)?;
+        let data_offset = read_u32(cell, 8)?;
+        let data_type = read_u32(cell, 12)?;
+        let flags = read_u16(cell, 16)?;
+        let name = cell.get(20..20 + name_len).ok_or_else(|| invalid("值名越界"))?;
+        let name = decode_name(name, flags & VALUE_COMP_NAME != 0);
+
+        let bytes = if data_size & 0x8000_0000 != 0 {
+            // 不超过4字节的数据直接存放在偏移字段中
+            let len = (data_size & 0x7fff_ffff).min(4) as usize;
+            data_offset.to_le_bytes()[..len].to_vec()
+        } else {
+            self.value_bytes(data_offset, data_size as usize)?
+        };
+
+        Ok(RegistryValue { name, data: decode_data(data_type, &bytes) })
+    }
+
+    fn value_bytes(&self, offset: u32, size: usize) -> io::Result<Vec<u8>> {
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let cell = self.cell(offset)?;
+        if size > BIG_DATA_SEGMENT && cell.get(..2) == Some(b"db") {
+            let segments = read_u16(cell, 2)? as usize;
+            let list = self.cell(read_u32(cell, 4)?)?;
+            let mut bytes = Vec::with_capacity(size);
+            for i in 0..segments {
+                let segment = self.cell(read_u32(list, i * 4)?)?;
+                let take = (size - bytes.len()).min(BIG_DATA_SEGMENT).min(segment.len());
+                bytes.extend_from_slice(&segment[..take]);
+            }
+            if bytes.len() != size {
+                return Err(invalid(format!("大数据长度不符: 偏移 {:#x}", offset)));
+            }
+            return Ok(bytes);
+        }
+
+        cell.get(..size)
+            .map(|b| b.to_vec())
+            .ok_or_else(|| invalid(format!("值数据越界: 偏移 {:#x}", offset)))
+    }
+}
+
+/// 按值类型解码数据
+fn decode_data(data_type: u32, bytes: &[u8]) -> RegistryData {
+    let text = || {
+        let s = decode_utf16(bytes);
+        s.split('\0').next().unwrap_or("").to_string()
+    };
+
+    match data_type {
+        REG_NONE if bytes.is_empty() => RegistryData::None,
+        REG_SZ => RegistryData::String(text()),
+        REG_EXPAND_SZ => RegistryData::ExpandString(text()),
+        REG_MULTI_SZ => RegistryData::MultiString(
+            decode_utf16(bytes)
+                .split('\0')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect(),
+        ),
+        REG_DWORD if bytes.len() == 4 => {
+            RegistryData::Dword(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+        REG_DWORD_BIG_ENDIAN if bytes.len() == 4 => {
+            RegistryData::Dword(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+        REG_QWORD if bytes.len() == 8 => {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(bytes);
+            RegistryData::Qword(u64::from_le_bytes(buf))
+        }
+        _ => RegistryData::Binary(bytes.to_vec()),
+    }
+}
+
+/// 以配置单元根键为顶层的注册表树（路径不含根键名）
+impl RegistryTree for Hive {
+    fn subkeys(&self, path: &str) -> io::Result<Vec<String>> {
+        match self.find_key(path)? {
+            Some(offset) => Ok(self.subkeys_of(offset)?.into_iter().map(|(n, _)| n).collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn values(&self, path: &str) -> io::Result<Vec<RegistryValue>> {
+        match self.find_key(path)? {
+            Some(offset) => self.values_of(offset),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// 挂载点
+#[derive(Debug, Clone)]
+struct Mount {
+    /// 注册表中的位置，如 `HKEY_LOCAL_MACHINE\SOFTWARE`
+    point: String,
+    hive: Arc<Hive>,
+    /// 对应配置单元内的路径
+    subpath: String,
+}
+
+/// 多个配置单元组成的注册表树
+///
+/// 同一位置挂载多个配置单元时子键取并集，同名值以先挂载者为准。
+#[derive(Debug, Clone, Default)]
+pub struct HiveSet {
+    mounts: Vec<Mount>,
+}
+
+impl HiveSet {
+    pub fn new() -> Self {
+        HiveSet::default()
+    }
+
+    /// 将配置单元内的 `subpath` 挂载到注册表位置 `point`
+    pub fn mount(&mut self, point: &str, hive: Arc<Hive>, subpath: &str) {
+        self.mounts.push(Mount {
+            point: normalize_key(point),
+            hive,
+            subpath: subpath.trim_matches('\\').to_string(),
+        });
+    }
+
+    /// SOFTWARE：`HKLM\SOFTWARE`，其中 `Classes` 同时构成 `HKCR` 的机器部分
+    pub fn mount_software(&mut self, hive: Hive) {
+        let hive = Arc::new(hive);
+        self.mount("HKEY_LOCAL_MACHINE\\SOFTWARE", hive.clone(), "");
+        self.mount("HKEY_CLASSES_ROOT", hive, "Classes");
+    }
+
+    /// SYSTEM：`HKLM\SYSTEM`
+    pub fn mount_system(&mut self, hive: Hive) {
+        self.mount("HKEY_LOCAL_MACHINE\\SYSTEM", Arc::new(hive), "");
+    }
+
+    /// NTUSER.DAT：`HKCU`
+    pub fn mount_ntuser(&mut self, hive: Hive) {
+        self.mount("HKEY_CURRENT_USER", Arc::new(hive), "");
+    }
+
+    /// UsrClass.dat：`HKCU\Software\Classes`，并优先于机器部分构成 `HKCR`
+    pub fn mount_usrclass(&mut self, hive: Hive) {
+        let hive = Arc::new(hive);
+        self.mounts.insert(0, Mount {
+            point: "HKEY_CLASSES_ROOT".to_string(),
+            hive: hive.clone(),
+            subpath: String::new(),
+        });
+        self.mount("HKEY_CURRENT_USER\\Software\\Classes", hive, "");
+    }
+
+    /// 已挂载的位置
+    pub fn mount_points(&self) -> impl Iterator<Item = &str> {
+        self.mounts.iter().map(|m| m.point.as_str())
+    }
+}
+
+/// 统一根键写法并去掉首尾分隔符
+fn normalize_key(path: &str) -> String {
+    let path = path.trim_matches('\\');
+    match path.split_once('\\') {
+        Some((root, rest)) => format!("{}\\{}", canonical_root(root), rest),
+        None => canonical_root(path).to_string(),
+    }
+}
+
+/// `path` 位于 `prefix` 或其下级时，返回剩余部分（忽略大小写）
+fn strip_key_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    if prefix.is_empty() {
+        return Some(path);
+    }
+    let head = path.get(..prefix.len())?;
+    if !head.eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+    match &path[prefix.len()..] {
+        "" => Some(""),
+        rest => rest.strip_prefix('\\'),
+    }
+}
+
+fn join_subpath(subpath: &str, rest: &str) -> String {
+    match (subpath.is_empty(), rest.is_empty()) {
+        (true, _) => rest.to_string(),
+        (_, true) => subpath.to_string(),
+        _ => format!("{}\\{}", subpath, rest),
+    }
+}
+
+impl RegistryTree for HiveSet {
+    fn subkeys(&self, path: &str) -> io::Result<Vec<String>> {
+        let path = normalize_key(path);
+        let mut names = Vec::new();
+        let mut seen = HashSet::new();
+        let mut add = |name: String| {
+            if seen.insert(name.to_lowercase()) {
+                names.push(name);
+            }
+        };
+
+        for mount in &self.mounts {
+            if let Some(rest) = strip_key_prefix(&path, &mount.point) {
+                for name in mount.hive.subkeys(&join_subpath(&mount.subpath, rest))? {
+                    add(name);
+                }
+            } else if let Some(rest) = strip_key_prefix(&mount.point, &path) {
+                // 挂载点位于更深处，补出中间一级
+                if let Some(next) = rest.split('\\').next() {
+                    add(next.to_string());
+                }
+            }
+        }
+
+        Ok(names)
+    }
+
+    fn values(&self, path: &str) -> io::Result<Vec<RegistryValue>> {
+        let path = normalize_key(path);
+        let mut values: Vec<RegistryValue> = Vec::new();
+
+        for mount in &self.mounts {
+            if let Some(rest) = strip_key_prefix(&path, &mount.point) {
+                for value in mount.hive.values(&join_subpath(&mount.subpath, rest))? {
+                    let name = value.name.to_lowercase();
+                    if !values.iter().any(|v| v.name.to_lowercase() == name) {
+                        values.push(value);
+                    }
+                }
+            }
+        }
+
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 手工构造的最小配置单元：基本块、一个 hbin，单元依次追加
+    struct Builder {
+        data: Vec<u8>,
+    }
+
+    impl Builder {
+        fn new() -> Self {
+            let mut data = vec![0u8; BASE_BLOCK_SIZE];
+            data[..4].copy_from_slice(b"regf");
+            data.extend_from_slice(b"hbin");
+            data.resize(BASE_BLOCK_SIZE + 0x20, 0);
+            Builder { data }
+        }
+
+        /// 追加一个已分配的单元，返回其相对于 hbin 的偏移
+        fn cell(&mut self, body: &[u8]) -> u32 {
+            let offset = (self.data.len() - BASE_BLOCK_SIZE) as u32;
+            let size = (body.len() + 4).next_multiple_of(8);
+            self.data.extend_from_slice(&(-(size as i32)).to_le_bytes());
+            self.data.extend_from_slice(body);
+            self.data.resize(BASE_BLOCK_SIZE + offset as usize + size, 0);
+            offset
+        }
+
+        fn key(&mut self, name: &str, subkeys: u32, subkey_list: u32, values: &[u32]) -> u32 {
+            let value_list = match values {
+                [] => u32::MAX,
+                _ => self.cell(&values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<_>>()),
+            };
+            let mut body = vec![0u8; 76];
+            body[..2].copy_from_slice(b"nk");
+            body[2..4].copy_from_slice(&KEY_COMP_NAME.to_le_bytes());
+            body[20..24].copy_from_slice(&subkeys.to_le_bytes());
+            body[28..32].copy_from_slice(&subkey_list.to_le_bytes());
+            body[36..40].copy_from_slice(&(values.len() as u32).to_le_bytes());
+            body[40..44].copy_from_slice(&value_list.to_le_bytes());
+            body[72..74].copy_from_slice(&(name.len() as u16).to_le_bytes());
+            body.extend_from_slice(name.as_bytes());
+            self.cell(&body)
+        }
+
+        /// lf/lh 每项为偏移与名称提示，li/ri 每项只有偏移
+        fn list(&mut self, kind: &[u8; 2], offsets: &[u32]) -> u32 {
+            let mut body = kind.to_vec();
+            body.extend_from_slice(&(offsets.len() as u16).to_le_bytes());
+            for offset in offsets {
+                body.extend_from_slice(&offset.to_le_bytes());
+                if kind == b"lf" || kind == b"lh" {
+                    body.extend_from_slice(&[0; 4]);
+                }
+            }
+            self.cell(&body)
+        }
+
+        fn value(&mut self, name: &str, data_type: u32, data: &[u8]) -> u32 {
+            let (size, offset) = if data.len() <= 4 {
+                let mut inline = [0u8; 4];
+                inline[..data.len()].copy_from_slice(data);
+                (data.len() as u32 | 0x8000_0000, u32::from_le_bytes(inline))
+            } else {
+                (data.len() as u32, self.cell(data))
+            };
+            let mut body = vec![0u8; 20];
+            body[..2].copy_from_slice(b"vk");
+            body[2..4].copy_from_slice(&(name.len() as u16).to_le_bytes());
+            body[4..8].copy_from_slice(&size.to_le_bytes());
+            body[8..12].copy_from_slice(&offset.to_le_bytes());
+            body[12..16].copy_from_slice(&data_type.to_le_bytes());
+            body[16..18].copy_from_slice(&VALUE_COMP_NAME.to_le_bytes());
+            body.extend_from_slice(name.as_bytes());
+            self.cell(&body)
+        }
+
+        fn finish(mut self, root: u32) -> Vec<u8> {
+            self.data[0x24..0x28].copy_from_slice(&root.to_le_bytes());
+            self.data
+        }
+    }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().chain([0]).flat_map(|u| u.to_le_bytes()).collect()
+    }
+
+    /// 根键下的子键经由 `list` 给出的索引挂接
+    fn hive_with_list(declared: u32, list: impl FnOnce(&mut Builder, &[u32]) -> u32) -> Hive {
+        let mut b = Builder::new();
+        let children = [b.key("Alpha", 0, u32::MAX, &[]), b.key("Beta", 0, u32::MAX, &[])];
+        let list = list(&mut b, &children);
+        let root = b.key("ROOT", declared, list, &[]);
+        Hive::from_bytes(b.finish(root)).unwrap()
+    }
+
+    #[test]
+    fn reads_subkeys_and_values() {
+        let mut b = Builder::new();
+        let dword = b.value("Flags", REG_DWORD, &7u32.to_le_bytes());
+        let text = b.value("", REG_SZ, &utf16("C:\\Program Files\\App"));
+        let app = b.key("App", 0, u32::MAX, &[dword, text]);
+        let other = b.key("Other", 0, u32::MAX, &[]);
+        let lf = b.list(b"lf", &[app]);
+        let li = b.list(b"li", &[other]);
+        let ri = b.list(b"ri", &[lf, li]);
+        let software = b.key("Software", 2, ri, &[]);
+        let root_list = b.list(b"lh", &[software]);
+        let root = b.key("ROOT", 1, root_list, &[]);
+        let hive = Hive::from_bytes(b.finish(root)).unwrap();
+
+        assert_eq!(hive.subkeys("").unwrap(), ["Software"]);
+        assert_eq!(hive.subkeys("software").unwrap(), ["App", "Other"]);
+        assert!(hive.subkeys("Software\\Missing").unwrap().is_empty());
+
+        let values = hive.values("SOFTWARE\\app").unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].name, "Flags");
+        assert_eq!(values[0].data, RegistryData::Dword(7));
+        assert_eq!(values[1].name, "");
+        assert_eq!(values[1].data, RegistryData::String("C:\\Program Files\\App".to_string()));
+    }
+
+    #[test]
+    fn rejects_non_regf_data() {
+        assert!(Hive::from_bytes(vec![0; BASE_BLOCK_SIZE]).is_err());
+        assert!(Hive::from_bytes(b"regf".to_vec()).is_err());
+    }
+
+    #[test]
+    fn rejects_root_that_is_not_a_key() {
+        let mut b = Builder::new();
+        let list = b.list(b"lf", &[]);
+        assert!(Hive::from_bytes(b.finish(list)).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_cells() {
+        // 单元长度超出文件末尾
+        let mut b = Builder::new();
+        let root = b.key("ROOT", 0, u32::MAX, &[]);
+        let mut data = b.finish(root);
+        data.truncate(data.len() - 8);
+        assert!(Hive::from_bytes(data).is_err());
+
+        // 键节点短于固定部分
+        let mut b = Builder::new();
+        let root = b.cell(b"nk\0\0");
+        assert!(Hive::from_bytes(b.finish(root)).is_err());
+
+        // 键名长度超出单元
+        let mut b = Builder::new();
+        let root = b.key("ROOT", 0, u32::MAX, &[]);
+        let mut data = b.finish(root);
+        let name_len = BASE_BLOCK_SIZE + root as usize + 4 + 72;
+        data[name_len..name_len + 2].copy_from_slice(&0x100u16.to_le_bytes());
+        let hive = Hive::from_bytes(data).unwrap();
+        assert!(hive.key(root).unwrap().name().is_err());
+
+        // 索引项数多于单元中的实际项
+        let hive = hive_with_list(2, |b, children| {
+            let mut body = b"lf".to_vec();
+            body.extend_from_slice(&2u16.to_le_bytes());
+            body.extend_from_slice(&children[0].to_le_bytes());
+            b.cell(&body)
+        });
+        assert!(hive.subkeys("").is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_offsets() {
+        assert!(Hive::from_bytes(Builder::new().finish(0x7fff_0000)).is_err());
+
+        let mut b = Builder::new();
+        let root = b.key("ROOT", 1, u32::MAX, &[]);
+        let hive = Hive::from_bytes(b.finish(root)).unwrap();
+        assert!(hive.subkeys("").is_err());
+
+        let hive = hive_with_list(1, |b, _| b.list(b"lf", &[0x10_0000]));
+        assert!(hive.subkeys("").is_err());
+
+        let mut b = Builder::new();
+        // REG_BINARY
+        let value = b.value("Big", 3, &[0; 64]);
+        let root = b.key("ROOT", 0, u32::MAX, &[value]);
+        let mut data = b.finish(root);
+        let data_offset = BASE_BLOCK_SIZE + value as usize + 4 + 8;
+        data[data_offset..data_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Hive::from_bytes(data).unwrap().values("").is_err());
+    }
+
+    #[test]
+    fn rejects_ri_cycles_and_repeated_lists() {
+        let hive = hive_with_list(2, |b, _| {
+            // ri 引用自身：先分配再回填
+            let ri = b.list(b"ri", &[0]);
+            let at = BASE_BLOCK_SIZE + ri as usize + 8;
+            b.data[at..at + 4].copy_from_slice(&ri.to_le_bytes());
+            ri
+        });
+        assert!(hive.subkeys("").is_err());
+
+        let hive = hive_with_list(2, |b, children| {
+            let lf = b.list(b"lf", &children[..1]);
+            b.list(b"ri", &[lf, lf])
+        });
+        assert!(hive.subkeys("").is_err());
+
+        let hive = hive_with_list(2, |b, children| {
+            let lf = b.list(b"lf", &children[..1]);
+            let li = b.list(b"li", &children[1..]);
+            let inner = b.list(b"ri", &[lf, li]);
+            b.list(b"ri", &[inner])
+        });
+        assert_eq!(hive.subkeys("").unwrap(), ["Alpha", "Beta"]);
+    }
+
+    #[test]
+    fn caps_subkeys_at_declared_count() {
+        let hive = hive_with_list(1, |b, children| b.list(b"lf", children));
+        assert!(hive.subkeys("").is_err());
+
+        let hive = hive_with_list(2, |b, children| b.list(b"lf", children));
+        assert_eq!(hive.subkeys("").unwrap(), ["Alpha", "Beta"]);
+    }
+
+    /// 只有一条键路径的配置单元，`values` 挂在最深一级
+    fn hive_with_path(path: &[&str], values: &[(&str, u32, &[u8])]) -> Hive {
+        let mut b = Builder::new();
+        let values: Vec<u32> = values.iter().map(|(name, data_type, data)| b.value(name, *data_type, data)).collect();
+        let mut key = b.key(path[path.len() - 1], 0, u32::MAX, &values);
+        for name in path[..path.len() - 1].iter().rev().chain(["ROOT"].iter()) {
+            let list = b.list(b"lf", &[key]);
+            key = b.key(name, 1, list, &[]);
+        }
+        Hive::from_bytes(b.finish(key)).unwrap()
+    }
+
+    #[test]
+    fn matches_rules_against_mounted_hives() {
+        use crate::registry::RegistryMatcher;
+        use crate::rule::{RegistryAction, RegistryRule};
+
+        let mut hives = HiveSet::new();
+        hives.mount_software(hive_with_path(
+            &["Classes", "*", "shellex", "ContextMenuHandlers", "SoftMgrOEMExt"],
+            &[("", REG_SZ, &utf16("{A1B2}"))],
+        ));
+        hives.mount_ntuser(hive_with_path(&["Software", "SoftMgr"], &[("Flags", REG_DWORD, &1u32.to_le_bytes())]));
+
+        let find = |path: &str, key: &str, action| {
+            let rule = RegistryRule { path: path.to_string(), key: key.to_string(), value: None, value_data: None, action };
+            let targets = RegistryMatcher::new(&rule).unwrap().find(&hives).unwrap();
+            targets.iter().map(ToString::to_string).collect::<Vec<_>>()
+        };
+
+        // SOFTWARE 中的 Classes 同时出现在 HKCR 与 HKLM\SOFTWARE\Classes 下
+        assert_eq!(
+            find(r"HKCR\*\shellex\ContextMenuHandlers", "SoftMgr*", RegistryAction::DeleteKey),
+            [r"HKEY_CLASSES_ROOT\*\shellex\ContextMenuHandlers\SoftMgrOEMExt"]
+        );
+        assert_eq!(
+            find(r"HKLM\SOFTWARE\Classes\*\shellex\ContextMenuHandlers", "SoftMgr*", RegistryAction::DeleteKey),
+            [r"HKEY_LOCAL_MACHINE\SOFTWARE\Classes\*\shellex\ContextMenuHandlers\SoftMgrOEMExt"]
+        );
+        assert_eq!(
+            find(r"HKCU\Software", "softmgr", RegistryAction::DeleteValue),
+            [r"HKEY_CURRENT_USER\Software\SoftMgr -> Flags"]
+        );
+        // 未挂载的 SYSTEM 视为空
+        assert!(find(r"HKLM\SYSTEM", "*", RegistryAction::DeleteKey).is_empty());
+    }
+}
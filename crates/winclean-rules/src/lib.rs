@@ -0,0 +1,21 @@
+//! WinClean Rules
+//! 规则模型、校验与匹配等可供客户端复用的库代码
+//!
+//! 打包工具 `winclean-rules-packer` 构建在本库之上；客户端与其他工具读取 `rules.bin`
+//! 时使用 [`package`] 中的模型与读取器，不必复制结构定义。
+
+pub mod diagnostic;
+pub mod feed;
+pub mod filesystem;
+pub mod fixture;
+pub mod hive;
+pub mod pack;
+pub mod package;
+pub mod pattern;
+mod proto;
+pub mod registry;
+pub mod rule;
+pub mod sample;
+pub mod signing;
+pub mod source;
+pub mod time;
@@ -0,0 +1,103 @@
+//! 打包流程
+//! 加载并校验规则目录、确定创建时间与内容序号、写出规则包，命令行工具与 Python 模块共用
+
+use crate::diagnostic::{Diagnostic, ValidationFailed};
+use crate::package::{self, Compression, Digest, Encoding, PackageHeader, SerializedRule};
+use crate::signing::SigningKey;
+use crate::source::{load_rules, RuleFile};
+use crate::time;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 打包选项
+#[derive(Debug, Clone)]
+pub struct PackOptions {
+    pub compression: Compression,
+    pub encoding: Encoding,
+    /// 内容序号，默认取创建日期 `YYYYMMDD`
+    pub serial: Option<u64>,
+    /// 创建时间，默认由 [`build_time`] 决定
+    pub created_at: Option<u64>,
+    /// 有效天数，默认永不过期
+    pub expires_in_days: Option<u64>,
+    /// 可复现构建
+    pub reproducible: bool,
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        PackOptions {
+            compression: Compression::Zstd,
+            encoding: Encoding::Bincode,
+            serial: None,
+            created_at: None,
+            expires_in_days: None,
+            reproducible: false,
+        }
+    }
+}
+
+/// 打包结果
+#[derive(Debug)]
+pub struct Packed {
+    /// 按路径排序的规则文件
+    pub files: Vec<RuleFile>,
+    /// 校验警告
+    pub warnings: Vec<Diagnostic>,
+    pub header: PackageHeader,
+    /// 编码后、压缩前的规则总大小
+    pub original_size: u64,
+    /// 规则包内容
+    pub data: Vec<u8>,
+    pub header_digest: Digest,
+}
+
+/// 校验并打包规则目录
+///
+/// 有校验错误时返回 [`ValidationFailed`]。规则文件按路径排序，创建时间与内容序号固定时
+/// 相同输入总是得到相同的规则包。
+pub fn pack(input: &Path, options: &PackOptions, signer: Option<&SigningKey>) -> Result<Packed> {
+    let (files, diagnostics) = load_rules(input)?;
+    let warnings = ValidationFailed::check(diagnostics)?;
+    let rules: Vec<SerializedRule> = files.iter().map(RuleFile::to_serialized).collect();
+
+    // 创建包头（分类按名称排序，目录与摘要由写出时生成）
+    let created_at = match options.created_at {
+        Some(created_at) => created_at,
+        None => build_time(&files, options.reproducible)?,
+    };
+    let serial = match options.serial {
+        Some(serial) => serial,
+        None => package::date_serial(created_at)?,
+    };
+    let expires_at = options.expires_in_days
+        .map(|days| time::add_days(created_at, days).with_context(|| format!("有效天数 {} 过大", days)))
+        .transpose()?;
+    let header = PackageHeader::new(&rules, serial, created_at, expires_at);
+
+    // 序列化并压缩
+    let original_size = rules.iter()
+        .map(|rule| options.encoding.encoded_size(rule))
+        .sum::<std::io::Result<u64>>()?;
+    let (data, header_digest) = package::write_package(&header, &rules, options.compression, options.encoding, signer)?;
+
+    Ok(Packed { files, warnings, header, original_size, data, header_digest })
+}
+
+/// 规则包的创建时间
+///
+/// 优先使用 `SOURCE_DATE_EPOCH`；可复现构建时取规则中最新的 update 日期，否则取当前时间。
+pub fn build_time(files: &[RuleFile], reproducible: bool) -> Result<u64> {
+    if let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH") {
+        return epoch.trim().parse()
+            .with_context(|| format!("SOURCE_DATE_EPOCH 不是合法的时间戳: {}", epoch));
+    }
+    if reproducible {
+        return files.iter()
+            .map(|f| time::parse_time(&format!("{}T00:00:00Z", f.rule.update))
+                .with_context(|| format!("update 日期无效: {}", f.path.display())))
+            .try_fold(0, |latest, time| Ok(latest.max(time?)));
+    }
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
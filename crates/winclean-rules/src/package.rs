@@ -0,0 +1,1534 @@
+//! 规则包格式
+//! `rules.bin` 的容器格式与规则包数据模型
+//!
+//! 文件以不压缩的固定前缀与包头开头，其后为各条规则独立压缩的数据帧，
+//! 因此只需读取开头几 KB 就能列出全部规则，并可按目录单独读取某条规则：
+//!
+//! ```text
+//! 偏移  长度  内容
+//! 0     8     魔数 `WCRULES\0`
+//! 8     2     格式版本（小端）
+//! 10    1     压缩标记：0 = none，1 = zstd
+//! 11    1     编码标记：0 = bincode，1 = protobuf
+//! 12    4     包头长度 N（小端）
+//! 16    32    包头的 BLAKE3 摘要
+//! 48    1     签名算法：0 = 未签名，1 = Ed25519
+//! 49    64    对前 48 字节的签名（未签名时为 0）
+//! 113   N     包头（按编码标记编码的 PackageHeader，含目录）
+//! 113+N ...   规则数据：每条 SerializedRule 编码后单独压缩，依次排列
+//! ```
+//!
+//! 目录中每条规则的 `offset`/`length` 指向其数据帧在规则数据区（自 113+N 起）中的字节范围。
+//!
+//! bincode 的布局取决于 Rust 结构体的字段顺序，只适合 Rust 客户端；其他语言的客户端应读取
+//! protobuf 编码的规则包，schema 为 `proto/winclean_rules.proto`。
+//! 按 id 或分类读取规则使用 [`PackageReader`]。
+//!
+//! 完整性由 BLAKE3 摘要保证：包头摘要在读取包头时校验；包头中记录规则数据区的长度与摘要，
+//! 目录中记录每个数据帧的摘要，读取规则时逐帧校验。[`verify_package`] 可找出损坏的规则。
+//!
+//! 读取时按 [`Limits`] 限制包头大小、规则数量、解压后大小以及字符串与列表长度，
+//! 构造的小文件无法耗尽内存。
+//!
+//! 签名覆盖包头摘要，从而间接覆盖包头、规则数据与每条规则。客户端应使用
+//! [`read_package_verified`] 或 [`PackageReader::open_verified`] 按 [`TrustPolicy`] 读取，
+//! 拒绝未签名、签名无效、内容序号早于上次接受的（回滚）或已过期（冻结）的规则包。
+
+use crate::proto;
+use crate::rule::{MatchSection, RegistryRule, Rule};
+use crate::time::{self, format_time};
+use bincode::Options;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey, SIGNATURE_LENGTH};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 文件魔数
+pub const MAGIC: &[u8; 8] = b"WCRULES\0";
+
+/// 当前格式版本
+pub const FORMAT_VERSION: u16 = 8;
+
+/// 签名覆盖的前缀长度（魔数、版本、压缩标记、编码标记、包头长度与包头摘要）
+pub const SIGNED_LEN: usize = MAGIC.len() + 2 + 1 + 1 + 4 + DIGEST_LEN;
+
+/// 固定前缀长度（签名覆盖的部分加签名算法与签名）
+pub const PREFIX_LEN: usize = SIGNED_LEN + 1 + SIGNATURE_LENGTH;
+
+/// 签名算法标记
+const SIGNATURE_NONE: u8 = 0;
+const SIGNATURE_ED25519: u8 = 1;
+
+/// 摘要长度
+pub const DIGEST_LEN: usize = 32;
+
+/// BLAKE3 摘要
+pub type Digest = [u8; DIGEST_LEN];
+
+/// 包头
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackageHeader {
+    /// 格式版本，与固定前缀中的版本一致
+    pub version: u32,
+    /// 内容序号（如发布日期 `YYYYMMDD`），随每次发布单调递增
+    pub serial: u64,
+    pub created_at: u64,
+    /// 过期时间（Unix 时间戳，秒），此后规则包不再被接受
+    pub expires_at: Option<u64>,
+    pub rule_count: usize,
+    pub compression: String,
+    pub encoding: String,
+    pub categories: Vec<String>,
+    /// 规则数据区长度
+    pub payload_len: u64,
+    /// 规则数据区的摘要
+    pub payload_digest: Digest,
+    /// 目录，与规则数据中的顺序一致
+    pub entries: Vec<TocEntry>,
+}
+
+impl PackageHeader {
+    /// 为一组规则创建包头，分类按名称排序
+    ///
+    /// 压缩方式、编码、目录与摘要由 [`write_package`] 在写出时生成。
+    pub fn new(rules: &[SerializedRule], serial: u64, created_at: u64, expires_at: Option<u64>) -> Self {
+        let categories: BTreeSet<&str> = rules.iter().map(|r| r.metadata.category.as_str()).collect();
+        PackageHeader {
+            version: FORMAT_VERSION as u32,
+            serial,
+            created_at,
+            expires_at,
+            rule_count: rules.len(),
+            compression: String::new(),
+            encoding: String::new(),
+            categories: categories.into_iter().map(String::from).collect(),
+            payload_len: 0,
+            payload_digest: [0; DIGEST_LEN],
+            entries: Vec::new(),
+        }
+    }
+}
+
+/// 当天日期形式的内容序号（`YYYYMMDD`，UTC），年份超过 9999 时返回错误
+pub fn date_serial(timestamp: u64) -> anyhow::Result<u64> {
+    let (year, month, day) = time::civil_date(timestamp);
+    if year > 9999 {
+        anyhow::bail!("时间 {} 超出 YYYYMMDD 内容序号的范围", timestamp);
+    }
+    Ok(year as u64 * 10000 + month as u64 * 100 + day as u64)
+}
+
+/// 目录项
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TocEntry {
+    pub id: String,
+    pub name: String,
+    pub risk: String,
+    pub category: String,
+    /// 数据帧在规则数据区中的起始位置
+    pub offset: u64,
+    pub length: u64,
+    /// 数据帧的摘要
+    pub digest: Digest,
+}
+
+/// 完整的规则包
+#[derive(Debug, Clone)]
+pub struct RulesPackage {
+    pub header: PackageHeader,
+    pub rules: Vec<SerializedRule>,
+}
+
+/// 规则元数据
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RuleMetadata {
+    pub id: String,
+    pub name: String,
+    pub risk: String,
+    pub systeminfo: Vec<String>,
+    pub update: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    pub category: String,
+    pub filename: String,
+}
+
+/// 序列化后的规则
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SerializedRule {
+    pub metadata: RuleMetadata,
+    pub yaml_content: String,
+    pub paths: Vec<String>,
+    pub registry_entries: Vec<RegistryEntry>,
+}
+
+/// 注册表条目
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RegistryEntry {
+    pub path: String,
+    pub key: String,
+    pub value: Option<String>,
+    pub value_data: Option<String>,
+    pub action: String,
+}
+
+impl RuleMetadata {
+    /// 解包时的相对路径 `分类/文件名`
+    ///
+    /// 分类与文件名都必须是单个合法的 Windows 文件名，文件名必须以 `.yaml` 或 `.yml` 结尾，
+    /// 否则返回 [`PackageError::UnsafePath`]，避免构造的规则包写到输出目录之外。
+    pub fn relative_path(&self) -> Result<PathBuf, PackageError> {
+        for (field, value) in [("category", &self.category), ("filename", &self.filename)] {
+            if let Some(reason) = unsafe_component(value) {
+                return Err(PackageError::UnsafePath {
+                    id: self.id.clone(),
+                    field,
+                    value: value.clone(),
+                    reason,
+                });
+            }
+        }
+
+        let lower = self.filename.to_ascii_lowercase();
+        if !lower.ends_with(".yaml") && !lower.ends_with(".yml") {
+            return Err(PackageError::UnsafePath {
+                id: self.id.clone(),
+                field: "filename",
+                value: self.filename.clone(),
+                reason: "不是 .yaml 文件",
+            });
+        }
+
+        Ok(Path::new(&self.category).join(&self.filename))
+    }
+}
+
+/// 单个路径组成部分不安全的原因
+fn unsafe_component(name: &str) -> Option<&'static str> {
+    const RESERVED: &[&str] = &["CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$"];
+
+    if name.is_empty() {
+        return Some("为空");
+    }
+    if name == "." || name == ".." {
+        return Some("指向当前或上级目录");
+    }
+    if name.contains(['/', '\\']) {
+        return Some("包含路径分隔符");
+    }
+    if name.contains(':') {
+        return Some("包含盘符或冒号");
+    }
+    if name.chars().any(|c| c.is_control() || matches!(c, '<' | '>' | '"' | '|' | '?' | '*')) {
+        return Some("包含控制字符或 Windows 不允许的字符");
+    }
+    if name.ends_with(['.', ' ']) {
+        return Some("以点或空格结尾");
+    }
+
+    let stem = name.split('.').next().unwrap_or(name).trim_end().to_ascii_uppercase();
+    let numbered = |prefix: &str| {
+        stem.strip_prefix(prefix)
+            .is_some_and(|n| n.len() == 1 && n.chars().all(|c| c.is_ascii_digit() && c != '0'))
+    };
+    if RESERVED.contains(&stem.as_str()) || numbered("COM") || numbered("LPT") {
+        return Some("是 Windows 保留名");
+    }
+
+    None
+}
+
+impl SerializedRule {
+    /// 由规则模型构建
+    pub fn new(rule: &Rule, category: &str, filename: &str, yaml_content: &str) -> Self {
+        SerializedRule {
+            metadata: RuleMetadata {
+                id: rule.id.clone(),
+                name: rule.name.clone(),
+                risk: rule.risk.to_string(),
+                systeminfo: rule.systeminfo.clone(),
+                update: rule.update.clone(),
+                author: rule.author.clone(),
+                description: rule.description.clone(),
+                category: category.to_string(),
+                filename: filename.to_string(),
+            },
+            yaml_content: yaml_content.to_string(),
+            paths: rule.matches.path.clone(),
+            registry_entries: rule.matches.registry.iter().map(RegistryEntry::from).collect(),
+        }
+    }
+
+    /// 还原为规则模型
+    pub fn to_rule(&self) -> anyhow::Result<Rule> {
+        let registry = self
+            .registry_entries
+            .iter()
+            .map(RegistryEntry::to_rule)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Rule {
+            id: self.metadata.id.clone(),
+            name: self.metadata.name.clone(),
+            risk: self.metadata.risk.parse()?,
+            systeminfo: self.metadata.systeminfo.clone(),
+            update: self.metadata.update.clone(),
+            author: self.metadata.author.clone(),
+            description: self.metadata.description.clone(),
+            matches: MatchSection {
+                path: self.paths.clone(),
+                registry,
+            },
+            // 测试样例只保存在YAML原文中
+            tests: serde_yaml::from_str::<Rule>(&self.yaml_content)
+                .map(|rule| rule.tests)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl RegistryEntry {
+    /// 还原为注册表规则模型
+    pub fn to_rule(&self) -> anyhow::Result<RegistryRule> {
+        Ok(RegistryRule {
+            path: self.path.clone(),
+            key: self.key.clone(),
+            value: self.value.clone(),
+            value_data: self.value_data.clone(),
+            action: self.action.parse()?,
+        })
+    }
+}
+
+impl From<&RegistryRule> for RegistryEntry {
+    fn from(rule: &RegistryRule) -> Self {
+        RegistryEntry {
+            path: rule.path.clone(),
+            key: rule.key.clone(),
+            value: rule.value.clone(),
+            value_data: rule.value_data.clone(),
+            action: rule.action.to_string(),
+        }
+    }
+}
+
+/// 压缩算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Zstd,
+}
+
+impl Compression {
+    /// 写入文件的压缩标记
+    pub fn tag(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Zstd => 1,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Compression::None),
+            1 => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// 压缩数据
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Zstd => {
+                let mut encoder = zstd::stream::Encoder::new(Vec::new(), 0)?;
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    /// 解压数据，解压后超过 `limit` 字节时返回 `None`
+    pub fn decompress(self, data: &[u8], limit: u64) -> io::Result<Option<Vec<u8>>> {
+        let mut decompressed = Vec::new();
+        match self {
+            Compression::None => data.take(limit + 1).read_to_end(&mut decompressed)?,
+            Compression::Zstd => zstd::stream::Decoder::new(data)?.take(limit + 1).read_to_end(&mut decompressed)?,
+        };
+        Ok((decompressed.len() as u64 <= limit).then_some(decompressed))
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            "zstd" => Ok(Compression::Zstd),
+            other => anyhow::bail!("不支持的压缩算法: {}", other),
+        }
+    }
+}
+
+/// 包头与规则数据的编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// bincode，布局取决于 Rust 结构体
+    Bincode,
+    /// Protobuf，schema 为 `proto/winclean_rules.proto`
+    Protobuf,
+}
+
+impl Encoding {
+    /// 写入文件的编码标记
+    pub fn tag(self) -> u8 {
+        match self {
+            Encoding::Bincode => 0,
+            Encoding::Protobuf => 1,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Encoding::Bincode),
+            1 => Some(Encoding::Protobuf),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Encoding::Bincode => "bincode",
+            Encoding::Protobuf => "protobuf",
+        }
+    }
+
+    /// 单条规则编码后（压缩前）的字节数
+    pub fn encoded_size(self, rule: &SerializedRule) -> io::Result<u64> {
+        match self {
+            Encoding::Bincode => bincode::serialized_size(rule).map_err(io::Error::other),
+            Encoding::Protobuf => Ok(prost::Message::encoded_len(&proto::ProtoRule::from(rule)) as u64),
+        }
+    }
+
+    fn encode_header(self, header: &PackageHeader) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Bincode => bincode::serialize(header).map_err(io::Error::other),
+            Encoding::Protobuf => Ok(prost::Message::encode_to_vec(&proto::ProtoHeader::from(header))),
+        }
+    }
+
+    fn encode_rule(self, rule: &SerializedRule) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Bincode => bincode::serialize(rule).map_err(io::Error::other),
+            Encoding::Protobuf => Ok(prost::Message::encode_to_vec(&proto::ProtoRule::from(rule))),
+        }
+    }
+
+    fn decode_header(self, data: &[u8], limits: &Limits) -> Result<PackageHeader, PackageError> {
+        match self {
+            Encoding::Bincode => Limits::decode(data, limits.max_header_size)
+                .map_err(|e| PackageError::InvalidHeader(e.to_string())),
+            Encoding::Protobuf => proto::decode_header(data, limits),
+        }
+    }
+
+    fn decode_rule(self, id: &str, data: &[u8], limits: &Limits) -> Result<SerializedRule, PackageError> {
+        match self {
+            Encoding::Bincode => Limits::decode(data, limits.max_rule_size)
+                .map_err(|e| PackageError::InvalidRule { id: id.to_string(), message: e.to_string() }),
+            Encoding::Protobuf => proto::decode_rule(id, data, limits),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bincode" => Ok(Encoding::Bincode),
+            "protobuf" => Ok(Encoding::Protobuf),
+            other => anyhow::bail!("不支持的编码: {}", other),
+        }
+    }
+}
+
+/// 规则包格式错误
+#[derive(Debug)]
+pub enum PackageError {
+    /// 魔数不符
+    NotPackage,
+    /// 魔数正确但文件不完整
+    Truncated,
+    /// 格式版本不受支持
+    UnsupportedVersion(u16),
+    /// 未知的压缩标记
+    UnknownCompression(u8),
+    /// 未知的编码标记
+    UnknownEncoding(u8),
+    /// 读取失败
+    Io(io::Error),
+    /// 包头无法解析
+    InvalidHeader(String),
+    /// 规则数据无法解析
+    InvalidRule { id: String, message: String },
+    /// 包头摘要不符
+    HeaderDigestMismatch,
+    /// 规则数据区摘要不符
+    PayloadDigestMismatch,
+    /// 规则数据帧摘要不符
+    RuleDigestMismatch { id: String },
+    /// 要求签名但规则包未签名
+    Unsigned,
+    /// 签名无效（规则包被篡改或公钥不匹配）
+    BadSignature,
+    /// 超出读取限制
+    LimitExceeded { what: String, limit: u64 },
+    /// 规则的分类或文件名不能安全地作为路径
+    UnsafePath { id: String, field: &'static str, value: String, reason: &'static str },
+    /// 内容序号早于上次接受的规则包
+    Rollback { serial: u64, last_serial: u64 },
+    /// 规则包已过期
+    Expired { expires_at: u64 },
+}
+
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageError::NotPackage => f.write_str("不是 WinClean 规则包"),
+            PackageError::Truncated => f.write_str("规则包不完整"),
+            PackageError::UnsupportedVersion(version) => {
+                write!(f, "不支持的规则包版本: {}（当前支持版本 {}）", version, FORMAT_VERSION)
+            }
+            PackageError::UnknownCompression(tag) => write!(f, "未知的压缩标记: {}", tag),
+            PackageError::UnknownEncoding(tag) => write!(f, "未知的编码标记: {}", tag),
+            PackageError::Io(e) => write!(f, "读取规则包失败: {}", e),
+            PackageError::InvalidHeader(message) => write!(f, "规则包头格式错误: {}", message),
+            PackageError::HeaderDigestMismatch => f.write_str("规则包头已损坏: 摘要不符"),
+            PackageError::PayloadDigestMismatch => f.write_str("规则数据已损坏: 摘要不符"),
+            PackageError::RuleDigestMismatch { id } => write!(f, "规则 {} 已损坏: 摘要不符", id),
+            PackageError::Unsigned => f.write_str("规则包未签名"),
+            PackageError::BadSignature => f.write_str("规则包签名无效: 内容已被篡改或公钥不匹配"),
+            PackageError::LimitExceeded { what, limit } => {
+                write!(f, "规则包超出读取限制: {}超过上限 {}", what, limit)
+            }
+            PackageError::UnsafePath { id, field, value, reason } => {
+                write!(f, "规则 {} 的 {} `{}` 不安全: {}", id, field, value, reason)
+            }
+            PackageError::Rollback { serial, last_serial } => {
+                write!(f, "规则包内容序号 {} 早于上次接受的 {}，拒绝回滚", serial, last_serial)
+            }
+            PackageError::Expired { expires_at } => {
+                write!(f, "规则包已于 {} 过期", format_time(*expires_at))
+            }
+            PackageError::InvalidRule { id, message } => {
+                write!(f, "规则 {} 的数据格式错误: {}", id, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PackageError {}
+
+impl From<io::Error> for PackageError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            PackageError::Truncated
+        } else {
+            PackageError::Io(e)
+        }
+    }
+}
+
+/// 写出规则包
+///
+/// 包头中的 `rule_count` 与目录按 `rules` 重新生成，其余字段沿用 `header`。
+/// 指定 `signer` 时以 Ed25519 签名。返回规则包内容与包头摘要。
+pub fn write_package(
+    header: &PackageHeader,
+    rules: &[SerializedRule],
+    compression: Compression,
+    encoding: Encoding,
+    signer: Option<&SigningKey>,
+) -> io::Result<(Vec<u8>, Digest)> {
+    let mut payload = Vec::new();
+    let mut entries = Vec::with_capacity(rules.len());
+    for rule in rules {
+        let encoded = encoding.encode_rule(rule)?;
+        let frame = compression.compress(&encoded)?;
+        entries.push(TocEntry {
+            id: rule.metadata.id.clone(),
+            name: rule.metadata.name.clone(),
+            risk: rule.metadata.risk.clone(),
+            category: rule.metadata.category.clone(),
+            offset: payload.len() as u64,
+            length: frame.len() as u64,
+            digest: *blake3::hash(&frame).as_bytes(),
+        });
+        payload.extend_from_slice(&frame);
+    }
+
+    let header = PackageHeader {
+        rule_count: rules.len(),
+        compression: compression.to_string(),
+        encoding: encoding.to_string(),
+        payload_len: payload.len() as u64,
+        payload_digest: *blake3::hash(&payload).as_bytes(),
+        entries,
+        ..header.clone()
+    };
+    let header = encoding.encode_header(&header)?;
+    let header_len = u32::try_from(header.len()).map_err(io::Error::other)?;
+    let header_digest = *blake3::hash(&header).as_bytes();
+
+    let mut data = Vec::with_capacity(PREFIX_LEN + header.len() + payload.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    data.push(compression.tag());
+    data.push(encoding.tag());
+    data.extend_from_slice(&header_len.to_le_bytes());
+    data.extend_from_slice(&header_digest);
+    match signer {
+        Some(key) => {
+            let signature = key.sign(&data[..SIGNED_LEN]);
+            data.push(SIGNATURE_ED25519);
+            data.extend_from_slice(&signature.to_bytes());
+        }
+        None => {
+            data.push(SIGNATURE_NONE);
+            data.extend_from_slice(&[0; SIGNATURE_LENGTH]);
+        }
+    }
+    data.extend_from_slice(&header);
+    data.extend_from_slice(&payload);
+
+    Ok((data, header_digest))
+}
+
+/// 读取不可信规则包时的资源上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// 包头字节数
+    pub max_header_size: u64,
+    /// 单条规则解压后的字节数
+    pub max_rule_size: u64,
+    /// 规则数据区与全部规则解压后的总字节数
+    pub max_total_size: u64,
+    /// 规则数量
+    pub max_rules: usize,
+    /// 单个字符串的字节数
+    pub max_string_len: usize,
+    /// 单个列表的元素数
+    pub max_vec_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_header_size: 16 << 20,
+            max_rule_size: 4 << 20,
+            max_total_size: 256 << 20,
+            max_rules: 100_000,
+            max_string_len: 1 << 20,
+            max_vec_len: 65_536,
+        }
+    }
+}
+
+impl Limits {
+    /// 以 bincode 解码，分配的内存不超过 `limit` 字节
+    fn decode<T: DeserializeOwned>(data: &[u8], limit: u64) -> bincode::Result<T> {
+        bincode::options()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(limit)
+            .deserialize(data)
+    }
+
+    pub(crate) fn check(what: impl FnOnce() -> String, value: u64, limit: u64) -> Result<(), PackageError> {
+        if value > limit {
+            return Err(PackageError::LimitExceeded { what: what(), limit });
+        }
+        Ok(())
+    }
+
+    fn check_string(&self, owner: &str, value: &str) -> Result<(), PackageError> {
+        Limits::check(|| format!("{}的字符串长度", owner), value.len() as u64, self.max_string_len as u64)
+    }
+
+    pub(crate) fn check_vec(&self, owner: &str, len: usize) -> Result<(), PackageError> {
+        Limits::check(|| format!("{}的列表长度", owner), len as u64, self.max_vec_len as u64)
+    }
+
+    fn check_header(&self, header: &PackageHeader) -> Result<(), PackageError> {
+        Limits::check(|| "规则数量".to_string(), header.entries.len() as u64, self.max_rules as u64)?;
+        Limits::check(|| "规则数据大小".to_string(), header.payload_len, self.max_total_size)?;
+        self.check_vec("包头", header.categories.len())?;
+        for value in header.categories.iter().chain([&header.compression, &header.encoding]) {
+            self.check_string("包头", value)?;
+        }
+        for entry in &header.entries {
+            for value in [&entry.id, &entry.name, &entry.risk, &entry.category] {
+                self.check_string("目录项", value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_rule(&self, rule: &SerializedRule) -> Result<(), PackageError> {
+        let owner = format!("规则 {} ", rule.metadata.id);
+        let metadata = &rule.metadata;
+        self.check_vec(&owner, metadata.systeminfo.len())?;
+        self.check_vec(&owner, rule.paths.len())?;
+        self.check_vec(&owner, rule.registry_entries.len())?;
+
+        let strings = [&metadata.id, &metadata.name, &metadata.risk, &metadata.update]
+            .into_iter()
+            .chain([&metadata.category, &metadata.filename, &rule.yaml_content])
+            .chain(metadata.author.iter().chain(&metadata.description))
+            .chain(&metadata.systeminfo)
+            .chain(&rule.paths);
+        for value in strings {
+            self.check_string(&owner, value)?;
+        }
+        for entry in &rule.registry_entries {
+            let strings = [&entry.path, &entry.key, &entry.action]
+                .into_iter()
+                .chain(entry.value.iter().chain(&entry.value_data));
+            for value in strings {
+                self.check_string(&owner, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 固定前缀与包头
+struct Head {
+    compression: Compression,
+    encoding: Encoding,
+    header: PackageHeader,
+    /// 签名覆盖的前缀
+    signed: [u8; SIGNED_LEN],
+    signature: Option<Signature>,
+}
+
+impl Head {
+    fn read<R: Read>(reader: &mut R, limits: &Limits) -> Result<Self, PackageError> {
+        let mut prefix = [0u8; PREFIX_LEN];
+        let mut read = 0;
+        while read < MAGIC.len() {
+            match reader.read(&mut prefix[read..MAGIC.len()])? {
+                0 => return Err(PackageError::NotPackage),
+                n => read += n,
+            }
+        }
+        if &prefix[..MAGIC.len()] != MAGIC {
+            return Err(PackageError::NotPackage);
+        }
+        reader.read_exact(&mut prefix[MAGIC.len()..])?;
+
+        let version = u16::from_le_bytes([prefix[8], prefix[9]]);
+        if version != FORMAT_VERSION {
+            return Err(PackageError::UnsupportedVersion(version));
+        }
+        let compression = Compression::from_tag(prefix[10]).ok_or(PackageError::UnknownCompression(prefix[10]))?;
+        let encoding = Encoding::from_tag(prefix[11]).ok_or(PackageError::UnknownEncoding(prefix[11]))?;
+        let header_len = u32::from_le_bytes([prefix[12], prefix[13], prefix[14], prefix[15]]) as u64;
+        Limits::check(|| "包头大小".to_string(), header_len, limits.max_header_size)?;
+        let digest = &prefix[16..SIGNED_LEN];
+        let signature = match prefix[SIGNED_LEN] {
+            SIGNATURE_NONE => None,
+            SIGNATURE_ED25519 => Some(Signature::from_slice(&prefix[SIGNED_LEN + 1..]).map_err(|_| PackageError::BadSignature)?),
+            _ => return Err(PackageError::BadSignature),
+        };
+
+        let mut header = Vec::new();
+        reader.take(header_len).read_to_end(&mut header)?;
+        if (header.len() as u64) < header_len {
+            return Err(PackageError::Truncated);
+        }
+        if blake3::hash(&header).as_bytes() != digest {
+            return Err(PackageError::HeaderDigestMismatch);
+        }
+        let header = encoding.decode_header(&header, limits)?;
+        limits.check_header(&header)?;
+        if header.rule_count != header.entries.len() {
+            return Err(PackageError::InvalidHeader(format!(
+                "规则数量 {} 与目录中的 {} 条不符",
+                header.rule_count,
+                header.entries.len()
+            )));
+        }
+
+        let mut signed = [0u8; SIGNED_LEN];
+        signed.copy_from_slice(&prefix[..SIGNED_LEN]);
+        Ok(Head { compression, encoding, header, signed, signature })
+    }
+
+    /// 用公钥校验签名
+    fn verify_signature(&self, key: &VerifyingKey) -> Result<(), PackageError> {
+        let signature = self.signature.as_ref().ok_or(PackageError::Unsigned)?;
+        key.verify_strict(&self.signed, signature).map_err(|_| PackageError::BadSignature)
+    }
+
+    /// 按策略校验签名、内容序号与过期时间
+    fn verify(&self, policy: &TrustPolicy) -> Result<(), PackageError> {
+        self.verify_signature(&policy.key)?;
+        policy.check_freshness(&self.header)
+    }
+}
+
+/// 接受规则包的条件
+///
+/// 内容序号与过期时间都在包头中，受签名保护；客户端应持久保存每次接受的规则包的
+/// `serial`，下次读取时填入 `last_serial`。
+#[derive(Debug, Clone)]
+pub struct TrustPolicy {
+    /// 发布公钥
+    pub key: VerifyingKey,
+    /// 上次接受的内容序号，更小的视为回滚
+    pub last_serial: Option<u64>,
+    /// 当前时间（Unix 时间戳，秒）
+    pub now: u64,
+    pub limits: Limits,
+}
+
+impl TrustPolicy {
+    /// 以当前时间、无历史序号、默认读取限制创建
+    pub fn new(key: VerifyingKey) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        TrustPolicy { key, last_serial: None, now, limits: Limits::default() }
+    }
+
+    /// 检查内容序号与过期时间（不校验签名）
+    pub fn check_freshness(&self, header: &PackageHeader) -> Result<(), PackageError> {
+        if let Some(last_serial) = self.last_serial {
+            if header.serial < last_serial {
+                return Err(PackageError::Rollback { serial: header.serial, last_serial });
+            }
+        }
+        match header.expires_at {
+            Some(expires_at) if self.now >= expires_at => Err(PackageError::Expired { expires_at }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 只读取固定前缀与包头，不读取规则数据（不校验签名）
+pub fn read_header<R: Read>(reader: &mut R) -> Result<(Compression, PackageHeader), PackageError> {
+    let head = Head::read(reader, &Limits::default())?;
+    Ok((head.compression, head.header))
+}
+
+/// 读取包头并按策略校验
+pub fn read_header_verified<R: Read>(
+    reader: &mut R,
+    policy: &TrustPolicy,
+) -> Result<(Compression, PackageHeader), PackageError> {
+    let head = Head::read(reader, &policy.limits)?;
+    head.verify(policy)?;
+    Ok((head.compression, head.header))
+}
+
+/// 读取完整的规则包，规则数据区与各数据帧均需通过摘要校验（不校验签名）
+pub fn read_package<R: Read>(reader: &mut R) -> Result<RulesPackage, PackageError> {
+    let limits = Limits::default();
+    let head = Head::read(reader, &limits)?;
+    read_body(reader, head, &limits)
+}
+
+/// 读取完整的规则包，策略与摘要均需通过校验
+pub fn read_package_verified<R: Read>(reader: &mut R, policy: &TrustPolicy) -> Result<RulesPackage, PackageError> {
+    let head = Head::read(reader, &policy.limits)?;
+    head.verify(policy)?;
+    read_body(reader, head, &policy.limits)
+}
+
+fn read_body<R: Read>(reader: &mut R, head: Head, limits: &Limits) -> Result<RulesPackage, PackageError> {
+    let Head { compression, encoding, header, .. } = head;
+
+    let mut payload = Vec::new();
+    reader.take(header.payload_len + 1).read_to_end(&mut payload)?;
+    if (payload.len() as u64) < header.payload_len {
+        return Err(PackageError::Truncated);
+    }
+    if payload.len() as u64 != header.payload_len || blake3::hash(&payload).as_bytes() != &header.payload_digest {
+        return Err(PackageError::PayloadDigestMismatch);
+    }
+
+    let mut decompressed = 0;
+    let rules = header
+        .entries
+        .iter()
+        .map(|entry| {
+            let frame = frame_range(entry, payload.len())
+                .map(|range| &payload[range])
+                .ok_or(PackageError::Truncated)?;
+            decode_frame(entry, compression, encoding, frame, limits, &mut decompressed)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(RulesPackage { header, rules })
+}
+
+/// 签名状态
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    /// 未签名
+    Unsigned,
+    /// 已签名，未提供公钥校验
+    Unchecked,
+    /// 签名有效
+    Valid,
+    /// 签名无效
+    Invalid,
+}
+
+/// 校验结果
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub header: PackageHeader,
+    /// 包头摘要，间接覆盖全部内容且与签名无关，可用于比对可复现构建
+    pub header_digest: Digest,
+    /// 实际读到的规则数据区长度
+    pub payload_len: u64,
+    /// 规则数据区长度与摘要是否相符
+    pub payload_intact: bool,
+    /// 数据帧缺失或摘要不符的规则 id
+    pub damaged: Vec<String>,
+    pub signature: SignatureStatus,
+    /// 是否提供了公钥（此时要求签名有效）
+    pub key_checked: bool,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        let signature_ok = match self.signature {
+            SignatureStatus::Valid => true,
+            SignatureStatus::Invalid => false,
+            SignatureStatus::Unsigned | SignatureStatus::Unchecked => !self.key_checked,
+        };
+        self.payload_intact && self.damaged.is_empty() && signature_ok
+    }
+}
+
+/// 校验规则包的全部摘要（不解压规则），提供公钥时同时校验签名；包头损坏时返回错误
+pub fn verify_package<R: Read>(
+    reader: &mut R,
+    key: Option<&VerifyingKey>,
+) -> Result<VerifyReport, PackageError> {
+    let head = Head::read(reader, &Limits::default())?;
+    let signature = match (&head.signature, key) {
+        (None, _) => SignatureStatus::Unsigned,
+        (Some(_), None) => SignatureStatus::Unchecked,
+        (Some(_), Some(key)) => match head.verify_signature(key) {
+            Ok(()) => SignatureStatus::Valid,
+            Err(_) => SignatureStatus::Invalid,
+        },
+    };
+    let mut header_digest = [0u8; DIGEST_LEN];
+    header_digest.copy_from_slice(&head.signed[SIGNED_LEN - DIGEST_LEN..]);
+    let header = head.header;
+
+    let mut payload = Vec::new();
+    reader.take(header.payload_len + 1).read_to_end(&mut payload)?;
+    let payload_intact = payload.len() as u64 == header.payload_len
+        && blake3::hash(&payload).as_bytes() == &header.payload_digest;
+
+    let damaged = header
+        .entries
+        .iter()
+        .filter(|entry| {
+            frame_range(entry, payload.len())
+                .is_none_or(|range| blake3::hash(&payload[range]).as_bytes() != &entry.digest)
+        })
+        .map(|entry| entry.id.clone())
+        .collect();
+
+    Ok(VerifyReport {
+        header,
+        header_digest,
+        payload_len: payload.len() as u64,
+        payload_intact,
+        damaged,
+        signature,
+        key_checked: key.is_some(),
+    })
+}
+
+/// 数据帧在规则数据区中的范围，超出 `len` 时返回 `None`
+fn frame_range(entry: &TocEntry, len: usize) -> Option<std::ops::Range<usize>> {
+    let offset = usize::try_from(entry.offset).ok()?;
+    let end = offset.checked_add(usize::try_from(entry.length).ok()?)?;
+    (end <= len).then_some(offset..end)
+}
+
+/// 校验、解压并解析单条规则的数据帧，`decompressed` 累计解压后的字节数
+fn decode_frame(
+    entry: &TocEntry,
+    compression: Compression,
+    encoding: Encoding,
+    frame: &[u8],
+    limits: &Limits,
+    decompressed: &mut u64,
+) -> Result<SerializedRule, PackageError> {
+    if blake3::hash(frame).as_bytes() != &entry.digest {
+        return Err(PackageError::RuleDigestMismatch { id: entry.id.clone() });
+    }
+
+    let invalid = |message: String| PackageError::InvalidRule { id: entry.id.clone(), message };
+    let encoded = compression
+        .decompress(frame, limits.max_rule_size)
+        .map_err(|e| invalid(format!("解压失败: {}", e)))?
+        .ok_or_else(|| PackageError::LimitExceeded {
+            what: format!("规则 {} 解压后的大小", entry.id),
+            limit: limits.max_rule_size,
+        })?;
+    *decompressed += encoded.len() as u64;
+    Limits::check(|| "全部规则解压后的大小".to_string(), *decompressed, limits.max_total_size)?;
+
+    let rule = encoding.decode_rule(&entry.id, &encoded, limits)?;
+    if rule.metadata.id != entry.id {
+        return Err(invalid(format!("数据帧中的 id 为 {}", rule.metadata.id)));
+    }
+    limits.check_rule(&rule)?;
+
+    Ok(rule)
+}
+
+/// 按目录随机读取规则包
+///
+/// 打开时只读取包头，每次读取规则时定位到对应的数据帧单独解压：
+///
+/// ```no_run
+/// use winclean_rules::package::PackageReader;
+///
+/// let rule = PackageReader::open("dist/rules.bin")?.rule("soft_mgr")?;
+/// # Ok::<(), winclean_rules::package::PackageError>(())
+/// ```
+#[derive(Debug)]
+pub struct PackageReader<R> {
+    reader: R,
+    compression: Compression,
+    encoding: Encoding,
+    header: PackageHeader,
+    /// 规则数据区在文件中的起始位置
+    payload_start: u64,
+    /// id 到目录项下标
+    index: HashMap<String, usize>,
+    limits: Limits,
+    /// 已解压的字节数，每条规则只计一次
+    decompressed: u64,
+    /// 已计入 `decompressed` 的目录项
+    counted: Vec<bool>,
+}
+
+impl PackageReader<File> {
+    /// 打开规则包文件（不校验签名）
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PackageError> {
+        PackageReader::new(File::open(path)?)
+    }
+
+    /// 打开规则包文件并按策略校验
+    pub fn open_verified(path: impl AsRef<Path>, policy: &TrustPolicy) -> Result<Self, PackageError> {
+        PackageReader::new_verified(File::open(path)?, policy)
+    }
+}
+
+impl<R: Read + Seek> PackageReader<R> {
+    /// 读取包头并建立索引
+    pub fn new(reader: R) -> Result<Self, PackageError> {
+        PackageReader::with_limits(reader, Limits::default())
+    }
+
+    /// 以指定的读取限制读取包头并建立索引（不校验签名）
+    pub fn with_limits(mut reader: R, limits: Limits) -> Result<Self, PackageError> {
+        let head = Head::read(&mut reader, &limits)?;
+        PackageReader::from_head(reader, head, limits)
+    }
+
+    /// 读取包头、按策略校验并建立索引
+    pub fn new_verified(mut reader: R, policy: &TrustPolicy) -> Result<Self, PackageError> {
+        let head = Head::read(&mut reader, &policy.limits)?;
+        head.verify(policy)?;
+        PackageReader::from_head(reader, head, policy.limits)
+    }
+
+    fn from_head(mut reader: R, head: Head, limits: Limits) -> Result<Self, PackageError> {
+        let Head { compression, encoding, header, .. } = head;
+        let payload_start = reader.stream_position()?;
+        let index = header.entries.iter().enumerate().map(|(i, e)| (e.id.clone(), i)).collect();
+        let counted = vec![false; header.entries.len()];
+
+        Ok(PackageReader {
+            reader,
+            compression,
+            encoding,
+            header,
+            payload_start,
+            index,
+            limits,
+            decompressed: 0,
+            counted,
+        })
+    }
+
+    pub fn header(&self) -> &PackageHeader {
+        &self.header
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// 目录
+    pub fn entries(&self) -> &[TocEntry] {
+        &self.header.entries
+    }
+
+    /// 按 id 读取规则，不存在时返回 `None`
+    pub fn rule(&mut self, id: &str) -> Result<Option<SerializedRule>, PackageError> {
+        match self.index.get(id) {
+            Some(&i) => self.read_entry(i).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 读取某个分类下的全部规则
+    pub fn category(&mut self, category: &str) -> Result<Vec<SerializedRule>, PackageError> {
+        let indices: Vec<usize> = (0..self.header.entries.len())
+            .filter(|&i| self.header.entries[i].category == category)
+            .collect();
+        indices.into_iter().map(|i| self.read_entry(i)).collect()
+    }
+
+    /// 按目录顺序读取全部规则
+    pub fn rules(&mut self) -> Result<Vec<SerializedRule>, PackageError> {
+        (0..self.header.entries.len()).map(|i| self.read_entry(i)).collect()
+    }
+
+    fn read_entry(&mut self, i: usize) -> Result<SerializedRule, PackageError> {
+        let entry = &self.header.entries[i];
+        let payload_len = usize::try_from(self.header.payload_len).map_err(|_| PackageError::Truncated)?;
+        let range = frame_range(entry, payload_len).ok_or(PackageError::Truncated)?;
+
+        self.reader.seek(SeekFrom::Start(self.payload_start + range.start as u64))?;
+        let mut frame = Vec::new();
+        (&mut self.reader).take(entry.length).read_to_end(&mut frame)?;
+        if frame.len() != range.len() {
+            return Err(PackageError::Truncated);
+        }
+
+        // 反复读取同一条规则不占用总解压预算
+        let mut decompressed = if self.counted[i] { 0 } else { self.decompressed };
+        let rule = decode_frame(entry, self.compression, self.encoding, &frame, &self.limits, &mut decompressed)?;
+        if !self.counted[i] {
+            self.counted[i] = true;
+            self.decompressed = decompressed;
+        }
+        Ok(rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const SERIAL: u64 = 20250101;
+    const CREATED_AT: u64 = 1_700_000_000;
+    const EXPIRES_AT: u64 = 1_800_000_000;
+
+    fn key() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    /// 由合法的规则源码构建，源码中重复的注释使压缩有效果
+    fn rule(id: &str, category: &str) -> SerializedRule {
+        let yaml = format!(
+            "id: {id}\nname: 规则 {id}\nrisk: high\nsysteminfo:\n  - Windows 11\nupdate: 2025-01-01\nauthor: winclean\n\
+             {}match:\n  path:\n    - \"%APPDATA%\\\\{id}\"\n  registry:\n    - path: HKCU\\Software\n      key: {id}\n\
+             \x20     action: delete_key\n",
+            format!("# 规则 {} 的说明\n", id).repeat(64),
+        );
+        let model: Rule = serde_yaml::from_str(&yaml).unwrap();
+        assert!(model.validate().iter().all(|issue| issue.severity != crate::diagnostic::Severity::Error));
+        SerializedRule::new(&model, category, &format!("{}.yaml", id), &yaml)
+    }
+
+    fn rules() -> Vec<SerializedRule> {
+        vec![rule("alpha", "apps"), rule("beta", "apps"), rule("gamma", "system")]
+    }
+
+    fn pack(compression: Compression, encoding: Encoding, signer: Option<&SigningKey>) -> Vec<u8> {
+        let rules = rules();
+        let header = PackageHeader::new(&rules, SERIAL, CREATED_AT, Some(EXPIRES_AT));
+        let (data, header_digest) = write_package(&header, &rules, compression, encoding, signer).unwrap();
+        assert_eq!(verify_package(&mut Cursor::new(&data), None).unwrap().header_digest, header_digest);
+        data
+    }
+
+    fn policy() -> TrustPolicy {
+        TrustPolicy { key: key().verifying_key(), last_serial: None, now: CREATED_AT, limits: Limits::default() }
+    }
+
+    fn read_verified(data: &[u8], policy: &TrustPolicy) -> Result<RulesPackage, PackageError> {
+        read_package_verified(&mut Cursor::new(data), policy)
+    }
+
+    /// 以新的包头替换原包头，重新计算长度与摘要（结果未签名）
+    fn replace_header(data: &[u8], encoding: Encoding, header: &PackageHeader) -> Vec<u8> {
+        replace_header_bytes(data, &encoding.encode_header(header).unwrap())
+    }
+
+    fn replace_header_bytes(data: &[u8], header: &[u8]) -> Vec<u8> {
+        let old_len = u32::from_le_bytes(data[12..16].try_into().unwrap()) as usize;
+        let mut out = data[..PREFIX_LEN].to_vec();
+        out[12..16].copy_from_slice(&(header.len() as u32).to_le_bytes());
+        out[16..SIGNED_LEN].copy_from_slice(blake3::hash(header).as_bytes());
+        out[SIGNED_LEN] = SIGNATURE_NONE;
+        out[SIGNED_LEN + 1..PREFIX_LEN].fill(0);
+        out.extend_from_slice(header);
+        out.extend_from_slice(&data[PREFIX_LEN + old_len..]);
+        out
+    }
+
+    fn header_of(data: &[u8]) -> PackageHeader {
+        read_header(&mut Cursor::new(data)).unwrap().1
+    }
+
+    #[test]
+    fn round_trips_every_compression_and_encoding() {
+        for compression in [Compression::None, Compression::Zstd] {
+            for encoding in [Encoding::Bincode, Encoding::Protobuf] {
+                let data = pack(compression, encoding, Some(&key()));
+                let package = read_verified(&data, &policy()).unwrap();
+                assert_eq!(package.header.serial, SERIAL);
+                assert_eq!(package.header.expires_at, Some(EXPIRES_AT));
+                assert_eq!(package.header.compression, compression.as_str());
+                assert_eq!(package.header.encoding, encoding.as_str());
+                assert_eq!(package.header.categories, ["apps", "system"]);
+                for (read, written) in package.rules.iter().zip(rules()) {
+                    assert_eq!(read.metadata.id, written.metadata.id);
+                    assert_eq!(read.metadata.author, written.metadata.author);
+                    assert_eq!(read.yaml_content, written.yaml_content);
+                    assert_eq!(read.paths, written.paths);
+                    assert_eq!(read.registry_entries[0].key, written.registry_entries[0].key);
+                }
+
+                let mut reader = PackageReader::new_verified(Cursor::new(&data), &policy()).unwrap();
+                assert_eq!(reader.compression(), compression);
+                assert_eq!(reader.encoding(), encoding);
+                assert_eq!(reader.rule("gamma").unwrap().unwrap().metadata.category, "system");
+                assert!(reader.rule("missing").unwrap().is_none());
+                assert_eq!(reader.category("apps").unwrap().len(), 2);
+                assert_eq!(reader.rules().unwrap().len(), 3);
+
+                let report = verify_package(&mut Cursor::new(&data), Some(&key().verifying_key())).unwrap();
+                assert_eq!(report.signature, SignatureStatus::Valid);
+                assert!(report.is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_header() {
+        for encoding in [Encoding::Bincode, Encoding::Protobuf] {
+            let mut data = pack(Compression::Zstd, encoding, Some(&key()));
+            data[PREFIX_LEN + 3] ^= 1;
+            assert!(matches!(read_verified(&data, &policy()), Err(PackageError::HeaderDigestMismatch)));
+            assert!(matches!(read_package(&mut Cursor::new(&data)), Err(PackageError::HeaderDigestMismatch)));
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_frame() {
+        let data = pack(Compression::Zstd, Encoding::Protobuf, Some(&key()));
+        let last = header_of(&data).entries.last().unwrap().id.clone();
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+
+        assert!(matches!(read_verified(&tampered, &policy()), Err(PackageError::PayloadDigestMismatch)));
+
+        let mut reader = PackageReader::new_verified(Cursor::new(&tampered), &policy()).unwrap();
+        assert!(reader.rule("alpha").unwrap().is_some());
+        assert!(matches!(reader.rule(&last), Err(PackageError::RuleDigestMismatch { id }) if id == last));
+
+        let report = verify_package(&mut Cursor::new(&tampered), None).unwrap();
+        assert!(!report.payload_intact);
+        assert_eq!(report.damaged, [last]);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn rejects_tampered_signature() {
+        let mut data = pack(Compression::Zstd, Encoding::Bincode, Some(&key()));
+        data[PREFIX_LEN - 1] ^= 1;
+        assert!(matches!(read_verified(&data, &policy()), Err(PackageError::BadSignature)));
+        // 不校验签名时摘要仍然完整
+        assert!(read_package(&mut Cursor::new(&data)).is_ok());
+
+        // 修改签名覆盖的前缀（压缩标记）同样使签名失效
+        let mut data = pack(Compression::Zstd, Encoding::Bincode, Some(&key()));
+        data[10] = Compression::None.tag();
+        assert!(matches!(read_verified(&data, &policy()), Err(PackageError::BadSignature)));
+
+        let data = pack(Compression::Zstd, Encoding::Bincode, Some(&SigningKey::from_bytes(&[8; 32])));
+        assert!(matches!(read_verified(&data, &policy()), Err(PackageError::BadSignature)));
+        let report = verify_package(&mut Cursor::new(&data), Some(&key().verifying_key())).unwrap();
+        assert_eq!(report.signature, SignatureStatus::Invalid);
+        assert!(!report.is_ok());
+
+        let data = pack(Compression::Zstd, Encoding::Bincode, None);
+        assert!(matches!(read_verified(&data, &policy()), Err(PackageError::Unsigned)));
+    }
+
+    #[test]
+    fn rejects_truncated_package() {
+        for encoding in [Encoding::Bincode, Encoding::Protobuf] {
+            let data = pack(Compression::Zstd, encoding, Some(&key()));
+            assert!(matches!(read_package(&mut Cursor::new(&data[..4])), Err(PackageError::NotPackage)));
+            for len in [MAGIC.len(), 20, PREFIX_LEN, PREFIX_LEN + 10, data.len() - 1] {
+                let result = read_verified(&data[..len], &policy());
+                assert!(matches!(result, Err(PackageError::Truncated)), "截断到 {} 字节: {:?}", len, result.err());
+            }
+        }
+        assert!(matches!(read_package(&mut Cursor::new(b"not a package")), Err(PackageError::NotPackage)));
+    }
+
+    #[test]
+    fn rejects_unknown_prefix_fields() {
+        let data = pack(Compression::Zstd, Encoding::Bincode, None);
+        let mut bad = data.clone();
+        bad[8] = 7;
+        assert!(matches!(read_package(&mut Cursor::new(&bad)), Err(PackageError::UnsupportedVersion(7))));
+        let mut bad = data.clone();
+        bad[10] = 9;
+        assert!(matches!(read_package(&mut Cursor::new(&bad)), Err(PackageError::UnknownCompression(9))));
+        let mut bad = data.clone();
+        bad[11] = 9;
+        assert!(matches!(read_package(&mut Cursor::new(&bad)), Err(PackageError::UnknownEncoding(9))));
+    }
+
+    #[test]
+    fn rejects_rollback_and_expired_packages() {
+        let data = pack(Compression::Zstd, Encoding::Bincode, Some(&key()));
+
+        let policy_at = |last_serial, now| TrustPolicy { last_serial, now, ..policy() };
+        assert!(read_verified(&data, &policy_at(Some(SERIAL), CREATED_AT)).is_ok());
+        assert!(matches!(
+            read_verified(&data, &policy_at(Some(SERIAL + 1), CREATED_AT)),
+            Err(PackageError::Rollback { serial: SERIAL, last_serial }) if last_serial == SERIAL + 1
+        ));
+        assert!(read_verified(&data, &policy_at(None, EXPIRES_AT - 1)).is_ok());
+        assert!(matches!(
+            read_verified(&data, &policy_at(None, EXPIRES_AT)),
+            Err(PackageError::Expired { expires_at: EXPIRES_AT })
+        ));
+        assert!(PackageReader::new_verified(Cursor::new(&data), &policy_at(None, EXPIRES_AT)).is_err());
+    }
+
+    #[test]
+    fn enforces_limits() {
+        let limited = |limits: Limits| TrustPolicy { limits, ..policy() };
+        let exceeded =
+            |result: Result<RulesPackage, PackageError>| matches!(result, Err(PackageError::LimitExceeded { .. }));
+
+        for encoding in [Encoding::Bincode, Encoding::Protobuf] {
+            let data = pack(Compression::Zstd, encoding, Some(&key()));
+            let header_len = u32::from_le_bytes(data[12..16].try_into().unwrap()) as u64;
+            let rule_size = encoding.encoded_size(&rules()[0]).unwrap();
+
+            let cases = [
+                Limits { max_header_size: header_len - 1, ..Limits::default() },
+                Limits { max_rules: 2, ..Limits::default() },
+                Limits { max_rule_size: rule_size - 1, ..Limits::default() },
+                Limits { max_total_size: rule_size * 2, ..Limits::default() },
+                Limits { max_string_len: 100, ..Limits::default() },
+                Limits { max_vec_len: 0, ..Limits::default() },
+            ];
+            for limits in cases {
+                assert!(exceeded(read_verified(&data, &limited(limits))), "{} {:?}", encoding, limits);
+            }
+            let sizes: Vec<u64> = rules().iter().map(|r| encoding.encoded_size(r).unwrap()).collect();
+            let max_rule_size = *sizes.iter().max().unwrap();
+            let limits = Limits { max_rule_size, max_total_size: sizes.iter().sum(), ..Limits::default() };
+            assert!(read_verified(&data, &limited(limits)).is_ok());
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_lengths_in_header() {
+        // bincode：compression 字符串声称的长度远超包头
+        let mut header = Vec::new();
+        header.extend_from_slice(&(FORMAT_VERSION as u32).to_le_bytes());
+        header.extend_from_slice(&SERIAL.to_le_bytes());
+        header.extend_from_slice(&CREATED_AT.to_le_bytes());
+        header.push(0);
+        header.extend_from_slice(&0u64.to_le_bytes());
+        header.extend_from_slice(&u64::MAX.to_le_bytes());
+        let data = replace_header_bytes(&pack(Compression::Zstd, Encoding::Bincode, None), &header);
+        assert!(read_package(&mut Cursor::new(&data)).is_err());
+
+        // 目录项多于上限
+        for encoding in [Encoding::Bincode, Encoding::Protobuf] {
+            let data = pack(Compression::Zstd, encoding, None);
+            let mut header = header_of(&data);
+            header.categories = vec!["apps".to_string(); 100];
+            let data = replace_header(&data, encoding, &header);
+            let limits = Limits { max_vec_len: 10, ..Limits::default() };
+            let result = PackageReader::with_limits(Cursor::new(&data), limits);
+            assert!(matches!(result, Err(PackageError::LimitExceeded { .. })), "{}", encoding);
+        }
+    }
+
+    #[test]
+    fn rejects_rule_count_mismatch() {
+        for encoding in [Encoding::Bincode, Encoding::Protobuf] {
+            let data = pack(Compression::Zstd, encoding, None);
+            let mut header = header_of(&data);
+            header.rule_count += 1;
+            let data = replace_header(&data, encoding, &header);
+            assert!(matches!(read_package(&mut Cursor::new(&data)), Err(PackageError::InvalidHeader(_))));
+        }
+    }
+
+    #[test]
+    fn rejects_frames_outside_payload() {
+        let data = pack(Compression::Zstd, Encoding::Bincode, None);
+        let mut header = header_of(&data);
+        header.entries[1].offset = u64::MAX;
+        let data = replace_header(&data, Encoding::Bincode, &header);
+        assert!(matches!(read_package(&mut Cursor::new(&data)), Err(PackageError::Truncated)));
+        let mut reader = PackageReader::new(Cursor::new(&data)).unwrap();
+        assert!(reader.rule("alpha").is_ok());
+        assert!(matches!(reader.rule("beta"), Err(PackageError::Truncated)));
+    }
+
+    #[test]
+    fn reader_counts_each_rule_once_toward_total_budget() {
+        let data = pack(Compression::Zstd, Encoding::Bincode, None);
+        let sizes: Vec<u64> = rules().iter().map(|r| Encoding::Bincode.encoded_size(r).unwrap()).collect();
+        let limits = Limits { max_total_size: sizes[0] + sizes[1], ..Limits::default() };
+        assert!(header_of(&data).payload_len <= limits.max_total_size);
+
+        let mut reader = PackageReader::with_limits(Cursor::new(&data), limits).unwrap();
+        for _ in 0..10 {
+            assert!(reader.rule("alpha").unwrap().is_some());
+        }
+        assert!(reader.rule("beta").unwrap().is_some());
+        assert!(reader.rule("alpha").unwrap().is_some());
+        assert!(matches!(reader.rule("gamma"), Err(PackageError::LimitExceeded { .. })));
+    }
+
+    #[test]
+    fn date_serial_is_yyyymmdd() {
+        assert_eq!(date_serial(0).unwrap(), 19700101);
+        assert_eq!(date_serial(CREATED_AT).unwrap(), 20231114);
+        assert_eq!(date_serial(time::MAX_TIME).unwrap(), 99991231);
+        assert!(date_serial(time::MAX_TIME + 1).is_err());
+    }
+
+    fn relative_path(category: &str, filename: &str) -> Result<PathBuf, PackageError> {
+        let mut metadata = rule("alpha", category).metadata;
+        metadata.filename = filename.to_string();
+        metadata.relative_path()
+    }
+
+    #[test]
+    fn relative_path_accepts_plain_names() {
+        assert_eq!(relative_path("apps", "alpha.yaml").unwrap(), Path::new("apps").join("alpha.yaml"));
+        assert_eq!(relative_path("系统", "Beta.YML").unwrap(), Path::new("系统").join("Beta.YML"));
+    }
+
+    #[test]
+    fn relative_path_rejects_unsafe_components() {
+        let cases = [
+            ("", "为空"),
+            (".", "指向当前或上级目录"),
+            ("..", "指向当前或上级目录"),
+            ("/etc", "包含路径分隔符"),
+            ("../apps", "包含路径分隔符"),
+            ("apps/evil", "包含路径分隔符"),
+            ("apps\\evil", "包含路径分隔符"),
+            ("\\\\server\\share", "包含路径分隔符"),
+            ("C:", "包含盘符或冒号"),
+            ("C:evil", "包含盘符或冒号"),
+            ("alpha.yaml:stream", "包含盘符或冒号"),
+            ("alpha.yaml::$DATA", "包含盘符或冒号"),
+            ("a\u{0}b", "包含控制字符或 Windows 不允许的字符"),
+            ("a?b", "包含控制字符或 Windows 不允许的字符"),
+            ("apps.", "以点或空格结尾"),
+            ("apps ", "以点或空格结尾"),
+            ("CON", "是 Windows 保留名"),
+            ("nul", "是 Windows 保留名"),
+            ("NUL.txt", "是 Windows 保留名"),
+            ("Aux .yaml", "是 Windows 保留名"),
+            ("COM1", "是 Windows 保留名"),
+            ("lpt9.yaml", "是 Windows 保留名"),
+            ("CONOUT$", "是 Windows 保留名"),
+        ];
+        for (name, expected) in cases {
+            let results = [("category", relative_path(name, "alpha.yaml")), ("filename", relative_path("apps", name))];
+            for (field, result) in results {
+                match result {
+                    Err(PackageError::UnsafePath { field: actual, value, reason, .. }) => {
+                        assert_eq!((actual, value.as_str(), reason), (field, name, expected), "{:?}", name);
+                    }
+                    other => panic!("{} {:?}: {:?}", field, name, other),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn relative_path_requires_yaml_extension() {
+        for filename in ["alpha", "alpha.json", "alpha.yaml.exe", "yaml"] {
+            assert!(
+                matches!(
+                    relative_path("apps", filename),
+                    Err(PackageError::UnsafePath { field: "filename", reason: "不是 .yaml 文件", .. })
+                ),
+                "{}",
+                filename
+            );
+        }
+        // 分类不要求扩展名
+        assert!(relative_path("apps.yaml", "alpha.yaml").is_ok());
+        // 只是以保留名开头的名称不是保留名
+        assert!(relative_path("console", "com10.yaml").is_ok());
+    }
+}
@@ -0,0 +1,496 @@
+//! 路径模式
+//! 解析并匹配 `%APPDATA%\<SoftMgr.+>` 形式的 WinClean 路径表达式
+//!
+//! 路径模式由可选的环境变量前缀、字面量段和 `<...>` 包裹的正则段组成。
+//! 匹配按 Windows 习惯忽略大小写，`\` 与 `/` 都视为分隔符，
+//! 每个正则段只在所在的路径组件内整段匹配，不会跨越分隔符。
+//! 注册表路径（见 [`PathPattern::parse_registry`]）只以 `\` 分隔，且没有环境变量前缀。
+
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// 路径模式中的一段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// 环境变量前缀，如 `%APPDATA%`（不含百分号）
+    Env(String),
+    /// 普通文本（可能包含分隔符）
+    Literal(String),
+    /// `<...>` 包裹的正则表达式（不含尖括号）
+    Regex(String),
+}
+
+/// 路径模式错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    /// `<` 没有对应的 `>`
+    UnclosedRegex { offset: usize },
+    /// `>` 没有对应的 `<`
+    UnexpectedClose { offset: usize },
+    /// `<>` 中没有内容
+    EmptyRegex { offset: usize },
+    /// 正则表达式语法错误
+    InvalidRegex { regex: String, message: String },
+    /// 通配符无法编译（如超出正则的大小上限）
+    InvalidWildcard { pattern: String, message: String },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::UnclosedRegex { offset } => {
+                write!(f, "第 {} 个字符处的 `<` 没有闭合", offset + 1)
+            }
+            PatternError::UnexpectedClose { offset } => {
+                write!(f, "第 {} 个字符处的 `>` 没有对应的 `<`", offset + 1)
+            }
+            PatternError::EmptyRegex { offset } => {
+                write!(f, "第 {} 个字符处的 `<>` 为空", offset + 1)
+            }
+            PatternError::InvalidRegex { regex, message } => {
+                write!(f, "正则表达式 `<{}>` 无效: {}", regex, message)
+            }
+            PatternError::InvalidWildcard { pattern, message } => {
+                // 通配符可能很长，只显示开头
+                match pattern.char_indices().nth(64) {
+                    Some((end, _)) => write!(f, "通配符 `{}…` 无效: {}", &pattern[..end], message),
+                    None => write!(f, "通配符 `{}` 无效: {}", pattern, message),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// 路径组件（两个分隔符之间的部分）
+#[derive(Debug, Clone)]
+pub struct Component {
+    parts: Vec<Segment>,
+    matcher: Matcher,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    /// 纯字面量，保存小写形式
+    Literal(String),
+    Regex(Regex),
+}
+
+impl Component {
+    fn new(parts: Vec<Segment>) -> Result<Self, PatternError> {
+        let matcher = if parts.iter().all(|p| !matches!(p, Segment::Regex(_))) {
+            Matcher::Literal(component_text(&parts).to_lowercase())
+        } else {
+            let mut source = String::new();
+            for part in &parts {
+                match part {
+                    Segment::Env(name) => source.push_str(&regex::escape(&format!("%{}%", name))),
+                    Segment::Literal(text) => source.push_str(&regex::escape(text)),
+                    Segment::Regex(regex) => {
+                        compile(regex)?;
+                        source.push_str(&format!("(?:{})", regex));
+                    }
+                }
+            }
+            Matcher::Regex(compile_anchored(&source, &source)?)
+        };
+
+        Ok(Component { parts, matcher })
+    }
+
+    /// 组成该组件的各段
+    pub fn parts(&self) -> &[Segment] {
+        &self.parts
+    }
+
+    /// 是否不含正则段
+    pub fn is_literal(&self) -> bool {
+        matches!(self.matcher, Matcher::Literal(_))
+    }
+
+    /// 匹配单个路径组件（文件或目录名）
+    pub fn matches(&self, name: &str) -> bool {
+        match &self.matcher {
+            Matcher::Literal(text) => name.to_lowercase() == *text,
+            Matcher::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Segment::Env(name) => write!(f, "%{}%", name)?,
+                Segment::Literal(text) => f.write_str(text)?,
+                Segment::Regex(regex) => write!(f, "<{}>", regex)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 路径语法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    /// 文件路径：`\` 与 `/` 均为分隔符，允许环境变量前缀
+    File,
+    /// 注册表路径：只有 `\` 是分隔符（键名中可以出现 `/`）
+    Registry,
+}
+
+impl Syntax {
+    fn is_separator(self, c: char) -> bool {
+        match self {
+            Syntax::File => is_separator(c),
+            Syntax::Registry => c == '\\',
+        }
+    }
+}
+
+/// 路径模式
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    source: String,
+    syntax: Syntax,
+    segments: Vec<Segment>,
+    components: Vec<Component>,
+}
+
+impl PathPattern {
+    /// 解析文件路径模式并编译其中的正则段
+    pub fn parse(pattern: &str) -> Result<Self, PatternError> {
+        PathPattern::parse_with(pattern, Syntax::File)
+    }
+
+    /// 解析注册表路径模式
+    pub fn parse_registry(pattern: &str) -> Result<Self, PatternError> {
+        PathPattern::parse_with(pattern, Syntax::Registry)
+    }
+
+    fn parse_with(pattern: &str, syntax: Syntax) -> Result<Self, PatternError> {
+        let segments = split_segments(pattern, syntax == Syntax::File)?;
+
+        // 按分隔符切分为组件，正则段内的 `\` 不参与切分
+        let mut components = Vec::new();
+        let mut current = Vec::new();
+        for segment in &segments {
+            match segment {
+                Segment::Literal(text) => {
+                    let mut pieces = text.split(|c| syntax.is_separator(c)).peekable();
+                    while let Some(piece) = pieces.next() {
+                        if !piece.is_empty() {
+                            current.push(Segment::Literal(piece.to_string()));
+                        }
+                        if pieces.peek().is_some() && !current.is_empty() {
+                            components.push(Component::new(std::mem::take(&mut current))?);
+                        }
+                    }
+                }
+                other => current.push(other.clone()),
+            }
+        }
+        if !current.is_empty() {
+            components.push(Component::new(current)?);
+        }
+
+        Ok(PathPattern { source: pattern.to_string(), syntax, segments, components })
+    }
+
+    /// 原始路径模式文本
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// 环境变量前缀（不含百分号）
+    pub fn env(&self) -> Option<&str> {
+        match self.segments.first() {
+            Some(Segment::Env(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// 按出现顺序遍历各段
+    pub fn segments(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.iter()
+    }
+
+    /// 按分隔符切分后的组件
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    /// 完整匹配路径（忽略大小写，未展开的环境变量按字面比较）
+    pub fn matches(&self, path: &str) -> bool {
+        let names: Vec<&str> = path
+            .split(|c| self.syntax.is_separator(c))
+            .filter(|s| !s.is_empty())
+            .collect();
+        names.len() == self.components.len()
+            && self.components.iter().zip(&names).all(|(c, name)| c.matches(name))
+    }
+
+    /// 用给定的取值展开环境变量前缀，无法展开时返回 `None`
+    pub fn expand_env<F>(&self, lookup: F) -> Option<Result<PathPattern, PatternError>>
+    where
+        F: FnOnce(&str) -> Option<String>,
+    {
+        let name = self.env()?;
+        let value = lookup(name)?;
+        let rest = &self.source[name.len() + 2..];
+        Some(PathPattern::parse(&format!("{}{}", value, rest)))
+    }
+}
+
+impl FromStr for PathPattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PathPattern::parse(s)
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+fn component_text(parts: &[Segment]) -> String {
+    parts
+        .iter()
+        .map(|p| match p {
+            Segment::Env(name) => format!("%{}%", name),
+            Segment::Literal(text) | Segment::Regex(text) => text.clone(),
+        })
+        .collect()
+}
+
+/// 拆分路径模式
+///
+/// 开头的 `%NAME%` 识别为环境变量前缀；正则段内的 `\` 视为转义符，
+/// 成对的 `<>`（如命名分组 `(?P<name>...)`）不会提前结束正则段。
+pub fn parse_segments(pattern: &str) -> Result<Vec<Segment>, PatternError> {
+    split_segments(pattern, true)
+}
+
+fn split_segments(pattern: &str, allow_env: bool) -> Result<Vec<Segment>, PatternError> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pattern.chars().enumerate();
+
+    if let Some(name) = env_prefix(pattern).filter(|_| allow_env) {
+        segments.push(Segment::Env(name.to_string()));
+        for _ in 0..name.chars().count() + 2 {
+            chars.next();
+        }
+    }
+
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '<' => {
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+
+                let mut regex = String::new();
+                let mut depth = 1;
+                loop {
+                    let Some((_, c)) = chars.next() else {
+                        return Err(PatternError::UnclosedRegex { offset });
+                    };
+                    match c {
+                        '\\' => {
+                            regex.push(c);
+                            if let Some((_, escaped)) = chars.next() {
+                                regex.push(escaped);
+                            }
+                            continue;
+                        }
+                        '<' => depth += 1,
+                        '>' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    regex.push(c);
+                }
+
+                if regex.is_empty() {
+                    return Err(PatternError::EmptyRegex { offset });
+                }
+                segments.push(Segment::Regex(regex));
+            }
+            '>' => return Err(PatternError::UnexpectedClose { offset }),
+            _ => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+
+    Ok(segments)
+}
+
+/// 识别开头的 `%NAME%`，要求其后为分隔符或结尾
+fn env_prefix(pattern: &str) -> Option<&str> {
+    let rest = pattern.strip_prefix('%')?;
+    let end = rest.find('%')?;
+    let name = &rest[..end];
+    let valid = !name.is_empty() && !name.contains(|c: char| is_separator(c) || c == '<' || c == '>');
+    let followed = rest[end + 1..].chars().next().is_none_or(is_separator);
+    (valid && followed).then_some(name)
+}
+
+/// 检查路径模式，返回全部错误（结构错误时只返回该错误）
+pub fn check(pattern: &str) -> Vec<PatternError> {
+    let segments = match parse_segments(pattern) {
+        Ok(segments) => segments,
+        Err(e) => return vec![e],
+    };
+
+    segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Regex(regex) => compile(regex).err(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 编译正则段（忽略大小写，整段匹配）
+pub fn compile(regex: &str) -> Result<Regex, PatternError> {
+    // 先单独编译，避免 `a)|(b` 这类表达式借助外层分组蒙混过关
+    Regex::new(regex).map_err(|e| invalid_regex(regex, &e))?;
+    compile_anchored(regex, regex)
+}
+
+fn compile_anchored(source: &str, regex: &str) -> Result<Regex, PatternError> {
+    Regex::new(&format!("(?i)^(?:{})$", source)).map_err(|e| invalid_regex(regex, &e))
+}
+
+fn invalid_regex(regex: &str, error: &regex::Error) -> PatternError {
+    PatternError::InvalidRegex {
+        regex: regex.to_string(),
+        message: regex_error_message(error),
+    }
+}
+
+/// 提取 regex 错误中的说明行
+pub(crate) fn regex_error_message(error: &regex::Error) -> String {
+    let text = error.to_string();
+    text.lines()
+        .find_map(|line| line.strip_prefix("error: "))
+        .unwrap_or(&text)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(text: &str) -> PathPattern {
+        PathPattern::parse(text).unwrap()
+    }
+
+    #[test]
+    fn splits_env_literal_and_regex_segments() {
+        let p = pattern(r"%APPDATA%\<SoftMgr.+>\update.exe");
+        assert_eq!(p.env(), Some("APPDATA"));
+        assert_eq!(
+            p.segments().cloned().collect::<Vec<_>>(),
+            [
+                Segment::Env("APPDATA".to_string()),
+                Segment::Literal("\\".to_string()),
+                Segment::Regex("SoftMgr.+".to_string()),
+                Segment::Literal("\\update.exe".to_string()),
+            ]
+        );
+        assert_eq!(p.components().len(), 3);
+        assert!(p.components()[0].is_literal());
+        assert!(!p.components()[1].is_literal());
+        assert_eq!(p.to_string(), r"%APPDATA%\<SoftMgr.+>\update.exe");
+
+        // 不在开头或其后不是分隔符的 `%...%` 是普通文本
+        assert_eq!(pattern(r"C:\%TEMP%\x").env(), None);
+        assert_eq!(pattern(r"%TEMP%x").env(), None);
+
+        // 正则段中的转义与成对尖括号
+        let p = pattern(r"C:\<(?P<n>a\>b)>");
+        assert_eq!(p.segments().last(), Some(&Segment::Regex(r"(?P<n>a\>b)".to_string())));
+    }
+
+    #[test]
+    fn matches_case_insensitively_per_component() {
+        let p = pattern(r"%APPDATA%\<SoftMgr.+>\update.exe");
+        assert!(p.matches(r"%appdata%\SoftMgrUpdate\UPDATE.EXE"));
+        assert!(p.matches("%APPDATA%/softmgr2/update.exe"));
+        assert!(p.matches(r"%APPDATA%\\SoftMgrX\update.exe\"));
+        // 正则段整段匹配且不跨越分隔符
+        assert!(!p.matches(r"%APPDATA%\SoftMgr\update.exe"));
+        assert!(!p.matches(r"%APPDATA%\xSoftMgrUpdate\update.exe"));
+        assert!(!p.matches(r"%APPDATA%\SoftMgr\a\update.exe"));
+        assert!(!p.matches(r"%APPDATA%\SoftMgrUpdate"));
+
+        let p = pattern(r"C:\Temp\cache_<\d+>.tmp");
+        assert!(p.matches(r"c:\temp\CACHE_42.TMP"));
+        assert!(!p.matches(r"C:\Temp\cache_.tmp"));
+        assert!(!p.matches(r"C:\Temp\cache_42xtmp"));
+    }
+
+    #[test]
+    fn registry_patterns_only_split_on_backslash() {
+        let p = PathPattern::parse_registry(r"HKCU\Software\<Soft.+>\a/b").unwrap();
+        assert_eq!(p.env(), None);
+        assert_eq!(p.components().len(), 4);
+        assert!(p.matches(r"hkcu\software\SoftMgr\A/B"));
+        assert!(!p.matches(r"HKCU\Software\SoftMgr\a\b"));
+
+        let p = PathPattern::parse_registry(r"%X%\Key").unwrap();
+        assert_eq!(p.env(), None);
+        assert!(p.matches(r"%x%\key"));
+    }
+
+    #[test]
+    fn expands_env_prefix() {
+        let p = pattern(r"%LOCALAPPDATA%\<Temp.*>");
+        let expanded = p.expand_env(|name| (name == "LOCALAPPDATA").then(|| r"C:\Users\u\AppData\Local".to_string()));
+        let expanded = expanded.unwrap().unwrap();
+        assert!(expanded.matches(r"C:\Users\u\AppData\Local\TempFiles"));
+        assert!(p.expand_env(|_| None).is_none());
+        assert!(pattern(r"C:\x").expand_env(|_| Some(String::new())).is_none());
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert_eq!(parse_segments(r"C:\<abc").unwrap_err(), PatternError::UnclosedRegex { offset: 3 });
+        assert_eq!(parse_segments(r"C:\a>b").unwrap_err(), PatternError::UnexpectedClose { offset: 4 });
+        assert_eq!(parse_segments(r"C:\<>").unwrap_err(), PatternError::EmptyRegex { offset: 3 });
+        assert!(matches!(PathPattern::parse(r"C:\<a(>"), Err(PatternError::InvalidRegex { .. })));
+        // 不能借助外层分组把两个正则段连成一个合法表达式
+        assert!(matches!(PathPattern::parse(r"C:\<a)|(b>"), Err(PatternError::InvalidRegex { .. })));
+        assert!(compile("a)|(b").is_err());
+
+        assert!(check(r"%TEMP%\<SoftMgr.+>").is_empty());
+        assert_eq!(check(r"C:\<a(>\<b[>").len(), 2);
+        assert_eq!(check(r"C:\<a(>\<b"), [PatternError::UnclosedRegex { offset: 8 }]);
+    }
+
+    #[test]
+    fn compiled_regex_matches_whole_text_ignoring_case() {
+        let regex = compile("soft.+").unwrap();
+        assert!(regex.is_match("SoftMgr"));
+        assert!(!regex.is_match("xSoftMgr"));
+        assert!(!regex.is_match("Soft"));
+    }
+}
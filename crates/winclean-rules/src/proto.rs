@@ -0,0 +1,403 @@
+//! Protobuf 编码
+//! 与 `proto/winclean_rules.proto` 一一对应的消息定义及其与规则包模型的转换
+//!
+//! 消息按 schema 手工定义（构建时不依赖 protoc），修改 schema 时需同步修改这里。
+//!
+//! prost 解码时不限制分配的内存，解码前先按 [`Limits`] 检查各列表的长度。
+
+use crate::package::{
+    self, Digest, Limits, PackageError, PackageHeader, RegistryEntry, RuleMetadata, SerializedRule, TocEntry,
+};
+use prost::encoding::decode_varint;
+use prost::Message;
+
+/// 包头
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoHeader {
+    #[prost(uint32, tag = "1")]
+    pub version: u32,
+    #[prost(uint64, tag = "2")]
+    pub serial: u64,
+    #[prost(uint64, tag = "3")]
+    pub created_at: u64,
+    #[prost(uint64, optional, tag = "4")]
+    pub expires_at: Option<u64>,
+    #[prost(uint64, tag = "5")]
+    pub rule_count: u64,
+    #[prost(string, tag = "6")]
+    pub compression: String,
+    #[prost(string, tag = "7")]
+    pub encoding: String,
+    #[prost(string, repeated, tag = "8")]
+    pub categories: Vec<String>,
+    #[prost(uint64, tag = "9")]
+    pub payload_len: u64,
+    #[prost(bytes = "vec", tag = "10")]
+    pub payload_digest: Vec<u8>,
+    #[prost(message, repeated, tag = "11")]
+    pub entries: Vec<ProtoTocEntry>,
+}
+
+/// 目录项
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoTocEntry {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, tag = "3")]
+    pub risk: String,
+    #[prost(string, tag = "4")]
+    pub category: String,
+    #[prost(uint64, tag = "5")]
+    pub offset: u64,
+    #[prost(uint64, tag = "6")]
+    pub length: u64,
+    #[prost(bytes = "vec", tag = "7")]
+    pub digest: Vec<u8>,
+}
+
+/// 规则
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoRule {
+    #[prost(message, optional, tag = "1")]
+    pub metadata: Option<ProtoMetadata>,
+    #[prost(string, tag = "2")]
+    pub yaml_content: String,
+    #[prost(string, repeated, tag = "3")]
+    pub paths: Vec<String>,
+    #[prost(message, repeated, tag = "4")]
+    pub registry_entries: Vec<ProtoRegistryEntry>,
+}
+
+/// 规则元数据
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoMetadata {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, tag = "3")]
+    pub risk: String,
+    #[prost(string, repeated, tag = "4")]
+    pub systeminfo: Vec<String>,
+    #[prost(string, tag = "5")]
+    pub update: String,
+    #[prost(string, optional, tag = "6")]
+    pub author: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub description: Option<String>,
+    #[prost(string, tag = "8")]
+    pub category: String,
+    #[prost(string, tag = "9")]
+    pub filename: String,
+}
+
+/// 注册表条目
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ProtoRegistryEntry {
+    #[prost(string, tag = "1")]
+    pub path: String,
+    #[prost(string, tag = "2")]
+    pub key: String,
+    #[prost(string, optional, tag = "3")]
+    pub value: Option<String>,
+    #[prost(string, optional, tag = "4")]
+    pub value_data: Option<String>,
+    #[prost(string, tag = "5")]
+    pub action: String,
+}
+
+impl From<&PackageHeader> for ProtoHeader {
+    fn from(header: &PackageHeader) -> Self {
+        ProtoHeader {
+            version: header.version,
+            serial: header.serial,
+            created_at: header.created_at,
+            expires_at: header.expires_at,
+            rule_count: header.rule_count as u64,
+            compression: header.compression.clone(),
+            encoding: header.encoding.clone(),
+            categories: header.categories.clone(),
+            payload_len: header.payload_len,
+            payload_digest: header.payload_digest.to_vec(),
+            entries: header.entries.iter().map(ProtoTocEntry::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<ProtoHeader> for PackageHeader {
+    type Error = String;
+
+    fn try_from(header: ProtoHeader) -> Result<Self, Self::Error> {
+        Ok(PackageHeader {
+            version: header.version,
+            serial: header.serial,
+            created_at: header.created_at,
+            expires_at: header.expires_at,
+            rule_count: usize::try_from(header.rule_count).map_err(|e| format!("rule_count: {}", e))?,
+            compression: header.compression,
+            encoding: header.encoding,
+            categories: header.categories,
+            payload_len: header.payload_len,
+            payload_digest: digest("payload_digest", &header.payload_digest)?,
+            entries: header.entries.into_iter().map(TocEntry::try_from).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl From<&TocEntry> for ProtoTocEntry {
+    fn from(entry: &TocEntry) -> Self {
+        ProtoTocEntry {
+            id: entry.id.clone(),
+            name: entry.name.clone(),
+            risk: entry.risk.clone(),
+            category: entry.category.clone(),
+            offset: entry.offset,
+            length: entry.length,
+            digest: entry.digest.to_vec(),
+        }
+    }
+}
+
+impl TryFrom<ProtoTocEntry> for TocEntry {
+    type Error = String;
+
+    fn try_from(entry: ProtoTocEntry) -> Result<Self, Self::Error> {
+        Ok(TocEntry {
+            digest: digest("digest", &entry.digest)?,
+            id: entry.id,
+            name: entry.name,
+            risk: entry.risk,
+            category: entry.category,
+            offset: entry.offset,
+            length: entry.length,
+        })
+    }
+}
+
+impl From<&SerializedRule> for ProtoRule {
+    fn from(rule: &SerializedRule) -> Self {
+        let metadata = &rule.metadata;
+        ProtoRule {
+            metadata: Some(ProtoMetadata {
+                id: metadata.id.clone(),
+                name: metadata.name.clone(),
+                risk: metadata.risk.clone(),
+                systeminfo: metadata.systeminfo.clone(),
+                update: metadata.update.clone(),
+                author: metadata.author.clone(),
+                description: metadata.description.clone(),
+                category: metadata.category.clone(),
+                filename: metadata.filename.clone(),
+            }),
+            yaml_content: rule.yaml_content.clone(),
+            paths: rule.paths.clone(),
+            registry_entries: rule
+                .registry_entries
+                .iter()
+                .map(|entry| ProtoRegistryEntry {
+                    path: entry.path.clone(),
+                    key: entry.key.clone(),
+                    value: entry.value.clone(),
+                    value_data: entry.value_data.clone(),
+                    action: entry.action.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<ProtoRule> for SerializedRule {
+    type Error = String;
+
+    fn try_from(rule: ProtoRule) -> Result<Self, Self::Error> {
+        let metadata = rule.metadata.ok_or("缺少 metadata")?;
+        Ok(SerializedRule {
+            metadata: RuleMetadata {
+                id: metadata.id,
+                name: metadata.name,
+                risk: metadata.risk,
+                systeminfo: metadata.systeminfo,
+                update: metadata.update,
+                author: metadata.author,
+                description: metadata.description,
+                category: metadata.category,
+                filename: metadata.filename,
+            },
+            yaml_content: rule.yaml_content,
+            paths: rule.paths,
+            registry_entries: rule
+                .registry_entries
+                .into_iter()
+                .map(|entry| RegistryEntry {
+                    path: entry.path,
+                    key: entry.key,
+                    value: entry.value,
+                    value_data: entry.value_data,
+                    action: entry.action,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// 检查列表长度后解码包头
+pub fn decode_header(data: &[u8], limits: &Limits) -> Result<PackageHeader, PackageError> {
+    let (categories, _) = scan_field(data, 8).map_err(PackageError::InvalidHeader)?;
+    limits.check_vec("包头", categories)?;
+    let (entries, _) = scan_field(data, 11).map_err(PackageError::InvalidHeader)?;
+    Limits::check(|| "规则数量".to_string(), entries as u64, limits.max_rules as u64)?;
+
+    let header = ProtoHeader::decode(data).map_err(|e| PackageError::InvalidHeader(e.to_string()))?;
+    PackageHeader::try_from(header).map_err(PackageError::InvalidHeader)
+}
+
+/// 检查列表长度后解码规则
+pub fn decode_rule(id: &str, data: &[u8], limits: &Limits) -> Result<SerializedRule, PackageError> {
+    let invalid = |message: String| PackageError::InvalidRule { id: id.to_string(), message };
+    let owner = format!("规则 {} ", id);
+    for tag in [3, 4] {
+        let (len, _) = scan_field(data, tag).map_err(invalid)?;
+        limits.check_vec(&owner, len)?;
+    }
+    let (count, metadata) = scan_field(data, 1).map_err(invalid)?;
+    if count > 1 {
+        return Err(invalid("metadata 重复".to_string()));
+    }
+    let (systeminfo, _) = scan_field(metadata, 4).map_err(invalid)?;
+    limits.check_vec(&owner, systeminfo)?;
+
+    let rule = ProtoRule::decode(data).map_err(|e| invalid(e.to_string()))?;
+    SerializedRule::try_from(rule).map_err(invalid)
+}
+
+/// 统计消息顶层字段 `tag` 出现的次数，并返回最后一次出现时的内容
+fn scan_field(mut data: &[u8], tag: u64) -> Result<(usize, &[u8]), String> {
+    let mut count = 0;
+    let mut last: &[u8] = &[];
+    while !data.is_empty() {
+        let key = decode_varint(&mut data).map_err(|e| e.to_string())?;
+        let len = match key & 7 {
+            0 => {
+                decode_varint(&mut data).map_err(|e| e.to_string())?;
+                0
+            }
+            1 => 8,
+            2 => usize::try_from(decode_varint(&mut data).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?,
+            5 => 4,
+            wire_type => return Err(format!("字段 {} 的类型 {} 无效", key >> 3, wire_type)),
+        };
+        if len > data.len() {
+            return Err(format!("字段 {} 不完整", key >> 3));
+        }
+        let (value, rest) = data.split_at(len);
+        if key >> 3 == tag {
+            count += 1;
+            last = value;
+        }
+        data = rest;
+    }
+    Ok((count, last))
+}
+
+fn digest(field: &str, bytes: &[u8]) -> Result<Digest, String> {
+    bytes
+        .try_into()
+        .map_err(|_| format!("{} 应为 {} 字节，实际为 {} 字节", field, package::DIGEST_LEN, bytes.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(paths: usize) -> ProtoRule {
+        ProtoRule {
+            metadata: Some(ProtoMetadata {
+                id: "alpha".to_string(),
+                systeminfo: vec!["Windows 11".to_string(); 3],
+                ..ProtoMetadata::default()
+            }),
+            yaml_content: "id: alpha\n".to_string(),
+            paths: vec!["%TEMP%\\alpha".to_string(); paths],
+            registry_entries: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn scan_field_counts_top_level_fields() {
+        let data = rule(4).encode_to_vec();
+        assert_eq!(scan_field(&data, 3).unwrap(), (4, "%TEMP%\\alpha".as_bytes()));
+        assert_eq!(scan_field(&data, 4).unwrap().0, 0);
+        // metadata 中的 systeminfo 不属于顶层字段
+        let (count, metadata) = scan_field(&data, 1).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(scan_field(metadata, 4).unwrap().0, 3);
+
+        let header = ProtoHeader { serial: 1, expires_at: Some(2), payload_digest: vec![0; 32], ..Default::default() };
+        assert_eq!(scan_field(&header.encode_to_vec(), 10).unwrap(), (1, &[0u8; 32][..]));
+        assert_eq!(scan_field(&[], 1).unwrap().0, 0);
+    }
+
+    #[test]
+    fn scan_field_rejects_malformed_data() {
+        let data = rule(1).encode_to_vec();
+        assert!(scan_field(&data[..data.len() - 1], 3).is_err());
+        // 长度前缀超出剩余数据
+        assert!(scan_field(&[0x1a, 0xff, 0xff, 0xff, 0xff, 0x0f], 3).is_err());
+        // 无效的 wire type
+        assert!(scan_field(&[0x1f], 3).is_err());
+        // 未结束的 varint
+        assert!(scan_field(&[0x08, 0x80], 1).is_err());
+    }
+
+    #[test]
+    fn decode_rule_checks_list_lengths_first() {
+        let limits = Limits { max_vec_len: 4, ..Limits::default() };
+        assert_eq!(decode_rule("alpha", &rule(4).encode_to_vec(), &limits).unwrap().paths.len(), 4);
+        assert!(matches!(
+            decode_rule("alpha", &rule(5).encode_to_vec(), &limits),
+            Err(PackageError::LimitExceeded { .. })
+        ));
+
+        let mut long = rule(0);
+        long.metadata.as_mut().unwrap().systeminfo = vec![String::new(); 5];
+        assert!(matches!(
+            decode_rule("alpha", &long.encode_to_vec(), &limits),
+            Err(PackageError::LimitExceeded { .. })
+        ));
+
+        // 重复的 metadata 在 protobuf 中会被合并，预检查只看到最后一个，因此直接拒绝
+        let mut data = long.encode_to_vec();
+        data.extend_from_slice(&rule(0).encode_to_vec());
+        assert!(matches!(decode_rule("alpha", &data, &limits), Err(PackageError::InvalidRule { .. })));
+
+        assert!(matches!(
+            decode_rule("alpha", &ProtoRule::default().encode_to_vec(), &limits),
+            Err(PackageError::InvalidRule { .. })
+        ));
+    }
+
+    #[test]
+    fn decode_header_checks_list_lengths_first() {
+        let entry = ProtoTocEntry { id: "alpha".to_string(), digest: vec![0; 32], ..Default::default() };
+        let header = |entries: usize, categories: usize| ProtoHeader {
+            payload_digest: vec![0; 32],
+            categories: vec!["apps".to_string(); categories],
+            entries: vec![entry.clone(); entries],
+            ..Default::default()
+        };
+        let limits = Limits { max_rules: 2, max_vec_len: 2, ..Limits::default() };
+
+        assert_eq!(decode_header(&header(2, 2).encode_to_vec(), &limits).unwrap().entries.len(), 2);
+        for (entries, categories) in [(3, 0), (0, 3)] {
+            assert!(matches!(
+                decode_header(&header(entries, categories).encode_to_vec(), &limits),
+                Err(PackageError::LimitExceeded { .. })
+            ));
+        }
+
+        let short = ProtoHeader { payload_digest: vec![0; 31], ..header(0, 0) };
+        assert!(matches!(decode_header(&short.encode_to_vec(), &limits), Err(PackageError::InvalidHeader(_))));
+    }
+}
@@ -0,0 +1,428 @@
+//! 注册表匹配
+//! 定义 `match.registry` 规则在注册表树上的匹配语义
+//!
+//! - `path`：父键路径，各级键名忽略大小写按字面匹配，`<...>` 为正则段；
+//!   `*` 在路径中没有通配含义（`HKEY_CLASSES_ROOT\*` 是真实存在的键）。
+//!   根键可以写全称或缩写（`HKCR`、`HKCU`、`HKLM`、`HKU`、`HKCC`）。
+//! - `key`：`path` 下的子键名，`*` 匹配任意字符序列。
+//! - `value` / `value_data`：值名与值数据，`*` 匹配任意字符序列；
+//!   未填写等同于 `*`。值数据按 [`RegistryData::to_match_text`] 转为文本后比较。
+//!
+//! 三种操作的作用对象：
+//! - `delete_key`：删除匹配的子键；若 `value`/`value_data` 不是 `*`，
+//!   则只删除含有匹配值的子键。
+//! - `delete_value`：删除匹配子键下的匹配值。
+//! - `delete_value_data`：清空匹配值的数据，保留值本身。
+
+use crate::pattern::{regex_error_message, PathPattern, PatternError};
+use crate::rule::{RegistryAction, RegistryRule};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+/// 根键缩写与全称
+const ROOT_ALIASES: &[(&str, &str)] = &[
+    ("HKCR", "HKEY_CLASSES_ROOT"),
+    ("HKCU", "HKEY_CURRENT_USER"),
+    ("HKLM", "HKEY_LOCAL_MACHINE"),
+    ("HKU", "HKEY_USERS"),
+    ("HKCC", "HKEY_CURRENT_CONFIG"),
+];
+
+/// 将根键缩写转换为全称，非缩写原样返回
+pub fn canonical_root(name: &str) -> &str {
+    ROOT_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(name))
+        .map(|(_, full)| *full)
+        .unwrap_or(name)
+}
+
+/// 注册表值数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryData {
+    None,
+    String(String),
+    ExpandString(String),
+    MultiString(Vec<String>),
+    Dword(u32),
+    Qword(u64),
+    Binary(Vec<u8>),
+}
+
+impl RegistryData {
+    /// 用于 `value_data` 匹配的文本形式
+    ///
+    /// 字符串原样返回，多字符串以换行连接，整数为十进制，二进制为小写十六进制。
+    pub fn to_match_text(&self) -> String {
+        match self {
+            RegistryData::None => String::new(),
+            RegistryData::String(s) | RegistryData::ExpandString(s) => s.clone(),
+            RegistryData::MultiString(items) => items.join("\n"),
+            RegistryData::Dword(n) => n.to_string(),
+            RegistryData::Qword(n) => n.to_string(),
+            RegistryData::Binary(bytes) => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+}
+
+/// 注册表值
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryValue {
+    /// 值名，默认值为空字符串
+    pub name: String,
+    pub data: RegistryData,
+}
+
+/// 注册表树
+///
+/// 键路径以 `\` 连接，首段为根键全称；空路径表示顶层，其子键即各根键。
+/// 键不存在时返回空列表而不是错误。
+pub trait RegistryTree {
+    /// 列出子键名
+    fn subkeys(&self, path: &str) -> io::Result<Vec<String>>;
+
+    /// 列出键下的值
+    fn values(&self, path: &str) -> io::Result<Vec<RegistryValue>>;
+}
+
+/// 匹配结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryTarget {
+    /// 整个键（含子键与值）
+    Key { path: String },
+    /// 单个值
+    Value { key: String, name: String },
+    /// 值的数据
+    ValueData { key: String, name: String, data: RegistryData },
+}
+
+impl fmt::Display for RegistryTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryTarget::Key { path } => write!(f, "{}", path),
+            RegistryTarget::Value { key, name } => write!(f, "{} -> {}", key, display_name(name)),
+            RegistryTarget::ValueData { key, name, data } => {
+                write!(f, "{} -> {} = {}", key, display_name(name), data.to_match_text())
+            }
+        }
+    }
+}
+
+fn display_name(name: &str) -> &str {
+    if name.is_empty() {
+        "(默认)"
+    } else {
+        name
+    }
+}
+
+/// `*` 通配符（忽略大小写）
+#[derive(Debug, Clone)]
+struct Wildcard {
+    regex: Regex,
+    any: bool,
+}
+
+impl Wildcard {
+    /// 转义后的文本只会产生合法正则，但过长的通配符会超出正则的大小上限
+    fn new(pattern: &str) -> Result<Self, PatternError> {
+        let source = pattern.split('*').map(regex::escape).collect::<Vec<_>>().join(".*");
+        let regex = Regex::new(&format!("(?is)^{}$", source)).map_err(|e| PatternError::InvalidWildcard {
+            pattern: pattern.to_string(),
+            message: regex_error_message(&e),
+        })?;
+        Ok(Wildcard { regex, any: pattern.chars().all(|c| c == '*') && !pattern.is_empty() })
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        self.any || self.regex.is_match(text)
+    }
+}
+
+/// 单条注册表规则的匹配器
+#[derive(Debug, Clone)]
+pub struct RegistryMatcher {
+    path: PathPattern,
+    key: Wildcard,
+    value: Option<Wildcard>,
+    value_data: Option<Wildcard>,
+    action: RegistryAction,
+}
+
+impl RegistryMatcher {
+    pub fn new(rule: &RegistryRule) -> Result<Self, PatternError> {
+        // 根键缩写统一为全称
+        let (root, rest) = rule.path.split_once('\\').unwrap_or((&rule.path, ""));
+        let path = format!("{}\\{}", canonical_root(root), rest);
+
+        let condition = |pattern: &Option<String>| -> Result<Option<Wildcard>, PatternError> {
+            Ok(pattern.as_deref().map(Wildcard::new).transpose()?.filter(|w| !w.any))
+        };
+
+        Ok(RegistryMatcher {
+            path: PathPattern::parse_registry(&path)?,
+            key: Wildcard::new(&rule.key)?,
+            value: condition(&rule.value)?,
+            value_data: condition(&rule.value_data)?,
+            action: rule.action,
+        })
+    }
+
+    pub fn action(&self) -> RegistryAction {
+        self.action
+    }
+
+    /// 在注册表树上查找该规则作用的对象
+    pub fn find<T: RegistryTree + ?Sized>(&self, tree: &T) -> io::Result<Vec<RegistryTarget>> {
+        let mut targets = Vec::new();
+
+        for parent in self.parents(tree)? {
+            for name in tree.subkeys(&parent)? {
+                if !self.key.matches(&name) {
+                    continue;
+                }
+                let key = format!("{}\\{}", parent, name);
+                let values: Vec<RegistryValue> = tree
+                    .values(&key)?
+                    .into_iter()
+                    .filter(|v| self.value_matches(v))
+                    .collect();
+
+                match self.action {
+                    RegistryAction::DeleteKey => {
+                        let conditional = self.value.is_some() || self.value_data.is_some();
+                        if !conditional || !values.is_empty() {
+                            targets.push(RegistryTarget::Key { path: key });
+                        }
+                    }
+                    RegistryAction::DeleteValue => {
+                        targets.extend(values.into_iter().map(|v| RegistryTarget::Value {
+                            key: key.clone(),
+                            name: v.name,
+                        }));
+                    }
+                    RegistryAction::DeleteValueData => {
+                        targets.extend(values.into_iter().map(|v| RegistryTarget::ValueData {
+                            key: key.clone(),
+                            name: v.name,
+                            data: v.data,
+                        }));
+                    }
+                }
+            }
+        }
+
+        Ok(targets)
+    }
+
+    fn value_matches(&self, value: &RegistryValue) -> bool {
+        self.value.as_ref().is_none_or(|w| w.matches(&value.name))
+            && self.value_data.as_ref().is_none_or(|w| w.matches(&value.data.to_match_text()))
+    }
+
+    /// 逐级展开 `path`，返回所有匹配的父键
+    fn parents<T: RegistryTree + ?Sized>(&self, tree: &T) -> io::Result<Vec<String>> {
+        let mut current = vec![String::new()];
+
+        for (depth, component) in self.path.components().iter().enumerate() {
+            let mut next = Vec::new();
+            for parent in &current {
+                for name in tree.subkeys(parent)? {
+                    let matched = if depth == 0 {
+                        component.matches(canonical_root(&name))
+                    } else {
+                        component.matches(&name)
+                    };
+                    if matched {
+                        next.push(join_key(parent, &name));
+                    }
+                }
+            }
+            current = next;
+        }
+
+        Ok(current)
+    }
+}
+
+fn join_key(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}\\{}", parent, name)
+    }
+}
+
+/// 内存中的注册表树，用于测试与模拟
+#[derive(Debug, Clone, Default)]
+pub struct MemoryRegistry {
+    root: MemoryKey,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MemoryKey {
+    name: String,
+    /// 以小写键名索引
+    subkeys: BTreeMap<String, MemoryKey>,
+    values: Vec<RegistryValue>,
+}
+
+impl MemoryRegistry {
+    pub fn new() -> Self {
+        MemoryRegistry::default()
+    }
+
+    /// 创建键（含所有上级键），根键缩写会转换为全称
+    pub fn insert_key(&mut self, path: &str) {
+        self.key_mut(path);
+    }
+
+    /// 设置值，键不存在时自动创建
+    pub fn set_value(&mut self, path: &str, name: &str, data: RegistryData) {
+        let key = self.key_mut(path);
+        match key.values.iter_mut().find(|v| v.name.to_lowercase() == name.to_lowercase()) {
+            Some(value) => value.data = data,
+            None => key.values.push(RegistryValue { name: name.to_string(), data }),
+        }
+    }
+
+    fn key_mut(&mut self, path: &str) -> &mut MemoryKey {
+        let mut key = &mut self.root;
+        for (depth, name) in path.split('\\').filter(|s| !s.is_empty()).enumerate() {
+            let name = if depth == 0 { canonical_root(name) } else { name };
+            key = key.subkeys.entry(name.to_lowercase()).or_insert_with(|| MemoryKey {
+                name: name.to_string(),
+                ..MemoryKey::default()
+            });
+        }
+        key
+    }
+
+    fn key(&self, path: &str) -> Option<&MemoryKey> {
+        let mut key = &self.root;
+        for (depth, name) in path.split('\\').filter(|s| !s.is_empty()).enumerate() {
+            let name = if depth == 0 { canonical_root(name) } else { name };
+            key = key.subkeys.get(&name.to_lowercase())?;
+        }
+        Some(key)
+    }
+}
+
+impl RegistryTree for MemoryRegistry {
+    fn subkeys(&self, path: &str) -> io::Result<Vec<String>> {
+        Ok(self
+            .key(path)
+            .map(|key| key.subkeys.values().map(|k| k.name.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    fn values(&self, path: &str) -> io::Result<Vec<RegistryValue>> {
+        Ok(self.key(path).map(|key| key.values.clone()).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> MemoryRegistry {
+        let mut registry = MemoryRegistry::new();
+        let handlers = r"HKCR\*\shellex\ContextMenuHandlers";
+        registry.set_value(&format!(r"{}\SoftMgrOEMExt", handlers), "", RegistryData::String("{A1B2}".into()));
+        registry.set_value(&format!(r"{}\Other", handlers), "", RegistryData::String("{C3D4}".into()));
+        registry.set_value(r"HKCU\Software\SoftMgr", "InstallDir", RegistryData::String(r"C:\SoftMgr".into()));
+        registry.set_value(r"HKCU\Software\SoftMgr", "Flags", RegistryData::Dword(1));
+        registry.set_value(r"HKCU\Software\SoftMgrCache", "Flags", RegistryData::Dword(2));
+        registry.set_value(r"HKCU\Software\Unrelated", "Flags", RegistryData::Dword(1));
+        registry
+    }
+
+    fn find(
+        path: &str,
+        key: &str,
+        value: Option<&str>,
+        value_data: Option<&str>,
+        action: RegistryAction,
+    ) -> Vec<String> {
+        let rule = RegistryRule {
+            path: path.to_string(),
+            key: key.to_string(),
+            value: value.map(str::to_string),
+            value_data: value_data.map(str::to_string),
+            action,
+        };
+        let targets = RegistryMatcher::new(&rule).unwrap().find(&registry()).unwrap();
+        targets.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn star_in_path_is_literal_and_wildcard_in_key() {
+        assert_eq!(
+            find(r"HKCR\*\shellex\ContextMenuHandlers", "softmgr*", None, None, RegistryAction::DeleteKey),
+            [r"HKEY_CLASSES_ROOT\*\shellex\ContextMenuHandlers\SoftMgrOEMExt"]
+        );
+        // 路径中的 `*` 只匹配名为 `*` 的键
+        assert!(find(r"HKCU\*", "SoftMgr", None, None, RegistryAction::DeleteKey).is_empty());
+        assert_eq!(find(r"HKEY_CURRENT_USER\software", "*", None, None, RegistryAction::DeleteKey).len(), 3);
+        assert_eq!(
+            find(r"hkcu\<Soft.*>", "SoftMgr", None, None, RegistryAction::DeleteKey),
+            [r"HKEY_CURRENT_USER\Software\SoftMgr"]
+        );
+    }
+
+    #[test]
+    fn delete_key_with_value_condition_requires_a_matching_value() {
+        assert_eq!(
+            find(r"HKCU\Software", "SoftMgr*", Some("installdir"), None, RegistryAction::DeleteKey),
+            [r"HKEY_CURRENT_USER\Software\SoftMgr"]
+        );
+        assert_eq!(
+            find(r"HKCU\Software", "*", Some("Flags"), Some("1"), RegistryAction::DeleteKey),
+            [r"HKEY_CURRENT_USER\Software\SoftMgr", r"HKEY_CURRENT_USER\Software\Unrelated"]
+        );
+        // 全为 `*` 的条件等同于未填写
+        assert_eq!(find(r"HKCU\Software", "SoftMgr*", Some("*"), None, RegistryAction::DeleteKey).len(), 2);
+    }
+
+    #[test]
+    fn deletes_values_and_value_data() {
+        assert_eq!(
+            find(r"HKCU\Software", "SoftMgr", None, None, RegistryAction::DeleteValue),
+            [r"HKEY_CURRENT_USER\Software\SoftMgr -> InstallDir", r"HKEY_CURRENT_USER\Software\SoftMgr -> Flags"]
+        );
+        assert_eq!(
+            find(r"HKCU\Software", "SoftMgr*", Some("fl*"), Some("2"), RegistryAction::DeleteValueData),
+            [r"HKEY_CURRENT_USER\Software\SoftMgrCache -> Flags = 2"]
+        );
+        assert_eq!(
+            find(r"HKCR\*\shellex\ContextMenuHandlers", "*", Some(""), Some("{a1*}"), RegistryAction::DeleteValue),
+            [r"HKEY_CLASSES_ROOT\*\shellex\ContextMenuHandlers\SoftMgrOEMExt -> (默认)"]
+        );
+    }
+
+    #[test]
+    fn wildcard_escapes_regex_syntax() {
+        let wildcard = Wildcard::new("a.b*(c)").unwrap();
+        assert!(wildcard.matches("A.B-anything-(C)"));
+        assert!(!wildcard.matches("axb(c)"));
+        assert!(Wildcard::new("").unwrap().matches(""));
+        assert!(!Wildcard::new("").unwrap().matches("x"));
+        assert!(Wildcard::new("multi\nline*").unwrap().matches("MULTI\nLINE\nmore"));
+    }
+
+    #[test]
+    fn oversized_wildcard_is_an_error() {
+        let rule = RegistryRule {
+            path: r"HKCU\Software".to_string(),
+            key: "ab".repeat(1 << 17),
+            value: None,
+            value_data: None,
+            action: RegistryAction::DeleteKey,
+        };
+        let error = RegistryMatcher::new(&rule).unwrap_err();
+        assert!(matches!(error, PatternError::InvalidWildcard { .. }));
+        assert!(error.to_string().len() < 512);
+    }
+}
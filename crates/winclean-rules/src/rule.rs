@@ -0,0 +1,382 @@
+//! 规则文件结构定义
+//! YAML规则的类型模型，打包、解包、校验共用
+
+use crate::diagnostic::{FieldPath, Issue};
+use crate::pattern;
+use crate::time;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// YAML规则文件
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub id: String,
+    pub name: String,
+    pub risk: RiskLevel,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub systeminfo: Vec<String>,
+    pub update: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "match", default)]
+    pub matches: MatchSection,
+    #[serde(default, skip_serializing_if = "RuleTests::is_empty")]
+    pub tests: RuleTests,
+}
+
+/// 匹配规则
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MatchSection {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub registry: Vec<RegistryRule>,
+}
+
+/// 注册表匹配规则
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryRule {
+    pub path: String,
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_data: Option<String>,
+    pub action: RegistryAction,
+}
+
+/// 规则自带的测试样例
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RuleTests {
+    /// 文件路径样例，写法与 `match.path` 相同（可使用环境变量前缀）
+    #[serde(default, skip_serializing_if = "SampleSet::is_empty")]
+    pub path: SampleSet<String>,
+    /// 注册表样例
+    #[serde(default, skip_serializing_if = "SampleSet::is_empty")]
+    pub registry: SampleSet<RegistrySample>,
+}
+
+/// 应当匹配与不应匹配的样例
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, bound(deserialize = "T: Deserialize<'de>"))]
+pub struct SampleSet<T> {
+    #[serde(rename = "match", default, skip_serializing_if = "Vec::is_empty")]
+    pub matches: Vec<T>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_match: Vec<T>,
+}
+
+/// 注册表样例：单独的键路径，或键下的某个值
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum RegistrySample {
+    Key(String),
+    Value(RegistryValueSample),
+}
+
+/// 注册表值样例
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryValueSample {
+    pub key: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+impl RuleTests {
+    pub fn is_empty(&self) -> bool {
+        self.path.is_empty() && self.registry.is_empty()
+    }
+}
+
+impl<T> SampleSet<T> {
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty() && self.no_match.is_empty()
+    }
+}
+
+impl<T> Default for SampleSet<T> {
+    fn default() -> Self {
+        SampleSet { matches: Vec::new(), no_match: Vec::new() }
+    }
+}
+
+impl RegistrySample {
+    /// 样例所在的键路径
+    pub fn key(&self) -> &str {
+        match self {
+            RegistrySample::Key(key) => key,
+            RegistrySample::Value(sample) => &sample.key,
+        }
+    }
+}
+
+impl fmt::Display for RegistrySample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistrySample::Key(key) => f.write_str(key),
+            RegistrySample::Value(sample) => {
+                let name = if sample.value.is_empty() { "(默认)" } else { &sample.value };
+                match &sample.data {
+                    Some(data) => write!(f, "{} -> {} = {}", sample.key, name, data),
+                    None => write!(f, "{} -> {}", sample.key, name),
+                }
+            }
+        }
+    }
+}
+
+/// 风险等级
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    High,
+    Default,
+}
+
+/// 注册表操作（变体名与YAML取值一一对应）
+#[allow(clippy::enum_variant_names)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryAction {
+    DeleteKey,
+    DeleteValue,
+    DeleteValueData,
+}
+
+impl RiskLevel {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            RiskLevel::High => "high",
+            RiskLevel::Default => "default",
+        }
+    }
+}
+
+impl fmt::Display for RiskLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RiskLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "high" => Ok(RiskLevel::High),
+            "default" => Ok(RiskLevel::Default),
+            _ => anyhow::bail!("未知的风险等级: {}", s),
+        }
+    }
+}
+
+impl RegistryAction {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            RegistryAction::DeleteKey => "delete_key",
+            RegistryAction::DeleteValue => "delete_value",
+            RegistryAction::DeleteValueData => "delete_value_data",
+        }
+    }
+}
+
+impl fmt::Display for RegistryAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RegistryAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "delete_key" => Ok(RegistryAction::DeleteKey),
+            "delete_value" => Ok(RegistryAction::DeleteValue),
+            "delete_value_data" => Ok(RegistryAction::DeleteValueData),
+            _ => anyhow::bail!("未知的注册表操作: {}", s),
+        }
+    }
+}
+
+impl Rule {
+    /// 检查字段取值，返回全部问题
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let root = FieldPath::default;
+
+        if !is_valid_id(&self.id) {
+            issues.push(Issue::error(
+                root().key("id"),
+                format!("id `{}` 不合法: 只能包含小写字母、数字和下划线", self.id),
+            ));
+        }
+        if self.name.trim().is_empty() {
+            issues.push(Issue::error(root().key("name"), "name 不能为空"));
+        }
+        if !is_valid_date(&self.update) {
+            issues.push(Issue::error(
+                root().key("update"),
+                format!("update `{}` 不合法: 日期格式应为 YYYY-MM-DD", self.update),
+            ));
+        }
+
+        let paths = root().key("match").key("path");
+        for (i, path) in self.matches.path.iter().enumerate() {
+            let field = paths.clone().index(i);
+            if path.trim().is_empty() {
+                issues.push(Issue::error(field.clone(), format!("match.path[{}] 不能为空", i)));
+            }
+            for error in pattern::check(path) {
+                issues.push(Issue::error(field.clone(), format!("match.path[{}]: {}", i, error)));
+            }
+            if self.matches.path[..i].contains(path) {
+                issues.push(Issue::warning(field, format!("match.path[{}] 与前面的路径重复", i)));
+            }
+        }
+
+        let registry = root().key("match").key("registry");
+        for (i, entry) in self.matches.registry.iter().enumerate() {
+            let field = registry.clone().index(i);
+            if entry.path.trim().is_empty() {
+                issues.push(Issue::error(
+                    field.clone().key("path"),
+                    format!("match.registry[{}].path 不能为空", i),
+                ));
+            }
+            for error in pattern::check(&entry.path) {
+                issues.push(Issue::error(
+                    field.clone().key("path"),
+                    format!("match.registry[{}].path: {}", i, error),
+                ));
+            }
+            if entry.key.trim().is_empty() {
+                issues.push(Issue::error(
+                    field.clone().key("key"),
+                    format!("match.registry[{}].key 不能为空", i),
+                ));
+            }
+            if let Some(first) = self.matches.registry[..i].iter().position(|e| e == entry) {
+                issues.push(Issue::warning(
+                    field,
+                    format!("match.registry[{}] 与 match.registry[{}] 完全相同", i, first),
+                ));
+            }
+        }
+
+        let tests = root().key("tests");
+        let path_sets = [("match", &self.tests.path.matches), ("no_match", &self.tests.path.no_match)];
+        for (name, samples) in path_sets {
+            for (i, sample) in samples.iter().enumerate() {
+                if sample.trim().is_empty() {
+                    issues.push(Issue::error(
+                        tests.clone().key("path").key(name).index(i),
+                        format!("tests.path.{}[{}] 不能为空", name, i),
+                    ));
+                }
+            }
+        }
+        let registry_sets =
+            [("match", &self.tests.registry.matches), ("no_match", &self.tests.registry.no_match)];
+        for (name, samples) in registry_sets {
+            for (i, sample) in samples.iter().enumerate() {
+                if sample.key().trim().is_empty() {
+                    issues.push(Issue::error(
+                        tests.clone().key("registry").key(name).index(i),
+                        format!("tests.registry.{}[{}] 的键路径不能为空", name, i),
+                    ));
+                }
+            }
+        }
+
+        issues
+    }
+}
+
+/// id 只允许小写字母、数字、下划线
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// 日期格式 YYYY-MM-DD
+fn is_valid_date(date: &str) -> bool {
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3
+        || parts[0].len() != 4
+        || parts[1].len() != 2
+        || parts[2].len() != 2
+        || !parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit()))
+    {
+        return false;
+    }
+
+    let year: i64 = parts[0].parse().unwrap_or(0);
+    let month: u32 = parts[1].parse().unwrap_or(0);
+    let day: u32 = parts[2].parse().unwrap_or(0);
+    (1..=12).contains(&month) && (1..=time::days_in_month(year, month)).contains(&day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(update: &str) -> Rule {
+        let yaml = format!("id: demo\nname: 演示\nrisk: high\nupdate: {}\n", update);
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    #[test]
+    fn dates_respect_month_length() {
+        for date in ["2026-01-31", "2026-02-28", "2024-02-29", "2000-02-29", "2026-04-30", "2026-12-31"] {
+            assert!(is_valid_date(date), "{}", date);
+        }
+        for date in [
+            "2026-02-29", "1900-02-29", "2026-02-31", "2026-04-31", "2026-06-31", "2026-09-31", "2026-11-31",
+            "2026-13-01", "2026-00-10", "2026-01-00", "2026-1-01", "26-01-01", "2026/01/01", "2026-01-0a",
+        ] {
+            assert!(!is_valid_date(date), "{}", date);
+        }
+    }
+
+    #[test]
+    fn validate_reports_impossible_date() {
+        assert!(rule("2026-02-28").validate().is_empty());
+
+        let issues = rule("2026-02-31").validate();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, FieldPath::default().key("update"));
+        assert_eq!(issues[0].message, "update `2026-02-31` 不合法: 日期格式应为 YYYY-MM-DD");
+    }
+
+    #[test]
+    fn validate_collects_every_issue() {
+        let yaml = "\
+id: Bad-Id
+name: \" \"
+risk: default
+update: 2026-04-31
+match:
+  path:
+    - \"\"
+  registry:
+    - path: \"HKCU\\\\Software\"
+      key: \"\"
+      action: delete_key
+";
+        let rule: Rule = serde_yaml::from_str(yaml).unwrap();
+        let fields: Vec<String> = rule.validate().iter().map(|issue| issue.field.to_string()).collect();
+        assert_eq!(fields, ["id", "name", "update", "match.path[0]", "match.registry[0].key"]);
+    }
+}
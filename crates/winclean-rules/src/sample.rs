@@ -0,0 +1,248 @@
+//! 规则测试
+//! 用规则自带的 `tests` 样例检验 `match.path` 与 `match.registry` 的匹配结果
+//!
+//! 路径样例与规则路径都按同一组环境变量（[`WindowsEnv::for_user`]，用户名为
+//! [`SAMPLE_USER`]）展开后比较，因此 `%TEMP%\x` 与 `%LOCALAPPDATA%\Temp\x` 视为同一路径；
+//! 未定义的变量按字面比较。
+//!
+//! 注册表样例放进只含该样例的 [`MemoryRegistry`] 中运行匹配器：
+//! - 键样例：被某条规则删除（或位于被删除的键之下）即为命中；
+//! - 值样例：值本身被删除或清空，或所在的键被删除即为命中。
+
+use crate::filesystem::WindowsEnv;
+use crate::pattern::{PathPattern, PatternError};
+use crate::registry::{canonical_root, MemoryRegistry, RegistryData, RegistryMatcher, RegistryTarget};
+use crate::rule::{RegistrySample, Rule};
+use serde::Serialize;
+use std::fmt;
+
+/// 展开路径样例时使用的用户名
+pub const SAMPLE_USER: &str = "user";
+
+/// 样例类别
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleKind {
+    Path,
+    Registry,
+}
+
+impl fmt::Display for SampleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SampleKind::Path => "路径",
+            SampleKind::Registry => "注册表",
+        })
+    }
+}
+
+/// 单个样例的检查结果
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SampleOutcome {
+    pub kind: SampleKind,
+    pub sample: String,
+    /// 样例是否应当命中
+    pub expected: bool,
+    /// 命中该样例的规则条目
+    pub matched_by: Option<String>,
+}
+
+impl SampleOutcome {
+    pub fn passed(&self) -> bool {
+        self.expected == self.matched_by.is_some()
+    }
+}
+
+/// 检查规则的全部样例
+pub fn run_samples(rule: &Rule) -> Result<Vec<SampleOutcome>, PatternError> {
+    let mut outcomes = Vec::new();
+
+    let env = WindowsEnv::for_user(SAMPLE_USER);
+    let mut paths = Vec::new();
+    for path in &rule.matches.path {
+        let pattern = PathPattern::parse(path)?;
+        let expanded = env.expand(&pattern)?;
+        paths.push((pattern, expanded));
+    }
+
+    let tests = &rule.tests.path;
+    let path_samples = tests.matches.iter().map(|s| (true, s)).chain(tests.no_match.iter().map(|s| (false, s)));
+    for (expected, sample) in path_samples {
+        let expanded = expand_sample(&env, sample);
+        let matched_by = paths
+            .iter()
+            .find(|(pattern, expanded_pattern)| {
+                pattern.matches(sample)
+                    || expanded_pattern.as_ref().is_some_and(|p| p.matches(&expanded))
+            })
+            .map(|(pattern, _)| pattern.to_string());
+        outcomes.push(SampleOutcome { kind: SampleKind::Path, sample: sample.clone(), expected, matched_by });
+    }
+
+    let matchers = rule
+        .matches
+        .registry
+        .iter()
+        .map(|entry| Ok((entry, RegistryMatcher::new(entry)?)))
+        .collect::<Result<Vec<_>, PatternError>>()?;
+
+    let tests = &rule.tests.registry;
+    let registry_samples =
+        tests.matches.iter().map(|s| (true, s)).chain(tests.no_match.iter().map(|s| (false, s)));
+    for (expected, sample) in registry_samples {
+        let tree = sample_registry(sample);
+        let matched_by = matchers
+            .iter()
+            .find(|(_, matcher)| {
+                // 内存注册表不会返回错误
+                let targets = matcher.find(&tree).unwrap_or_default();
+                targets.iter().any(|target| covers(target, sample))
+            })
+            .map(|(entry, _)| format!("{}{} ({})", entry.path, entry.key, entry.action));
+        outcomes.push(SampleOutcome {
+            kind: SampleKind::Registry,
+            sample: sample.to_string(),
+            expected,
+            matched_by,
+        });
+    }
+
+    Ok(outcomes)
+}
+
+/// 展开样例的环境变量前缀，无法展开时原样返回
+fn expand_sample(env: &WindowsEnv, sample: &str) -> String {
+    PathPattern::parse(sample)
+        .ok()
+        .and_then(|pattern| env.expand(&pattern).ok().flatten())
+        .map(|pattern| pattern.to_string())
+        .unwrap_or_else(|| sample.to_string())
+}
+
+/// 只含一个样例的注册表
+fn sample_registry(sample: &RegistrySample) -> MemoryRegistry {
+    let mut tree = MemoryRegistry::new();
+    match sample {
+        RegistrySample::Key(key) => tree.insert_key(key),
+        RegistrySample::Value(value) => {
+            let data = value.data.clone().unwrap_or_default();
+            tree.set_value(&value.key, &value.value, RegistryData::String(data));
+        }
+    }
+    tree
+}
+
+/// 匹配结果是否作用到样例
+fn covers(target: &RegistryTarget, sample: &RegistrySample) -> bool {
+    let sample_key = normalize_key(sample.key());
+    match target {
+        RegistryTarget::Key { path } => {
+            let path = normalize_key(path);
+            sample_key == path || sample_key.starts_with(&format!("{}\\", path))
+        }
+        RegistryTarget::Value { key, name } | RegistryTarget::ValueData { key, name, .. } => {
+            match sample {
+                RegistrySample::Key(_) => false,
+                RegistrySample::Value(value) => {
+                    sample_key == normalize_key(key) && value.value.to_lowercase() == name.to_lowercase()
+                }
+            }
+        }
+    }
+}
+
+/// 根键统一为全称，去掉空段并转为小写
+fn normalize_key(path: &str) -> String {
+    path.split('\\')
+        .filter(|s| !s.is_empty())
+        .enumerate()
+        .map(|(depth, name)| if depth == 0 { canonical_root(name) } else { name })
+        .collect::<Vec<_>>()
+        .join("\\")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcomes(matches: &str, tests: &str) -> Vec<SampleOutcome> {
+        let yaml = format!("id: demo\nname: 演示\nrisk: high\nupdate: 2026-01-01\nmatch:\n{}tests:\n{}", matches, tests);
+        let rule: Rule = serde_yaml::from_str(&yaml).unwrap();
+        run_samples(&rule).unwrap()
+    }
+
+    /// (样例, 是否应命中, 命中的条目, 是否通过)
+    fn summary(outcomes: &[SampleOutcome]) -> Vec<(&str, bool, Option<&str>, bool)> {
+        outcomes.iter().map(|o| (o.sample.as_str(), o.expected, o.matched_by.as_deref(), o.passed())).collect()
+    }
+
+    #[test]
+    fn path_samples_compare_after_env_expansion() {
+        let outcomes = outcomes(
+            "  path:\n    - \"%TEMP%\\\\<SoftMgr.+>\"\n    - \"C:\\\\Program Files (x86)\\\\SoftMgr2\"\n",
+            "  path:\n    match:\n      - \"%LOCALAPPDATA%\\\\Temp\\\\SoftMgrSetup.exe\"\n      - \"%TEMP%\\\\SoftMgr2\"\n\
+             \x20     - \"C:\\\\program files (x86)\\\\softmgr2\"\n\
+             \x20     - \"%APPDATA%\\\\SoftMgr2\"\n\
+             \x20   no_match:\n      - \"%TEMP%\\\\SoftMgr\"\n      - \"%TEMP%\\\\SoftMgrX\"\n      - \"%NOPE%\\\\SoftMgr2\"\n",
+        );
+        assert!(outcomes.iter().all(|o| o.kind == SampleKind::Path));
+        assert_eq!(
+            summary(&outcomes),
+            [
+                (r"%LOCALAPPDATA%\Temp\SoftMgrSetup.exe", true, Some(r"%TEMP%\<SoftMgr.+>"), true),
+                (r"%TEMP%\SoftMgr2", true, Some(r"%TEMP%\<SoftMgr.+>"), true),
+                (r"C:\program files (x86)\softmgr2", true, Some(r"C:\Program Files (x86)\SoftMgr2"), true),
+                // 应命中而未命中
+                (r"%APPDATA%\SoftMgr2", true, None, false),
+                (r"%TEMP%\SoftMgr", false, None, true),
+                // 不应命中却命中
+                (r"%TEMP%\SoftMgrX", false, Some(r"%TEMP%\<SoftMgr.+>"), false),
+                // 未定义的变量按字面比较
+                (r"%NOPE%\SoftMgr2", false, None, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn registry_samples_follow_the_action() {
+        let outcomes = outcomes(
+            "  registry:\n\
+             \x20   - path: HKCR\\*\\shellex\\ContextMenuHandlers\\\n      key: SoftMgr*\n      action: delete_key\n\
+             \x20   - path: HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\\n      key: Run\n\
+             \x20     value: SoftMgr*\n      action: delete_value\n",
+            "  registry:\n    match:\n\
+             \x20     - \"HKEY_CLASSES_ROOT\\\\*\\\\shellex\\\\ContextMenuHandlers\\\\SoftMgrOEMExt\"\n\
+             \x20     - \"HKCR\\\\*\\\\shellex\\\\ContextMenuHandlers\\\\SoftMgrOEMExt\\\\Sub\"\n\
+             \x20     - key: \"HKCR\\\\*\\\\shellex\\\\ContextMenuHandlers\\\\SoftMgrOEMExt\"\n        value: \"\"\n\
+             \x20     - key: \"HKCU\\\\Software\\\\Microsoft\\\\Windows\\\\CurrentVersion\\\\Run\"\n\
+             \x20       value: SoftMgrTray\n        data: \"C:\\\\SoftMgr\\\\tray.exe\"\n\
+             \x20   no_match:\n\
+             \x20     - \"HKCR\\\\*\\\\shellex\\\\ContextMenuHandlers\\\\Other\"\n\
+             \x20     - \"HKCU\\\\Software\\\\Microsoft\\\\Windows\\\\CurrentVersion\\\\Run\"\n\
+             \x20     - key: \"HKCU\\\\Software\\\\Microsoft\\\\Windows\\\\CurrentVersion\\\\Run\"\n        value: OneDrive\n",
+        );
+        let delete_key = r"HKCR\*\shellex\ContextMenuHandlers\SoftMgr* (delete_key)";
+        let delete_value = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run (delete_value)";
+        assert!(outcomes.iter().all(|o| o.kind == SampleKind::Registry));
+        assert_eq!(
+            summary(&outcomes),
+            [
+                (r"HKEY_CLASSES_ROOT\*\shellex\ContextMenuHandlers\SoftMgrOEMExt", true, Some(delete_key), true),
+                // 位于被删除的键之下
+                (r"HKCR\*\shellex\ContextMenuHandlers\SoftMgrOEMExt\Sub", true, Some(delete_key), true),
+                (r"HKCR\*\shellex\ContextMenuHandlers\SoftMgrOEMExt -> (默认)", true, Some(delete_key), true),
+                (
+                    r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run -> SoftMgrTray = C:\SoftMgr\tray.exe",
+                    true,
+                    Some(delete_value),
+                    true
+                ),
+                (r"HKCR\*\shellex\ContextMenuHandlers\Other", false, None, true),
+                // 只删除值时键本身不算命中
+                (r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run", false, None, true),
+                (r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run -> OneDrive", false, None, true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! 签名密钥
+//! Ed25519 密钥的生成与读写
+//!
+//! 私钥使用 PKCS#8 PEM，公钥使用 SPKI PEM，与 `openssl genpkey -algorithm ed25519`
+//! 及 `openssl pkey -pubout` 生成的文件互通。
+
+use anyhow::{Context, Result};
+use ed25519_dalek::pkcs8::spki::der::pem::LineEnding;
+use ed25519_dalek::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey};
+use std::fs;
+use std::path::Path;
+
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+/// 生成新的签名私钥
+pub fn generate_key() -> SigningKey {
+    SigningKey::generate(&mut rand::rngs::OsRng)
+}
+
+/// 私钥的 PEM 文本
+pub fn signing_key_pem(key: &SigningKey) -> Result<String> {
+    Ok(key.to_pkcs8_pem(LineEnding::LF).context("私钥编码失败")?.to_string())
+}
+
+/// 公钥的 PEM 文本
+pub fn verifying_key_pem(key: &VerifyingKey) -> Result<String> {
+    key.to_public_key_pem(LineEnding::LF).context("公钥编码失败")
+}
+
+/// 读取私钥文件
+pub fn load_signing_key(path: &Path) -> Result<SigningKey> {
+    let pem = fs::read_to_string(path)
+        .with_context(|| format!("读取私钥失败: {}", path.display()))?;
+    SigningKey::from_pkcs8_pem(&pem)
+        .map_err(|e| anyhow::anyhow!("{}", e))
+        .with_context(|| format!("私钥格式错误（应为 Ed25519 PKCS#8 PEM）: {}", path.display()))
+}
+
+/// 读取公钥文件
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKey> {
+    let pem = fs::read_to_string(path)
+        .with_context(|| format!("读取公钥失败: {}", path.display()))?;
+    VerifyingKey::from_public_key_pem(&pem)
+        .map_err(|e| anyhow::anyhow!("{}", e))
+        .with_context(|| format!("公钥格式错误（应为 Ed25519 SPKI PEM）: {}", path.display()))
+}
+
+/// 公钥指纹（BLAKE3 摘要的前 8 字节，十六进制）
+pub fn fingerprint(key: &VerifyingKey) -> String {
+    hex(&blake3::hash(key.as_bytes()).as_bytes()[..8])
+}
+
+/// 摘要、公钥与签名的小写十六进制形式
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
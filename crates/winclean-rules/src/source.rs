@@ -0,0 +1,438 @@
+//! 规则源文件
+//! 按 `分类目录/*.yaml` 加载 YAML 规则，严格校验并收集全部诊断
+
+use crate::diagnostic::{Diagnostic, FieldPath, Issue, Severity};
+use crate::package::SerializedRule;
+use crate::rule::{RegistryAction, RiskLevel, Rule};
+use anyhow::Result;
+use glob::glob;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 已加载的规则文件
+#[derive(Debug, Clone)]
+pub struct RuleFile {
+    pub path: PathBuf,
+    pub content: String,
+    pub rule: Rule,
+}
+
+impl RuleFile {
+    /// 分类，取自所在目录名
+    pub fn category(&self) -> &str {
+        self.path.parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .unwrap_or("other")
+    }
+
+    /// 文件名
+    pub fn filename(&self) -> &str {
+        self.path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown.yaml")
+    }
+
+    /// 构建写入规则包的序列化规则
+    pub fn to_serialized(&self) -> SerializedRule {
+        SerializedRule::new(&self.rule, self.category(), self.filename(), &self.content)
+    }
+}
+
+/// 加载规则目录并按严格模式校验，收集全部诊断
+///
+/// 结构不符（未知字段、缺少字段、取值不合法）的文件仍会执行字段检查，一次报告全部问题，
+/// 但不出现在返回的规则中；其余文件即使有校验错误也会返回。
+pub fn load_rules(input: &Path) -> Result<(Vec<RuleFile>, Vec<Diagnostic>)> {
+    let mut rules = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut seen_ids: HashMap<String, PathBuf> = HashMap::new();
+
+    for path in collect_rule_files(input)? {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                diagnostics.push(Diagnostic::new(Severity::Error, &path, format!("读取失败: {}", e)));
+                continue;
+            }
+        };
+
+        let Some(rule) = parse_rule(&path, &content, &mut diagnostics) else { continue };
+
+        if let Some(first) = seen_ids.get(&rule.id) {
+            let issue = Issue::error(
+                FieldPath::default().key("id"),
+                format!("id `{}` 与 {} 重复", rule.id, first.display()),
+            );
+            diagnostics.push(Diagnostic::from_issue(&path, &content, &issue));
+        } else {
+            seen_ids.insert(rule.id.clone(), path.clone());
+        }
+
+        rules.push(RuleFile { path, content, rule });
+    }
+
+    Ok((rules, diagnostics))
+}
+
+/// 解析并校验单个规则文件，诊断追加到 `diagnostics`
+///
+/// 严格解析失败时按字段表重新检查，返回 `None`。
+fn parse_rule(path: &Path, content: &str, diagnostics: &mut Vec<Diagnostic>) -> Option<Rule> {
+    let error = match serde_yaml::from_str::<Rule>(content) {
+        Ok(rule) => {
+            for issue in rule.validate() {
+                diagnostics.push(Diagnostic::from_issue(path, content, &issue));
+            }
+            return Some(rule);
+        }
+        Err(e) => e,
+    };
+
+    // YAML语法错误或顶层不是映射时只能报告解析错误
+    let mut value: Value = match serde_yaml::from_str(content) {
+        Ok(value) => value,
+        Err(_) => {
+            diagnostics.push(Diagnostic::from_yaml_error(path, content, &error));
+            return None;
+        }
+    };
+    let Value::Mapping(map) = &mut value else {
+        diagnostics.push(Diagnostic::from_yaml_error(path, content, &error));
+        return None;
+    };
+
+    let mut check = StructureCheck::default();
+    check_mapping(map, RULE_FIELDS, &FieldPath::default(), &mut check);
+    if check.issues.is_empty() {
+        // 字段表未覆盖的情况，保留原始解析错误
+        diagnostics.push(Diagnostic::from_yaml_error(path, content, &error));
+    }
+
+    // 修正后的结构可以反序列化，继续执行字段检查；已报告结构错误的字段及其上下级不再重复报告
+    let mut issues = check.issues;
+    if let Ok(rule) = serde_yaml::from_value::<Rule>(value) {
+        let overlaps = |issue: &Issue| {
+            check.broken.iter().any(|field| issue.field.0.starts_with(&field.0) || field.0.starts_with(&issue.field.0))
+        };
+        issues.extend(rule.validate().into_iter().filter(|issue| !overlaps(issue)));
+    }
+    for issue in &issues {
+        diagnostics.push(Diagnostic::from_issue(path, content, issue));
+    }
+    None
+}
+
+/// 字段的取值形态
+enum Shape {
+    /// 字符串，其他标量按原文当作字符串
+    Str,
+    /// 取值固定的字符串
+    Enum(&'static [&'static str]),
+    List(&'static Shape),
+    Map(&'static [Field]),
+    /// 字符串或映射（注册表样例）
+    StrOrMap(&'static [Field]),
+}
+
+/// 字段表中的一项
+struct Field {
+    name: &'static str,
+    required: bool,
+    shape: Shape,
+}
+
+const fn required(name: &'static str, shape: Shape) -> Field {
+    Field { name, required: true, shape }
+}
+
+const fn optional(name: &'static str, shape: Shape) -> Field {
+    Field { name, required: false, shape }
+}
+
+// 与 rule.rs 中的结构一一对应
+const RULE_FIELDS: &[Field] = &[
+    required("id", Shape::Str),
+    required("name", Shape::Str),
+    required("risk", Shape::Enum(&[RiskLevel::High.as_str(), RiskLevel::Default.as_str()])),
+    optional("systeminfo", Shape::List(&Shape::Str)),
+    required("update", Shape::Str),
+    optional("author", Shape::Str),
+    optional("description", Shape::Str),
+    optional("match", Shape::Map(MATCH_FIELDS)),
+    optional("tests", Shape::Map(TESTS_FIELDS)),
+];
+
+const MATCH_FIELDS: &[Field] = &[
+    optional("path", Shape::List(&Shape::Str)),
+    optional("registry", Shape::List(&Shape::Map(REGISTRY_FIELDS))),
+];
+
+const REGISTRY_FIELDS: &[Field] = &[
+    required("path", Shape::Str),
+    required("key", Shape::Str),
+    optional("value", Shape::Str),
+    optional("value_data", Shape::Str),
+    required(
+        "action",
+        Shape::Enum(&[
+            RegistryAction::DeleteKey.as_str(),
+            RegistryAction::DeleteValue.as_str(),
+            RegistryAction::DeleteValueData.as_str(),
+        ]),
+    ),
+];
+
+const TESTS_FIELDS: &[Field] = &[
+    optional("path", Shape::Map(PATH_SAMPLE_FIELDS)),
+    optional("registry", Shape::Map(REGISTRY_SAMPLE_FIELDS)),
+];
+
+const PATH_SAMPLE_FIELDS: &[Field] = &[
+    optional("match", Shape::List(&Shape::Str)),
+    optional("no_match", Shape::List(&Shape::Str)),
+];
+
+const REGISTRY_SAMPLE_FIELDS: &[Field] = &[
+    optional("match", Shape::List(&Shape::StrOrMap(REGISTRY_VALUE_SAMPLE_FIELDS))),
+    optional("no_match", Shape::List(&Shape::StrOrMap(REGISTRY_VALUE_SAMPLE_FIELDS))),
+];
+
+const REGISTRY_VALUE_SAMPLE_FIELDS: &[Field] = &[
+    required("key", Shape::Str),
+    required("value", Shape::Str),
+    optional("data", Shape::Str),
+];
+
+impl Shape {
+    fn expected(&self) -> &'static str {
+        match self {
+            Shape::Str | Shape::Enum(_) => "字符串",
+            Shape::List(_) => "列表",
+            Shape::Map(_) => "映射",
+            Shape::StrOrMap(_) => "字符串或映射",
+        }
+    }
+
+    /// 出错字段的替代值，保证修正后的结构可以反序列化
+    fn placeholder(&self) -> Value {
+        match self {
+            Shape::Str | Shape::StrOrMap(_) => Value::String(String::new()),
+            Shape::Enum(values) => Value::String(values[0].to_string()),
+            Shape::List(_) => Value::Sequence(Vec::new()),
+            Shape::Map(_) => Value::Mapping(Mapping::new()),
+        }
+    }
+}
+
+/// 结构检查结果
+#[derive(Default)]
+struct StructureCheck {
+    issues: Vec<Issue>,
+    /// 已替换为占位值的字段
+    broken: Vec<FieldPath>,
+}
+
+impl StructureCheck {
+    fn replace(&mut self, value: &mut Value, shape: &Shape, field: FieldPath, message: String) {
+        *value = shape.placeholder();
+        self.issues.push(Issue::error(field.clone(), message));
+        self.broken.push(field);
+    }
+}
+
+/// 按字段表检查映射，移除未知字段、补齐缺少的字段
+fn check_mapping(map: &mut Mapping, fields: &'static [Field], path: &FieldPath, check: &mut StructureCheck) {
+    let unknown: Vec<Value> = map
+        .keys()
+        .filter(|key| !key.as_str().is_some_and(|key| fields.iter().any(|f| f.name == key)))
+        .cloned()
+        .collect();
+    for key in unknown {
+        map.remove(&key);
+        let name = scalar_text(&key).unwrap_or_else(|| "?".to_string());
+        let field = path.clone().key(name);
+        check.issues.push(Issue::error(field.clone(), format!("未知字段 `{}`", field)));
+    }
+
+    for spec in fields {
+        let field = path.clone().key(spec.name);
+        match map.get_mut(spec.name) {
+            Some(value) => check_value(value, &spec.shape, spec.required, field, check),
+            None if spec.required => {
+                check.issues.push(Issue::error(path.clone(), format!("缺少字段 `{}`", field)));
+                map.insert(Value::from(spec.name), spec.shape.placeholder());
+                check.broken.push(field);
+            }
+            None => {}
+        }
+    }
+}
+
+/// 检查字段取值的形态，不符时替换为占位值
+fn check_value(value: &mut Value, shape: &Shape, required: bool, field: FieldPath, check: &mut StructureCheck) {
+    if let Shape::Enum(values) = shape {
+        match scalar_text(value) {
+            Some(text) if values.contains(&text.as_str()) => {}
+            Some(text) => {
+                let message = format!("{} `{}` 不合法: 可选值为 {}", field, text, values.join("、"));
+                check.replace(value, shape, field, message);
+            }
+            None => {
+                let message = format!("{} 应为{}", field, shape.expected());
+                check.replace(value, shape, field, message);
+            }
+        }
+        return;
+    }
+
+    match (shape, &mut *value) {
+        // 与严格解析一致：可选字符串写 `~` 视为未填写，其他标量按原文当作字符串
+        (Shape::Str, Value::Null) if !required => {}
+        (Shape::Str, Value::String(_)) => {}
+        (Shape::Str, Value::Null | Value::Bool(_) | Value::Number(_)) => {
+            *value = Value::String(scalar_text(value).unwrap_or_default());
+        }
+        (Shape::List(item), Value::Sequence(items)) => {
+            for (i, item_value) in items.iter_mut().enumerate() {
+                check_value(item_value, item, true, field.clone().index(i), check);
+            }
+        }
+        (Shape::Map(fields) | Shape::StrOrMap(fields), Value::Mapping(map)) => {
+            check_mapping(map, fields, &field, check);
+        }
+        (Shape::StrOrMap(_), Value::String(_)) => {}
+        _ => {
+            let message = format!("{} 应为{}", field, shape.expected());
+            check.replace(value, shape, field, message);
+        }
+    }
+}
+
+/// 标量的原文，非标量返回 `None`
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::new()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// 收集规则文件（分类目录/*.yaml），按路径排序
+pub fn collect_rule_files(input: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    let pattern = format!("{}/*/*.yaml", input.display());
+    for entry in glob(&pattern)? {
+        let path = entry?;
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    // 不依赖文件系统的遍历顺序
+    files.sort();
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> (Option<Rule>, Vec<Diagnostic>) {
+        let mut diagnostics = Vec::new();
+        let rule = parse_rule(Path::new("rules/demo/demo.yaml"), content, &mut diagnostics);
+        (rule, diagnostics)
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<(Severity, Option<usize>, &str)> {
+        diagnostics.iter().map(|d| (d.severity, d.span.map(|s| s.line), d.message.as_str())).collect()
+    }
+
+    #[test]
+    fn valid_rule_parses() {
+        let (rule, diagnostics) =
+            parse("id: demo\nname: 演示\nrisk: high\nupdate: 2026-01-01\nmatch:\n  path:\n    - \"%TEMP%\\\\demo\"\n");
+        assert_eq!(rule.unwrap().id, "demo");
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn unknown_field_does_not_hide_field_checks() {
+        let (rule, diagnostics) = parse("id: Bad-Id\nname: 演示\nrisk: high\nupdate: 2026-13-01\nextra: 1\n");
+        assert!(rule.is_none());
+        assert_eq!(
+            messages(&diagnostics),
+            [
+                (Severity::Error, Some(5), "未知字段 `extra`"),
+                (Severity::Error, Some(1), "id `Bad-Id` 不合法: 只能包含小写字母、数字和下划线"),
+                (Severity::Error, Some(4), "update `2026-13-01` 不合法: 日期格式应为 YYYY-MM-DD"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_every_missing_field_and_bad_enum() {
+        let content = "\
+id: demo
+update: 2026-01-01
+match:
+  registry:
+    - path: \"HKCU\\\\Software\"
+      action: delete
+";
+        let (rule, diagnostics) = parse(content);
+        assert!(rule.is_none());
+        assert_eq!(
+            messages(&diagnostics),
+            [
+                (Severity::Error, None, "缺少字段 `name`"),
+                (Severity::Error, None, "缺少字段 `risk`"),
+                (Severity::Error, Some(5), "缺少字段 `match.registry[0].key`"),
+                (
+                    Severity::Error,
+                    Some(6),
+                    "match.registry[0].action `delete` 不合法: 可选值为 delete_key、delete_value、delete_value_data",
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_types_are_reported_once() {
+        let content = "\
+id: demo
+name: [a, b]
+risk: default
+update: 2026-01-01
+systeminfo: win11_x64
+tests:
+  registry:
+    match:
+      - [HKCU]
+      - value: x
+";
+        let (_, diagnostics) = parse(content);
+        assert_eq!(
+            messages(&diagnostics),
+            [
+                (Severity::Error, Some(2), "name 应为字符串"),
+                (Severity::Error, Some(5), "systeminfo 应为列表"),
+                (Severity::Error, Some(9), "tests.registry.match[0] 应为字符串或映射"),
+                (Severity::Error, Some(10), "缺少字段 `tests.registry.match[1].key`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_error_keeps_parser_message() {
+        let (rule, diagnostics) = parse("id: demo\nname: [unclosed\n");
+        assert!(rule.is_none());
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].span.is_some());
+    }
+}
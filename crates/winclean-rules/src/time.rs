@@ -0,0 +1,125 @@
+//! 时间
+//! UTC 时间与 Unix 时间戳（秒）之间的转换，不依赖时区数据
+
+/// 解析 UTC 时间，返回 Unix 时间戳（秒）
+pub fn parse_time(text: &str) -> Option<u64> {
+    let text = text.trim().strip_suffix('Z').unwrap_or(text.trim());
+    let (date, time) = text.split_once(['T', ' '])?;
+
+    let date: Vec<&str> = date.split('-').collect();
+    let time: Vec<&str> = time.split(':').collect();
+    let digits = |s: &str, len: usize| s.len() == len && s.chars().all(|c| c.is_ascii_digit());
+    if date.len() != 3 || time.len() != 3 {
+        return None;
+    }
+    if !digits(date[0], 4) || !date[1..].iter().chain(&time).all(|s| digits(s, 2)) {
+        return None;
+    }
+
+    let year: i64 = date[0].parse().ok()?;
+    let month: u32 = date[1].parse().ok()?;
+    let day: u32 = date[2].parse().ok()?;
+    let (hour, minute, second): (u64, u64, u64) =
+        (time[0].parse().ok()?, time[1].parse().ok()?, time[2].parse().ok()?);
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// 将 Unix 时间戳格式化为 `YYYY-MM-DDTHH:MM:SSZ`
+pub fn format_time(timestamp: u64) -> String {
+    let (year, month, day) = civil_date(timestamp);
+    let seconds = timestamp % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// 可表示的最晚时间 9999-12-31T23:59:59Z，更晚的时间无法按 `YYYY-MM-DD` 格式化后再解析
+pub const MAX_TIME: u64 = 253_402_300_799;
+
+/// 若干天后的时间，溢出或晚于 [`MAX_TIME`] 时返回 `None`
+pub fn add_days(timestamp: u64, days: u64) -> Option<u64> {
+    days.checked_mul(86400)?.checked_add(timestamp).filter(|&time| time <= MAX_TIME)
+}
+
+/// Unix 时间戳对应的 UTC 日期（年、月、日）
+pub fn civil_date(timestamp: u64) -> (i64, u32, u32) {
+    civil_from_days((timestamp / 86400) as i64)
+}
+
+/// 某月的天数（月份须在 1..=12 之内）
+pub(crate) fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// 公历日期距 1970-01-01 的天数
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// 距 1970-01-01 的天数对应的公历日期
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_utc_time() {
+        assert_eq!(parse_time("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_time("2023-11-14 22:13:20"), Some(1_700_000_000));
+        assert_eq!(parse_time("2024-02-29T12:00:00Z"), Some(1_709_208_000));
+        assert_eq!(format_time(1_709_208_000), "2024-02-29T12:00:00Z");
+        assert_eq!(format_time(MAX_TIME), "9999-12-31T23:59:59Z");
+        assert_eq!(parse_time(&format_time(MAX_TIME)), Some(MAX_TIME));
+
+        let invalid = ["2023-02-29T00:00:00Z", "2023-13-01T00:00:00Z", "2023-01-01T24:00:00Z", "2023-1-01T00:00:00Z"];
+        for text in invalid.into_iter().chain(["2023-01-01", ""]) {
+            assert_eq!(parse_time(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn add_days_rejects_overflow() {
+        assert_eq!(add_days(1_700_000_000, 1), Some(1_700_086_400));
+        assert_eq!(add_days(MAX_TIME, 0), Some(MAX_TIME));
+        assert_eq!(add_days(MAX_TIME - 86399, 1), None);
+        assert_eq!(add_days(0, u64::MAX / 86400 + 1), None);
+        assert_eq!(add_days(u64::MAX, 1), None);
+    }
+}